@@ -0,0 +1,367 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized, parse_macro_input, Attribute, Data, DeriveInput, Fields, GenericArgument,
+    LitStr, PathArguments, Type,
+};
+
+/// Derive `copepod_sdk::schema::CopepodRecord` for a struct with named fields.
+///
+/// Container attributes:
+/// - `#[copepod(collection = "notes")]` (required): the collection name.
+///
+/// Field attributes:
+/// - `#[copepod(rename = "name")]`: record field name (defaults to the serde
+///   name, then the Rust name).
+/// - `#[copepod(type = "email")]`: override the inferred field type.
+/// - `#[copepod(relation = "users")]`: relation to the named collection.
+/// - `#[copepod(file)]`: file field.
+/// - `#[copepod(unique)]`: mark the field unique.
+/// - `#[copepod(optional)]`: mark the field as not required.
+/// - `#[copepod(skip)]`: leave the field out of the schema.
+///
+/// `RelationId<T>` and `Relation<T>` fields (or `Vec`s of them) are relations
+/// to the collection of `T`. `Option<T>` and `#[serde(default)]` fields are
+/// not required. The system fields `id`, `created` and `updated` are always
+/// left out.
+///
+/// Serde's `rename`, `rename_all`, `default` and `skip` attributes are
+/// followed, so field names match what serde sends; `#[serde(flatten)]`
+/// fields are rejected.
+#[proc_macro_derive(CopepodRecord, attributes(copepod))]
+pub fn derive_copepod_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+const SYSTEM_FIELDS: &[&str] = &["id", "created", "updated"];
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    field_type: Option<String>,
+    relation: Option<String>,
+    file: bool,
+    unique: bool,
+    optional: bool,
+    skip: bool,
+    serde_default: bool,
+    flatten: bool,
+}
+
+/// A serde `rename_all` rule, applied to snake_case Rust field names.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "unknown serde rename_all rule",
+                ))
+            }
+        })
+    }
+
+    /// Rename a field the way serde does.
+    fn apply(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal | Self::Camel => {
+                let mut out = String::new();
+                let mut capitalize = matches!(self, Self::Pascal);
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        out.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (rename_all, container_default) = container_serde(&input.attrs)?;
+    let collection = container_collection(&input.attrs)?.ok_or_else(|| {
+        syn::Error::new_spanned(
+            ident,
+            "missing `#[copepod(collection = \"...\")]` attribute",
+        )
+    })?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "CopepodRecord requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "CopepodRecord can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut definitions = Vec::new();
+    let mut constants = Vec::new();
+    for field in fields {
+        let attrs = field_attrs(&field.attrs)?;
+        if attrs.flatten {
+            return Err(syn::Error::new_spanned(
+                field,
+                "CopepodRecord does not support `#[serde(flatten)]` fields",
+            ));
+        }
+        let rust_name = field.ident.as_ref().expect("named field").to_string();
+        let rust_name = rust_name.trim_start_matches("r#");
+        let name = attrs.rename.clone().unwrap_or_else(|| match rename_all {
+            Some(rule) => rule.apply(rust_name),
+            None => rust_name.to_string(),
+        });
+        if attrs.skip || SYSTEM_FIELDS.contains(&name.as_str()) {
+            continue;
+        }
+
+        let const_ident = format_ident!("{}", rust_name.to_uppercase());
+        let doc = format!("Record field name of `{rust_name}`.");
+        constants.push(quote! {
+            #[doc = #doc]
+            #vis const #const_ident: &'static str = #name;
+        });
+
+        let (ty, is_option) = match generic_arg(&field.ty, "Option") {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+        let required = !is_option && !attrs.optional && !attrs.serde_default && !container_default;
+        let unique = attrs.unique;
+        let (element, multiple) = match generic_arg(ty, "Vec") {
+            Some(inner) => (inner, true),
+            None => (ty, false),
+        };
+
+        let definition = if let Some(target) = &attrs.relation {
+            quote! {
+                ::copepod_sdk::schema::__private::relation_field(
+                    #name, #target, #multiple, #required, #unique,
+                )
+            }
+        } else if let Some(target) =
+            generic_arg(element, "RelationId").or_else(|| generic_arg(element, "Relation"))
+        {
+            quote! {
+                ::copepod_sdk::schema::__private::relation_field(
+                    #name,
+                    <#target as ::copepod_sdk::schema::CopepodRecord>::COLLECTION,
+                    #multiple,
+                    #required,
+                    #unique,
+                )
+            }
+        } else if attrs.file || type_name(element).as_deref() == Some("FileName") {
+            quote! {
+                ::copepod_sdk::schema::__private::file_field(#name, #multiple, #required, #unique)
+            }
+        } else {
+            let field_type = attrs
+                .field_type
+                .clone()
+                .unwrap_or_else(|| infer_type(ty).to_string());
+            quote! {
+                ::copepod_sdk::schema::__private::field(#name, #field_type, #required, #unique)
+            }
+        };
+        definitions.push(definition);
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::copepod_sdk::schema::CopepodRecord for #ident #ty_generics #where_clause {
+            const COLLECTION: &'static str = #collection;
+
+            fn fields() -> ::std::vec::Vec<::copepod_sdk::models::CollectionField> {
+                ::std::vec![#(#definitions),*]
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#constants)*
+        }
+    })
+}
+
+fn container_collection(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut collection = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("copepod")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("collection") {
+                collection = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported copepod container attribute"))
+            }
+        })?;
+    }
+    Ok(collection)
+}
+
+/// Read the container's serde `rename_all` rule and whether it has
+/// `#[serde(default)]`.
+fn container_serde(attrs: &[Attribute]) -> syn::Result<(Option<RenameRule>, bool)> {
+    let mut rename_all = None;
+    let mut default = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                if !meta.input.peek(syn::Token![=]) {
+                    return Err(
+                        meta.error("CopepodRecord only supports `#[serde(rename_all = \"...\")]`")
+                    );
+                }
+                rename_all = Some(RenameRule::parse(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("default") {
+                default = true;
+                skip_meta_value(&meta)?;
+            } else {
+                skip_meta_value(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok((rename_all, default))
+}
+
+fn field_attrs(attrs: &[Attribute]) -> syn::Result<FieldAttrs> {
+    let mut out = FieldAttrs::default();
+    for attr in attrs {
+        if attr.path().is_ident("copepod") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("type") {
+                    out.field_type = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("relation") {
+                    out.relation = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("file") {
+                    out.file = true;
+                } else if meta.path.is_ident("unique") {
+                    out.unique = true;
+                } else if meta.path.is_ident("optional") {
+                    out.optional = true;
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else {
+                    return Err(meta.error("unsupported copepod field attribute"));
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("serde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                    let name = meta.value()?.parse::<LitStr>()?.value();
+                    out.rename.get_or_insert(name);
+                } else if meta.path.is_ident("default") {
+                    out.serde_default = true;
+                    skip_meta_value(&meta)?;
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else {
+                    skip_meta_value(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(out)
+}
+
+/// Consume `= value` or `(...)` after a serde attribute we don't interpret.
+fn skip_meta_value(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in meta.input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
+
+/// Name of the last path segment of a type, e.g. `DateTime` for `chrono::DateTime<Utc>`.
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        Type::Reference(reference) => type_name(&reference.elem),
+        _ => None,
+    }
+}
+
+/// If `ty` is `wrapper<T>`, return `T`.
+fn generic_arg<'t>(ty: &'t Type, wrapper: &str) -> Option<&'t Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+fn infer_type(ty: &Type) -> &'static str {
+    match type_name(ty).as_deref() {
+        Some("String" | "str" | "char") => "text",
+        Some("bool") => "bool",
+        Some(
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize" | "f32" | "f64",
+        ) => "number",
+        Some("DateTime" | "NaiveDate" | "NaiveDateTime") => "date",
+        _ => "json",
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use copepod_sdk::schema::{FileName, RelationId};
+use copepod_sdk::{CopepodClient, CopepodRecord, FieldType};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize, CopepodRecord)]
+#[copepod(collection = "users")]
+struct User {
+    #[copepod(type = "email", unique)]
+    email: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, CopepodRecord)]
+#[copepod(collection = "notes")]
+struct Note {
+    id: String,
+    title: String,
+    #[serde(rename = "body_text", default)]
+    body: String,
+    views: u32,
+    published_at: Option<DateTime<Utc>>,
+    author: RelationId<User>,
+    tags: Vec<RelationId<User>>,
+    attachment: Option<FileName>,
+    #[copepod(relation = "categories")]
+    category: String,
+    meta: serde_json::Value,
+    #[copepod(skip)]
+    local_only: bool,
+}
+
+#[test]
+fn derives_collection_name_and_field_constants() {
+    assert_eq!(Note::COLLECTION, "notes");
+    assert_eq!(Note::TITLE, "title");
+    assert_eq!(Note::BODY, "body_text");
+    assert_eq!(User::EMAIL, "email");
+}
+
+#[test]
+fn derives_field_schema() {
+    let fields = serde_json::to_value(Note::fields()).unwrap();
+    assert_eq!(
+        fields,
+        json!([
+            { "name": "title", "type": "text", "required": true, "options": null },
+            { "name": "body_text", "type": "text", "required": false, "options": null },
+            { "name": "views", "type": "number", "required": true, "options": null },
+            { "name": "published_at", "type": "date", "required": false, "options": null },
+            {
+                "name": "author", "type": "relation", "required": true,
+                "options": { "collection": "users", "max_select": 1 }
+            },
+            {
+                "name": "tags", "type": "relation", "required": true,
+                "options": { "collection": "users" }
+            },
+            {
+                "name": "attachment", "type": "file", "required": false,
+                "options": { "max_files": 1 }
+            },
+            {
+                "name": "category", "type": "relation", "required": true,
+                "options": { "collection": "categories", "max_select": 1 }
+            },
+            { "name": "meta", "type": "json", "required": true, "options": null }
+        ])
+    );
+
+    let users = User::schema();
+    assert_eq!(users.name, "users");
+    assert_eq!(users.fields[0].field_type, FieldType::Email);
+    assert!(users.fields[0].unique);
+    assert!(!users.fields[1].required);
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, CopepodRecord)]
+#[copepod(collection = "profiles")]
+#[serde(rename_all = "camelCase", default)]
+struct Profile {
+    display_name: String,
+    #[serde(rename = "avatar")]
+    avatar_url: String,
+    view_count: u32,
+}
+
+#[test]
+fn follows_serde_rename_all_and_container_default() {
+    assert_eq!(Profile::DISPLAY_NAME, "displayName");
+    assert_eq!(Profile::AVATAR_URL, "avatar");
+    assert_eq!(Profile::VIEW_COUNT, "viewCount");
+
+    let wire = serde_json::to_value(Profile::default()).unwrap();
+    let fields = Profile::fields();
+    let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["displayName", "avatar", "viewCount"]);
+    for name in names {
+        assert!(wire.get(name).is_some(), "serde does not send `{name}`");
+    }
+    assert!(fields.iter().all(|f| !f.required));
+}
+
+#[test]
+fn typed_accessor_uses_bound_collection() {
+    let client = CopepodClient::builder()
+        .base_url("http://localhost:8090")
+        .build()
+        .unwrap();
+    let notes = client.app("o1", "a1").typed::<Note>();
+    assert_eq!(notes.collection(), "notes");
+}
+
+#[derive(Debug, Serialize, Deserialize, CopepodRecord)]
+#[copepod(collection = "comments")]
+struct Comment {
+    author: copepod_sdk::Relation<User>,
+    likes: Vec<copepod_sdk::Relation<User>>,
+}
+
+#[test]
+fn expandable_relations_are_relation_fields() {
+    let fields = serde_json::to_value(Comment::fields()).unwrap();
+    assert_eq!(fields[0]["type"], "relation");
+    assert_eq!(
+        fields[0]["options"],
+        json!({ "collection": "users", "max_select": 1 })
+    );
+    assert_eq!(fields[1]["options"], json!({ "collection": "users" }));
+}
@@ -34,11 +34,10 @@ impl CopepodClient {
         let resp: AuthResponse =
             serde_json::from_value(raw).map_err(crate::error::CopepodError::Deserialize)?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set_refreshed_by(
+                TokenPair::new(resp.token.clone(), resp.refresh_token.clone()),
+                app_refresh_path(org_id, app_id, collection),
+            )
             .await;
         Ok(AppLoginResult::Success(resp))
     }
@@ -71,18 +70,14 @@ impl CopepodClient {
             .await
             .ok_or_else(|| crate::error::CopepodError::Auth("No token to refresh".into()))?;
 
-        let path = format!(
-            "api/platform/orgs/{}/apps/{}/auth/{}/auth-refresh",
-            org_id, app_id, collection
-        );
+        let path = app_refresh_path(org_id, app_id, collection);
         let body = serde_json::json!({ "refresh_token": pair.refresh_token });
         let resp: AuthResponse = self.post(&path, &body).await?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set_refreshed_by(
+                TokenPair::new(resp.token.clone(), resp.refresh_token.clone()),
+                path,
+            )
             .await;
         Ok(resp)
     }
@@ -281,3 +276,8 @@ impl CopepodClient {
         self.post(&path, &body).await
     }
 }
+
+/// The endpoint refreshing app user tokens of `collection`.
+fn app_refresh_path(org_id: &str, app_id: &str, collection: &str) -> String {
+    format!("api/platform/orgs/{org_id}/apps/{app_id}/auth/{collection}/auth-refresh")
+}
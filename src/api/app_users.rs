@@ -3,6 +3,7 @@ use serde_json::Value;
 use crate::client::CopepodClient;
 use crate::error::Result;
 use crate::models::{ListResult, User};
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     /// List users of an app.
@@ -14,6 +15,15 @@ impl CopepodClient {
         .await
     }
 
+    /// Paginate over every user of an app.
+    pub fn paginate_app_users<'a>(&'a self, org_id: &str, app_id: &str) -> Paginator<'a, User> {
+        let path = format!("api/platform/orgs/{}/apps/{}/users", org_id, app_id);
+        Paginator::new(move |page, per_page| {
+            let path = path.clone();
+            async move { self.get_page(&path, page, per_page).await }
+        })
+    }
+
     /// Get an app user by ID.
     pub async fn get_app_user(&self, org_id: &str, app_id: &str, user_id: &str) -> Result<User> {
         self.get(&format!(
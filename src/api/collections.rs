@@ -1,6 +1,7 @@
 use crate::client::CopepodClient;
 use crate::error::Result;
 use crate::models::{Collection, ListResult};
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     /// List all collections in an app.
@@ -16,6 +17,19 @@ impl CopepodClient {
         .await
     }
 
+    /// Paginate over every collection in an app.
+    pub fn paginate_collections<'a>(
+        &'a self,
+        org_id: &str,
+        app_id: &str,
+    ) -> Paginator<'a, Collection> {
+        let path = format!("api/platform/orgs/{}/apps/{}/collections", org_id, app_id);
+        Paginator::new(move |page, per_page| {
+            let path = path.clone();
+            async move { self.get_page(&path, page, per_page).await }
+        })
+    }
+
     /// Get a collection by ID or name.
     pub async fn get_collection(
         &self,
@@ -0,0 +1,430 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use eventsource_stream::{Event, Eventsource};
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use reqwest::{Method, StatusCode};
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+use crate::models::{DeploymentLogLine, DeploymentLogs};
+
+/// How many of the latest lines, with or without timestamps, are remembered
+/// to find where a polled snapshot continues them.
+const REMEMBERED_LINES: usize = 1000;
+
+/// Options controlling [`CopepodClient::follow_deployment_logs`].
+#[derive(Debug, Clone)]
+pub struct LogFollowOptions {
+    since: Option<DateTime<Utc>>,
+    tail: Option<u32>,
+    poll_interval: Duration,
+}
+
+impl Default for LogFollowOptions {
+    fn default() -> Self {
+        Self {
+            since: None,
+            tail: None,
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl LogFollowOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from lines written at or after `since` (default: the tail of
+    /// the current logs).
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Set how many existing lines to start with (default: the server's
+    /// default, 200 lines).
+    pub fn tail(mut self, lines: u32) -> Self {
+        self.tail = Some(lines);
+        self
+    }
+
+    /// Set how often logs are polled when the server does not stream them
+    /// (default: 2s).
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+type LogEvents = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
+
+enum Mode {
+    Start,
+    Streaming(LogEvents),
+    Polling { wait: bool },
+    Done,
+}
+
+/// The state of a followed log.
+struct Follow {
+    client: CopepodClient,
+    path: String,
+    options: LogFollowOptions,
+    cursor: LogCursor,
+    mode: Mode,
+    pending: VecDeque<DeploymentLogLine>,
+}
+
+impl Follow {
+    async fn next(&mut self) -> Option<Result<DeploymentLogLine>> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return Some(Ok(line));
+            }
+            match &mut self.mode {
+                Mode::Start => match self.open().await {
+                    Ok(mode) => self.mode = mode,
+                    Err(e) => {
+                        self.mode = Mode::Done;
+                        return Some(Err(e));
+                    }
+                },
+                Mode::Streaming(events) => match events.next().await {
+                    Some(Ok(event)) => {
+                        for line in parse_event(&event) {
+                            self.cursor.record(&line);
+                            self.pending.push_back(line);
+                        }
+                    }
+                    // The stream dropped: carry on by polling from the last
+                    // line received.
+                    Some(Err(e)) => {
+                        tracing::debug!("deployment log stream failed, polling instead: {e}");
+                        self.mode = Mode::Polling { wait: false };
+                    }
+                    None => self.mode = Mode::Polling { wait: false },
+                },
+                Mode::Polling { wait } => {
+                    if *wait {
+                        tokio::time::sleep(self.options.poll_interval).await;
+                    }
+                    self.mode = Mode::Polling { wait: true };
+                    match self.poll().await {
+                        Ok(lines) => self.pending.extend(self.cursor.fresh(lines)),
+                        Err(e) => {
+                            if !e.is_transient() {
+                                self.mode = Mode::Done;
+                            }
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                Mode::Done => return None,
+            }
+        }
+    }
+
+    /// Ask the server to stream the logs, falling back to polling when it
+    /// answers with a snapshot or does not support following.
+    async fn open(&mut self) -> Result<Mode> {
+        let mut query = self.query();
+        query.push(("follow", "true".to_string()));
+        let resp = self
+            .client
+            .auth_request(Method::GET, &self.path)
+            .await?
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .query(&query)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if matches!(
+            status,
+            StatusCode::NOT_FOUND
+                | StatusCode::METHOD_NOT_ALLOWED
+                | StatusCode::NOT_ACCEPTABLE
+                | StatusCode::NOT_IMPLEMENTED
+        ) {
+            return Ok(Mode::Polling { wait: false });
+        }
+        let streaming = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if status.is_success() && streaming {
+            let events = resp
+                .bytes_stream()
+                .map_err(std::io::Error::other)
+                .eventsource()
+                .map_err(|e| CopepodError::Sse(e.to_string()));
+            return Ok(Mode::Streaming(Box::pin(events)));
+        }
+
+        let logs: DeploymentLogs = CopepodClient::handle_response_pub(resp).await?;
+        let lines = logs.lines.iter().map(|line| parse_line(line)).collect();
+        self.pending.extend(self.cursor.fresh(lines));
+        Ok(Mode::Polling { wait: true })
+    }
+
+    async fn poll(&mut self) -> Result<Vec<DeploymentLogLine>> {
+        let logs: DeploymentLogs = CopepodClient::handle_response_pub(
+            self.client
+                .auth_request(Method::GET, &self.path)
+                .await?
+                .query(&self.query())
+                .send()
+                .await?,
+        )
+        .await?;
+        Ok(logs.lines.iter().map(|line| parse_line(line)).collect())
+    }
+
+    /// Continue after the last line seen when it had a timestamp, otherwise
+    /// fetch the tail and find where it overlaps the lines already seen.
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("timestamps", "true".to_string())];
+        match self.cursor.since.or(self.options.since) {
+            Some(since) => {
+                let since = since.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+                query.push(("since", since));
+                if self.cursor.since.is_none() {
+                    query.extend(self.options.tail.map(|n| ("tail", n.to_string())));
+                }
+            }
+            None => query.extend(self.options.tail.map(|n| ("tail", n.to_string()))),
+        }
+        query
+    }
+}
+
+/// Tracks which lines were already yielded, so overlapping snapshots only
+/// yield new ones.
+#[derive(Debug, Default)]
+struct LogCursor {
+    /// The newest timestamp seen.
+    since: Option<DateTime<Utc>>,
+    /// Lines seen with exactly that timestamp.
+    at_since: Vec<DeploymentLogLine>,
+    /// The latest lines seen, in order.
+    recent: Vec<DeploymentLogLine>,
+}
+
+impl LogCursor {
+    fn starting_at(since: Option<DateTime<Utc>>) -> Self {
+        Self {
+            since,
+            ..Self::default()
+        }
+    }
+
+    /// Return the lines of a snapshot not seen before, recording them.
+    ///
+    /// Timestamped lines are new when they are newer than the newest seen,
+    /// or as new and not yet seen. A snapshot with any untimestamped line
+    /// continues after its longest prefix that ends the lines already seen.
+    fn fresh(&mut self, lines: Vec<DeploymentLogLine>) -> Vec<DeploymentLogLine> {
+        if !lines.is_empty() && lines.iter().all(|line| line.timestamp.is_some()) {
+            let mut unmatched = self.at_since.clone();
+            let fresh: Vec<_> = lines
+                .into_iter()
+                .filter(|line| match line.timestamp.cmp(&self.since) {
+                    std::cmp::Ordering::Less => false,
+                    std::cmp::Ordering::Greater => true,
+                    std::cmp::Ordering::Equal => match unmatched.iter().position(|l| l == line) {
+                        Some(index) => {
+                            unmatched.swap_remove(index);
+                            false
+                        }
+                        None => true,
+                    },
+                })
+                .collect();
+            fresh.iter().for_each(|line| self.record(line));
+            return fresh;
+        }
+
+        let overlap = (0..=lines.len().min(self.recent.len()))
+            .rev()
+            .find(|&n| self.recent[self.recent.len() - n..] == lines[..n])
+            .unwrap_or(0);
+        let fresh = lines[overlap..].to_vec();
+        fresh.iter().for_each(|line| self.record(line));
+        fresh
+    }
+
+    /// Record a line as seen.
+    fn record(&mut self, line: &DeploymentLogLine) {
+        match line.timestamp {
+            Some(timestamp) if self.since.is_none_or(|since| timestamp > since) => {
+                self.since = Some(timestamp);
+                self.at_since = vec![line.clone()];
+            }
+            Some(timestamp) if Some(timestamp) == self.since => self.at_since.push(line.clone()),
+            _ => {}
+        }
+        self.recent.push(line.clone());
+        if self.recent.len() > REMEMBERED_LINES {
+            self.recent.drain(..self.recent.len() - REMEMBERED_LINES);
+        }
+    }
+}
+
+/// Read the lines of a streamed log event, sent either as JSON
+/// [`DeploymentLogLine`] objects or as plain text.
+fn parse_event(event: &Event) -> Vec<DeploymentLogLine> {
+    if !matches!(event.event.as_str(), "log" | "message") {
+        return Vec::new();
+    }
+    if let Ok(line) = serde_json::from_str::<DeploymentLogLine>(&event.data) {
+        return vec![line];
+    }
+    event.data.lines().map(parse_line).collect()
+}
+
+/// Parse a plain log line, reading an optional `[replica/container]` label
+/// and RFC 3339 timestamp from its start, as in
+/// `[pod/web-7d9f/app] 2024-05-01T12:00:00.123Z listening on :8080`.
+fn parse_line(line: &str) -> DeploymentLogLine {
+    let mut rest = line;
+    let (mut replica, mut container) = (None, None);
+    if let Some((label, after)) = rest.strip_prefix('[').and_then(|r| r.split_once("] ")) {
+        let label = label.strip_prefix("pod/").unwrap_or(label);
+        match label.rsplit_once('/') {
+            Some((pod, name)) => {
+                replica = Some(pod.to_string());
+                container = Some(name.to_string());
+            }
+            None => replica = Some(label.to_string()),
+        }
+        rest = after;
+    }
+    let (first, after) = rest.split_once(' ').unwrap_or((rest, ""));
+    let timestamp = DateTime::parse_from_rfc3339(first).ok();
+    if timestamp.is_some() {
+        rest = after;
+    }
+    DeploymentLogLine {
+        timestamp: timestamp.map(|t| t.with_timezone(&Utc)),
+        replica,
+        container,
+        message: rest.to_string(),
+    }
+}
+
+impl CopepodClient {
+    /// Follow a deployment's container logs, like `tail -f`.
+    ///
+    /// Lines are streamed when the server supports following logs; otherwise,
+    /// or once the server closes the stream, the logs are polled every
+    /// [`LogFollowOptions::poll_interval`] and only lines not yielded before
+    /// are returned. Failed polls are yielded as errors; the stream ends
+    /// after one that is not worth retrying, and otherwise never ends on its
+    /// own. The stream does not borrow the client.
+    pub fn follow_deployment_logs(
+        &self,
+        org_id: &str,
+        deploy_id: &str,
+        options: LogFollowOptions,
+    ) -> impl Stream<Item = Result<DeploymentLogLine>> + Send + 'static {
+        let follow = Follow {
+            client: self.clone(),
+            path: format!(
+                "api/platform/orgs/{}/deployments/{}/logs",
+                org_id, deploy_id
+            ),
+            cursor: LogCursor::starting_at(options.since),
+            options,
+            mode: Mode::Start,
+            pending: VecDeque::new(),
+        };
+        stream::unfold(follow, |mut follow| async move {
+            let item = follow.next().await?;
+            Some((item, follow))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_labels_and_timestamps() {
+        let line = parse_line("[pod/web-7d9f/app] 2024-05-01T12:00:00.5Z listening on :8080");
+        assert_eq!(line.replica.as_deref(), Some("web-7d9f"));
+        assert_eq!(line.container.as_deref(), Some("app"));
+        assert_eq!(
+            line.timestamp.unwrap().to_rfc3339(),
+            "2024-05-01T12:00:00.500+00:00"
+        );
+        assert_eq!(line.message, "listening on :8080");
+
+        let plain = parse_line("[oops no label");
+        assert_eq!(plain.timestamp, None);
+        assert_eq!(plain.replica, None);
+        assert_eq!(plain.message, "[oops no label");
+    }
+
+    #[test]
+    fn skips_timestamped_lines_already_seen() {
+        let mut cursor = LogCursor::default();
+        let lines = |lines: &[&str]| lines.iter().map(|l| parse_line(l)).collect::<Vec<_>>();
+        let first = cursor.fresh(lines(&[
+            "2024-05-01T12:00:00Z a",
+            "2024-05-01T12:00:01Z b",
+            "2024-05-01T12:00:01Z b",
+        ]));
+        assert_eq!(first.len(), 3);
+
+        let next = cursor.fresh(lines(&[
+            "2024-05-01T12:00:01Z b",
+            "2024-05-01T12:00:01Z b",
+            "2024-05-01T12:00:01Z c",
+            "2024-05-01T12:00:02Z d",
+        ]));
+        let messages: Vec<_> = next.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["c", "d"]);
+    }
+
+    #[test]
+    fn continues_untimestamped_snapshots_after_their_overlap() {
+        let mut cursor = LogCursor::default();
+        let lines = |lines: &[&str]| lines.iter().map(|l| parse_line(l)).collect::<Vec<_>>();
+        assert_eq!(cursor.fresh(lines(&["a", "b", "c"])).len(), 3);
+        let next = cursor.fresh(lines(&["b", "c", "d", "e"]));
+        let messages: Vec<_> = next.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["d", "e"]);
+        assert!(cursor.fresh(lines(&["d", "e"])).is_empty());
+    }
+
+    #[test]
+    fn continues_mixed_snapshots_after_their_overlap() {
+        let mut cursor = LogCursor::default();
+        let lines = |lines: &[&str]| lines.iter().map(|l| parse_line(l)).collect::<Vec<_>>();
+        assert_eq!(
+            cursor
+                .fresh(lines(&[
+                    "2024-05-01T12:00:00Z a",
+                    "b",
+                    "2024-05-01T12:00:01Z c"
+                ]))
+                .len(),
+            3
+        );
+        let next = cursor.fresh(lines(&["b", "2024-05-01T12:00:01Z c", "d"]));
+        let messages: Vec<_> = next.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["d"]);
+
+        // A timestamped snapshot is remembered for the mixed one after it.
+        cursor.fresh(lines(&["2024-05-01T12:00:02Z e"]));
+        let next = cursor.fresh(lines(&["d", "2024-05-01T12:00:02Z e", "f"]));
+        let messages: Vec<_> = next.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["f"]);
+    }
+}
@@ -277,6 +277,9 @@ impl CopepodClient {
     // -- Logs & Metrics --
 
     /// Fetch recent container logs for a deployment.
+    ///
+    /// Use [`CopepodClient::follow_deployment_logs`] to keep receiving new
+    /// lines.
     pub async fn get_deployment_logs(
         &self,
         org_id: &str,
@@ -1,6 +1,7 @@
 use crate::client::CopepodClient;
 use crate::error::Result;
 use crate::models::{Entitlement, ListResult};
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     /// Get entitlements for an organization.
@@ -9,6 +10,15 @@ impl CopepodClient {
             .await
     }
 
+    /// Paginate over every entitlement for an organization.
+    pub fn paginate_entitlements<'a>(&'a self, org_id: &str) -> Paginator<'a, Entitlement> {
+        let path = format!("api/platform/orgs/{}/entitlements", org_id);
+        Paginator::new(move |page, per_page| {
+            let path = path.clone();
+            async move { self.get_page(&path, page, per_page).await }
+        })
+    }
+
     /// Create an entitlement override.
     pub async fn create_entitlement_override(
         &self,
@@ -1,6 +1,7 @@
 use crate::client::CopepodClient;
 use crate::error::Result;
 use crate::models::{Job, ListResult};
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     /// List jobs for an app.
@@ -9,6 +10,15 @@ impl CopepodClient {
             .await
     }
 
+    /// Paginate over every job for an app.
+    pub fn paginate_jobs<'a>(&'a self, app_id: &str) -> Paginator<'a, Job> {
+        let path = format!("api/platform/apps/{}/jobs", app_id);
+        Paginator::new(move |page, per_page| {
+            let path = path.clone();
+            async move { self.get_page(&path, page, per_page).await }
+        })
+    }
+
     /// Create a job.
     pub async fn create_job(&self, app_id: &str, body: &impl serde::Serialize) -> Result<Job> {
         self.post(&format!("api/platform/apps/{}/jobs", app_id), body)
@@ -3,6 +3,7 @@ use serde_json::Value;
 use crate::client::CopepodClient;
 use crate::error::Result;
 use crate::models::{ActionLog, ListResult, LogStats};
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     /// List action logs (with optional query parameters).
@@ -10,6 +11,13 @@ impl CopepodClient {
         self.get("api/platform/logs").await
     }
 
+    /// Paginate over every action log entry.
+    pub fn paginate_logs(&self) -> Paginator<'_, ActionLog> {
+        Paginator::new(move |page, per_page| async move {
+            self.get_page("api/platform/logs", page, per_page).await
+        })
+    }
+
     /// Get a single log entry by ID.
     pub async fn get_log(&self, id: &str) -> Result<ActionLog> {
         self.get(&format!("api/platform/logs/{}", id)).await
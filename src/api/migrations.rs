@@ -38,8 +38,9 @@ impl CopepodClient {
     /// Read all `.sql` files from a directory, assign sequential version numbers
     /// based on filename sort order, and sync them to copepod.
     ///
-    /// Files are sorted lexicographically by name, so both `001_foo.sql` and
-    /// `20260215000001_create_notes.sql` patterns work correctly.
+    /// Files are sorted by their numeric prefix, then by name, so both
+    /// `001_foo.sql` and `20260215000001_create_notes.sql` patterns work
+    /// correctly, and `1000_foo.sql` sorts after `999_bar.sql`.
     pub async fn sync_migrations_dir(
         &self,
         org_id: &str,
@@ -66,7 +67,11 @@ fn read_migrations_dir(dir: &Path) -> Result<Vec<MigrationInput>> {
         })
         .collect();
 
-    entries.sort();
+    entries.sort_by_cached_key(|path| {
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+        let digits: String = name.chars().take_while(char::is_ascii_digit).collect();
+        (digits.parse::<u128>().ok(), path.clone())
+    });
 
     let mut migrations = Vec::with_capacity(entries.len());
     for (i, path) in entries.iter().enumerate() {
@@ -90,3 +95,35 @@ fn read_migrations_dir(dir: &Path) -> Result<Vec<MigrationInput>> {
 
     Ok(migrations)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_migrations_by_number_prefix() {
+        let dir = std::env::temp_dir().join(format!("copepod-sync-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "1000_d.sql",
+            "999_c.sql",
+            "100_b.sql",
+            "002_a.sql",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), "SELECT 1;").unwrap();
+        }
+
+        let migrations = read_migrations_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let order: Vec<_> = migrations
+            .iter()
+            .map(|m| (m.version, m.name.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [(1, "002_a"), (2, "100_b"), (3, "999_c"), (4, "1000_d")]
+        );
+    }
+}
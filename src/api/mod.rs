@@ -11,6 +11,7 @@ pub mod cdn;
 pub mod cluster;
 pub mod collections;
 pub mod dashboard;
+pub mod deployment_logs;
 pub mod deployments;
 pub mod entitlements;
 pub mod environments;
@@ -11,11 +11,10 @@ impl CopepodClient {
         let body = serde_json::json!({ "email": email, "password": password });
         let resp: AuthResponse = self.post("api/platform/auth/login", &body).await?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set(TokenPair::new(
+                resp.token.clone(),
+                resp.refresh_token.clone(),
+            ))
             .await;
         Ok(resp)
     }
@@ -31,11 +30,10 @@ impl CopepodClient {
         let body = serde_json::json!({ "refresh_token": pair.refresh_token });
         let resp: AuthResponse = self.post("api/platform/auth/refresh", &body).await?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set(TokenPair::new(
+                resp.token.clone(),
+                resp.refresh_token.clone(),
+            ))
             .await;
         Ok(resp)
     }
@@ -54,11 +52,10 @@ impl CopepodClient {
         let body = serde_json::json!({ "mfa_token": mfa_token, "code": code });
         let resp: AuthResponse = self.post("api/platform/auth/mfa/verify", &body).await?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set(TokenPair::new(
+                resp.token.clone(),
+                resp.refresh_token.clone(),
+            ))
             .await;
         Ok(resp)
     }
@@ -86,11 +83,10 @@ impl CopepodClient {
         let body = serde_json::json!({ "mfa_token": mfa_token, "recovery_code": recovery_code });
         let resp: AuthResponse = self.post("api/platform/auth/mfa/recovery", &body).await?;
         self.token_store
-            .set(TokenPair {
-                token: resp.token.clone(),
-                refresh_token: resp.refresh_token.clone(),
-                expires_at: None,
-            })
+            .set(TokenPair::new(
+                resp.token.clone(),
+                resp.refresh_token.clone(),
+            ))
             .await;
         Ok(resp)
     }
@@ -9,6 +9,7 @@ use crate::models::{
     ItemsResponse, ListResult, Ticket, TicketAttachment, TicketComment, TicketListQuery,
     TicketStats,
 };
+use crate::pagination::Paginator;
 
 impl CopepodClient {
     async fn get_ticket_list_with_query(
@@ -95,6 +96,29 @@ impl CopepodClient {
         self.get_ticket_list_with_query(&path, query).await
     }
 
+    /// Paginate over every ticket in an app matching the given filters.
+    ///
+    /// `query.page` and `query.per_page` are ignored in favour of the
+    /// paginator's own settings.
+    pub fn paginate_app_tickets_filtered<'a>(
+        &'a self,
+        org_id: &str,
+        app_id: &str,
+        query: &TicketListQuery,
+    ) -> Paginator<'a, Ticket> {
+        let path = format!("api/platform/orgs/{}/apps/{}/tickets", org_id, app_id);
+        let query = query.clone();
+        Paginator::new(move |page, per_page| {
+            let path = path.clone();
+            let query = TicketListQuery {
+                page: Some(page),
+                per_page: Some(per_page),
+                ..query.clone()
+            };
+            async move { self.get_ticket_list_with_query(&path, &query).await }
+        })
+    }
+
     /// Get a specific ticket in an app.
     pub async fn get_app_ticket(
         &self,
@@ -1,3 +1,5 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
 
@@ -9,10 +11,50 @@ pub struct TokenPair {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+impl TokenPair {
+    /// Create a token pair, reading the expiry from the access token's `exp`
+    /// claim when it is a JWT.
+    pub fn new(token: impl Into<String>, refresh_token: impl Into<String>) -> Self {
+        let token = token.into();
+        Self {
+            expires_at: jwt_expiry(&token),
+            token,
+            refresh_token: refresh_token.into(),
+        }
+    }
+
+    /// Return when the token should be refreshed: 60 seconds before it
+    /// expires, if its expiry is known and there is a refresh token.
+    pub fn refresh_at(&self) -> Option<DateTime<Utc>> {
+        if self.refresh_token.is_empty() {
+            return None;
+        }
+        self.expires_at
+            .map(|exp| exp - chrono::Duration::seconds(60))
+    }
+}
+
+/// Read the `exp` claim of a JWT without verifying it.
+fn jwt_expiry(token: &str) -> Option<DateTime<Utc>> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    DateTime::from_timestamp(claims.get("exp")?.as_i64()?, 0)
+}
+
+/// A stored token pair and the endpoint that refreshes it.
+#[derive(Debug, Clone)]
+struct Session {
+    pair: TokenPair,
+    /// Refresh endpoint path relative to the base URL, or `None` for the
+    /// platform's.
+    refresh_path: Option<String>,
+}
+
 /// Thread-safe token store used by `CopepodClient` for automatic auth management.
 #[derive(Debug, Default)]
 pub struct TokenStore {
-    inner: RwLock<Option<TokenPair>>,
+    inner: RwLock<Option<Session>>,
 }
 
 impl TokenStore {
@@ -25,19 +67,41 @@ impl TokenStore {
     /// Create a new token store pre-loaded with a token pair.
     pub fn with_token(pair: TokenPair) -> Self {
         Self {
-            inner: RwLock::new(Some(pair)),
+            inner: RwLock::new(Some(Session {
+                pair,
+                refresh_path: None,
+            })),
         }
     }
 
-    /// Store a new token pair.
+    /// Store a new platform token pair.
     pub async fn set(&self, pair: TokenPair) {
+        self.store(pair, None).await;
+    }
+
+    /// Store a new token pair that is refreshed by posting its refresh token
+    /// to `path`, relative to the base URL, as app user sessions are.
+    pub async fn set_refreshed_by(&self, pair: TokenPair, path: impl Into<String>) {
+        self.store(pair, Some(path.into())).await;
+    }
+
+    pub(crate) async fn store(&self, pair: TokenPair, refresh_path: Option<String>) {
         let mut guard = self.inner.write().await;
-        *guard = Some(pair);
+        *guard = Some(Session { pair, refresh_path });
     }
 
     /// Retrieve the current token pair, if any.
     pub async fn get(&self) -> Option<TokenPair> {
-        self.inner.read().await.clone()
+        self.inner.read().await.as_ref().map(|s| s.pair.clone())
+    }
+
+    /// Retrieve the current token pair and its refresh endpoint path, if it
+    /// isn't the platform's.
+    pub(crate) async fn session(&self) -> Option<(TokenPair, Option<String>)> {
+        let guard = self.inner.read().await;
+        guard
+            .as_ref()
+            .map(|s| (s.pair.clone(), s.refresh_path.clone()))
     }
 
     /// Clear the stored tokens.
@@ -49,7 +113,7 @@ impl TokenStore {
     /// Check if the current token has expired.
     pub async fn is_expired(&self) -> bool {
         let guard = self.inner.read().await;
-        match &*guard {
+        match guard.as_ref().map(|s| &s.pair) {
             None => true,
             Some(pair) => match pair.expires_at {
                 None => false,
@@ -61,16 +125,10 @@ impl TokenStore {
     /// Check if the token should be refreshed (expires within 60 seconds).
     pub async fn needs_refresh(&self) -> bool {
         let guard = self.inner.read().await;
-        match &*guard {
-            None => false,
-            Some(pair) => match pair.expires_at {
-                None => false,
-                Some(exp) => {
-                    let threshold = exp - chrono::Duration::seconds(60);
-                    Utc::now() >= threshold
-                }
-            },
-        }
+        guard
+            .as_ref()
+            .and_then(|s| s.pair.refresh_at())
+            .is_some_and(|threshold| Utc::now() >= threshold)
     }
 }
 
@@ -79,6 +137,26 @@ mod tests {
     use super::*;
     use chrono::Duration;
 
+    #[test]
+    fn test_token_pair_reads_jwt_expiry() {
+        let claims = URL_SAFE_NO_PAD.encode(r#"{"sub":"u1","exp":1700000000}"#);
+        let pair = TokenPair::new(format!("eyJhbGciOiJIUzI1NiJ9.{claims}.sig"), "ref");
+        assert_eq!(pair.expires_at.map(|t| t.timestamp()), Some(1_700_000_000));
+        assert_eq!(pair.refresh_token, "ref");
+
+        assert_eq!(TokenPair::new("opaque-token", "ref").expires_at, None);
+        // Without a refresh token the pair is used until it expires.
+        assert!(pair.refresh_at().is_some());
+        assert_eq!(
+            TokenPair {
+                refresh_token: String::new(),
+                ..pair
+            }
+            .refresh_at(),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_token_store_set_get_clear() {
         let store = TokenStore::new();
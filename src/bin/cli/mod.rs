@@ -0,0 +1,85 @@
+//! Argument parsing and client setup shared by the command-line tools.
+
+use std::future::Future;
+use std::process::ExitCode;
+
+use copepod_sdk::CopepodClient;
+
+/// The flags selecting the app to work on, accepted by every tool.
+#[derive(Default)]
+pub struct Connection {
+    base_url: Option<String>,
+    token: Option<String>,
+    org: Option<String>,
+    app: Option<String>,
+}
+
+impl Connection {
+    /// Build a client for the connection, returning it with the org and app
+    /// IDs.
+    pub fn connect(self) -> Result<(CopepodClient, String, String), String> {
+        let (Some(base_url), Some(org), Some(app)) = (self.base_url, self.org, self.app) else {
+            return Err("--base-url, --org and --app are required".into());
+        };
+        let mut builder = CopepodClient::builder().base_url(base_url);
+        if let Some(token) = self.token {
+            builder = builder.token(token);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        Ok((client, org, app))
+    }
+}
+
+/// Parse `args` (without the program name). The connection flags are
+/// handled here; every other flag is passed to `flag` along with a function
+/// reading its value, and `flag` returns whether it knew the flag.
+pub fn parse_args(
+    args: impl IntoIterator<Item = String>,
+    mut flag: impl FnMut(&str, &mut dyn FnMut() -> Result<String, String>) -> Result<bool, String>,
+) -> Result<Connection, String> {
+    let mut connection = Connection {
+        token: std::env::var("COPEPOD_TOKEN").ok(),
+        ..Default::default()
+    };
+    let mut iter = args.into_iter();
+    while let Some(name) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("missing value for {name}"))
+        };
+        match name.as_str() {
+            "--base-url" => connection.base_url = Some(value()?),
+            "--token" => connection.token = Some(value()?),
+            "--org" => connection.org = Some(value()?),
+            "--app" => connection.app = Some(value()?),
+            other => {
+                if !flag(other, &mut value)? {
+                    return Err(format!("unknown argument: {other}"));
+                }
+            }
+        }
+    }
+    Ok(connection)
+}
+
+/// Run a tool: print `usage` for `--help`, otherwise await `run` and
+/// report its error, if any.
+pub async fn main(
+    name: &str,
+    usage: &str,
+    run: impl Future<Output = Result<(), String>>,
+) -> ExitCode {
+    if std::env::args().any(|arg| arg == "--help") {
+        println!(
+            "usage:\n{usage}\n\nThe token defaults to the COPEPOD_TOKEN environment variable."
+        );
+        return ExitCode::SUCCESS;
+    }
+    match run.await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{name}: {e} (see --help)");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Generate Rust types from Copepod collection schemas. Run with `--help`
+//! for usage; without `--out`, the generated module is written to stdout.
+
+mod cli;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use copepod_sdk::schema::codegen::{generate_module, load_snapshot, write_snapshot};
+
+const USAGE: &str = "\
+copepod-codegen --base-url URL --org ORG --app APP [--token TOKEN] [--snapshot FILE] [--out FILE]
+copepod-codegen --from-snapshot FILE [--out FILE]";
+
+async fn run() -> Result<(), String> {
+    let (mut snapshot, mut from_snapshot, mut out) = (None, None, None::<PathBuf>);
+    let connection = cli::parse_args(std::env::args().skip(1), |flag, value| {
+        match flag {
+            "--snapshot" => snapshot = Some(PathBuf::from(value()?)),
+            "--from-snapshot" => from_snapshot = Some(PathBuf::from(value()?)),
+            "--out" => out = Some(value()?.into()),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+
+    let schemas = match from_snapshot {
+        Some(path) => load_snapshot(&path).map_err(|e| e.to_string())?,
+        None => {
+            let (client, org, app) = connection.connect()?;
+            let schemas = client
+                .collection_schemas(&org, &app)
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Some(path) = &snapshot {
+                write_snapshot(path, &schemas).map_err(|e| e.to_string())?;
+            }
+            schemas
+        }
+    };
+
+    let code = generate_module(&schemas);
+    match out {
+        Some(path) => std::fs::write(&path, code)
+            .map_err(|e| format!("failed to write {}: {e}", path.display())),
+        None => {
+            print!("{code}");
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    cli::main("copepod-codegen", USAGE, run()).await
+}
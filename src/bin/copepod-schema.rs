@@ -0,0 +1,55 @@
+//! Plan and apply declarative collection schemas. Run with `--help` for
+//! usage; `--schema` is a YAML/JSON file or a directory of them.
+
+mod cli;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use copepod_sdk::schema::load_schema_files;
+
+const USAGE: &str = "\
+copepod-schema plan  --base-url URL --org ORG --app APP --schema PATH [--token TOKEN]
+copepod-schema apply --base-url URL --org ORG --app APP --schema PATH [--token TOKEN] [--allow-destructive]";
+
+async fn run() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let apply = match args.next().as_deref() {
+        Some("plan") => false,
+        Some("apply") => true,
+        _ => return Err("expected a `plan` or `apply` command".into()),
+    };
+    let (mut schema, mut allow_destructive) = (None::<PathBuf>, false);
+    let connection = cli::parse_args(args, |flag, value| {
+        match flag {
+            "--schema" => schema = Some(value()?.into()),
+            "--allow-destructive" => allow_destructive = true,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+
+    let schema = schema.ok_or("--schema is required")?;
+    let desired = load_schema_files(&schema).map_err(|e| e.to_string())?;
+    let (client, org, app) = connection.connect()?;
+    let app = client.app(org, app);
+
+    let plan = app.plan_schema(&desired).await.map_err(|e| e.to_string())?;
+    print!("{plan}");
+    if !apply || plan.is_empty() {
+        return Ok(());
+    }
+    if plan.is_destructive() && !allow_destructive {
+        return Err("plan has destructive changes; rerun with --allow-destructive".into());
+    }
+    app.apply_schema_plan(&plan, allow_destructive)
+        .await
+        .map_err(|e| e.to_string())?;
+    println!("Applied {} change(s).", plan.changes.len());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    cli::main("copepod-schema", USAGE, run()).await
+}
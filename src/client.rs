@@ -1,13 +1,18 @@
+use std::cell::Cell;
 use std::sync::Arc;
+use std::time::Duration;
 
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use reqwest::{Method, RequestBuilder, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use tokio::sync::Mutex;
 use url::Url;
 
 use crate::auth::{TokenPair, TokenStore};
 use crate::error::{CopepodError, Result};
+use crate::models::ListResult;
+use crate::realtime::RealtimeTransport;
 
 /// The main client for interacting with the Copepod API.
 #[derive(Debug, Clone)]
@@ -16,6 +21,10 @@ pub struct CopepodClient {
     pub(crate) base_url: Url,
     pub(crate) token_store: Arc<TokenStore>,
     pub(crate) auto_refresh: bool,
+    pub(crate) realtime_transport: RealtimeTransport,
+    /// Held while a token refresh is in flight, so concurrent requests
+    /// share one refresh instead of each spending the refresh token.
+    refresh_lock: Arc<Mutex<()>>,
 }
 
 /// Builder for constructing a [`CopepodClient`].
@@ -25,6 +34,7 @@ pub struct CopepodClientBuilder {
     refresh_token: Option<String>,
     auto_refresh: bool,
     http_client: Option<reqwest::Client>,
+    realtime_transport: RealtimeTransport,
 }
 
 impl CopepodClientBuilder {
@@ -35,6 +45,7 @@ impl CopepodClientBuilder {
             refresh_token: None,
             auto_refresh: true,
             http_client: None,
+            realtime_transport: RealtimeTransport::default(),
         }
     }
 
@@ -69,6 +80,13 @@ impl CopepodClientBuilder {
         self
     }
 
+    /// Set the transport used for realtime subscriptions (default:
+    /// [`RealtimeTransport::Sse`]). Subscriptions can override it.
+    pub fn realtime_transport(mut self, transport: RealtimeTransport) -> Self {
+        self.realtime_transport = transport;
+        self
+    }
+
     /// Build the client.
     pub fn build(self) -> Result<CopepodClient> {
         let base_url_str = self
@@ -82,11 +100,10 @@ impl CopepodClientBuilder {
         }
 
         let token_store = if let Some(token) = self.token {
-            Arc::new(TokenStore::with_token(TokenPair {
+            Arc::new(TokenStore::with_token(TokenPair::new(
                 token,
-                refresh_token: self.refresh_token.unwrap_or_default(),
-                expires_at: None,
-            }))
+                self.refresh_token.unwrap_or_default(),
+            )))
         } else {
             Arc::new(TokenStore::new())
         };
@@ -109,6 +126,8 @@ impl CopepodClientBuilder {
             base_url,
             token_store,
             auto_refresh: self.auto_refresh,
+            realtime_transport: self.realtime_transport,
+            refresh_lock: Arc::new(Mutex::new(())),
         })
     }
 }
@@ -157,48 +176,75 @@ impl CopepodClient {
     /// Ensure we have a valid auth token, refreshing if needed.
     pub(crate) async fn ensure_auth(&self) -> Result<()> {
         if self.auto_refresh && self.token_store.needs_refresh().await {
-            let pair = self
-                .token_store
-                .get()
-                .await
-                .ok_or_else(|| CopepodError::Auth("No token available for refresh".into()))?;
-
-            if pair.refresh_token.is_empty() {
-                return Err(CopepodError::Auth("No refresh token available".into()));
+            let _refreshing = self.refresh_lock.lock().await;
+            // Another request may have refreshed the token while this one
+            // waited for the lock.
+            if self.token_store.needs_refresh().await {
+                self.exchange_refresh_token().await?;
             }
+        }
+        Ok(())
+    }
 
-            // Call the refresh endpoint
-            let url = self.base_url.join("api/platform/auth/refresh")?;
-            let resp = self
-                .http
-                .post(url)
-                .json(&serde_json::json!({ "refresh_token": pair.refresh_token }))
-                .send()
-                .await?;
-
-            if !resp.status().is_success() {
-                let status = resp.status().as_u16();
-                let body: serde_json::Value = resp.json().await.unwrap_or_default();
-                return Err(CopepodError::Api {
-                    status,
-                    code: body.get("code").and_then(|v| v.as_str()).map(String::from),
-                    message: body
-                        .get("message")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Token refresh failed")
-                        .to_string(),
-                });
-            }
+    /// Refresh the stored token pair, even if it isn't about to expire.
+    ///
+    /// Nothing is sent if another refresh replaced the token while this one
+    /// waited for it to finish.
+    pub(crate) async fn refresh_auth(&self) -> Result<()> {
+        let stale = self.token_store.get().await.map(|pair| pair.token);
+        let _refreshing = self.refresh_lock.lock().await;
+        if self.token_store.get().await.map(|pair| pair.token) != stale {
+            return Ok(());
+        }
+        self.exchange_refresh_token().await
+    }
 
-            let auth_resp: crate::models::AuthResponse = resp.json().await?;
-            self.token_store
-                .set(TokenPair {
-                    token: auth_resp.token.clone(),
-                    refresh_token: auth_resp.refresh_token.clone(),
-                    expires_at: None,
-                })
-                .await;
+    /// Exchange the stored refresh token for a new token pair, at the
+    /// endpoint that issued the stored pair. Callers hold `refresh_lock`.
+    async fn exchange_refresh_token(&self) -> Result<()> {
+        let (pair, refresh_path) = self
+            .token_store
+            .session()
+            .await
+            .ok_or_else(|| CopepodError::Auth("No token available for refresh".into()))?;
+
+        if pair.refresh_token.is_empty() {
+            return Err(CopepodError::Auth("No refresh token available".into()));
         }
+
+        // Call the refresh endpoint
+        let path = refresh_path
+            .as_deref()
+            .unwrap_or("api/platform/auth/refresh");
+        let url = self.base_url.join(path)?;
+        let resp = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({ "refresh_token": pair.refresh_token }))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CopepodError::Api {
+                status,
+                code: body.get("code").and_then(|v| v.as_str()).map(String::from),
+                message: body
+                    .get("message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Token refresh failed")
+                    .to_string(),
+            });
+        }
+
+        let auth_resp: crate::models::AuthResponse = resp.json().await?;
+        self.token_store
+            .store(
+                TokenPair::new(auth_resp.token, auth_resp.refresh_token),
+                refresh_path,
+            )
+            .await;
         Ok(())
     }
 
@@ -218,6 +264,22 @@ impl CopepodClient {
         Self::handle_response(resp).await
     }
 
+    /// Perform an authenticated GET request for a single page of a list endpoint.
+    pub(crate) async fn get_page<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        page: u32,
+        per_page: u32,
+    ) -> Result<ListResult<T>> {
+        let resp = self
+            .auth_request(Method::GET, path)
+            .await?
+            .query(&[("page", page), ("per_page", per_page)])
+            .send()
+            .await?;
+        Self::handle_response(resp).await
+    }
+
     /// Perform an unauthenticated GET request and deserialize the response.
     pub(crate) async fn get_public<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
         let resp = self.request(Method::GET, path).send().await?;
@@ -363,7 +425,14 @@ impl CopepodClient {
     /// `"failed to reach primary node"`), falls back to the raw body text so
     /// the real reason isn't lost. The fallback message is truncated to
     /// [`MAX_ERROR_BODY_CHARS`] characters to bound log size.
-    async fn map_error<T>(status: StatusCode, resp: reqwest::Response) -> Result<T> {
+    pub(crate) async fn map_error<T>(status: StatusCode, resp: reqwest::Response) -> Result<T> {
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            // Only bulk operations ask for the wait; see `RETRY_AFTER`.
+            let _ = RETRY_AFTER.try_with(|slot| slot.set(retry_after(resp.headers())));
+        }
         let bytes = resp.bytes().await.unwrap_or_default();
         let (code, message) = decode_error_body(&bytes);
         Err(CopepodError::Api {
@@ -374,6 +443,31 @@ impl CopepodClient {
     }
 }
 
+tokio::task_local! {
+    /// Set by bulk operations around each attempt to receive the
+    /// `Retry-After` of a 429 or 503 response, which other callers never
+    /// see.
+    pub(crate) static RETRY_AFTER: Cell<Option<Duration>>;
+}
+
+/// Read a `Retry-After` header, given either in seconds or as an HTTP date.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
 /// Maximum number of characters we keep when falling back to a plain-text
 /// error body. Long upstream HTML/text bodies are truncated to bound log
 /// size while still carrying enough context to debug.
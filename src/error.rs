@@ -1,5 +1,8 @@
+use reqwest::StatusCode;
 use thiserror::Error;
 
+use crate::schema::FieldError;
+
 /// Errors returned by the Copepod SDK.
 #[derive(Debug, Error)]
 pub enum CopepodError {
@@ -31,9 +34,58 @@ pub enum CopepodError {
     #[error("SSE error: {0}")]
     Sse(String),
 
+    /// WebSocket transport error.
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    /// Invalid query input (e.g. a value that cannot be used in a filter).
+    #[error("Query error: {0}")]
+    Query(String),
+
+    /// Record body failed client-side schema validation.
+    #[error("Validation error: {}", join_field_errors(.0))]
+    Validation(Vec<FieldError>),
+
+    /// Invalid schema definition or schema plan.
+    #[error("Schema error: {0}")]
+    Schema(String),
+
+    /// Invalid fixture data (e.g. an unknown or circular reference).
+    #[error("Fixture error: {0}")]
+    Fixture(String),
+
     /// Filesystem I/O error (e.g. reading migration files).
     #[error("IO error: {0}")]
     Io(String),
 }
 
+impl CopepodError {
+    /// Whether the failed request may succeed if retried: transport errors,
+    /// server errors, rate limiting and timeouts.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            Self::Api { status, .. } => is_retryable(*status),
+            Self::Http(_) | Self::Sse(_) | Self::WebSocket(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Whether a response with `status` is worth retrying.
+pub(crate) fn is_retryable(status: u16) -> bool {
+    StatusCode::from_u16(status).is_ok_and(|s| {
+        s.is_server_error()
+            || s == StatusCode::TOO_MANY_REQUESTS
+            || s == StatusCode::REQUEST_TIMEOUT
+    })
+}
+
+fn join_field_errors(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 pub type Result<T> = std::result::Result<T, CopepodError>;
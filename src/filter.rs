@@ -0,0 +1,478 @@
+//! Local evaluation of record filter and sort expressions.
+//!
+//! Supports the subset of the server filter syntax used by the query
+//! builder: comparisons (`=`, `!=`, `>`, `>=`, `<`, `<=`, `~`, `!~`),
+//! their any-of forms for array fields (`?=`, `?~`, ...), `&&`, `||` and
+//! parentheses. Operands are field paths (`author.name`), quoted strings,
+//! numbers, `true`, `false` and `null`.
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use crate::error::{CopepodError, Result};
+use crate::query::compare_values;
+
+/// A parsed filter expression that can be evaluated against records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    All,
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare {
+        left: Operand,
+        op: Op,
+        any: bool,
+        right: Operand,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Field(String),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Like,
+    NotLike,
+}
+
+impl Filter {
+    /// Parse a filter expression. An empty expression matches every record.
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(Self { expr: Expr::All });
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(filter_error(format!(
+                "unexpected {:?} in filter",
+                parser.tokens[parser.pos]
+            )));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Whether `record` matches the filter.
+    pub fn matches(&self, record: &Value) -> bool {
+        self.expr.eval(record)
+    }
+}
+
+impl Expr {
+    fn eval(&self, record: &Value) -> bool {
+        match self {
+            Self::All => true,
+            Self::And(a, b) => a.eval(record) && b.eval(record),
+            Self::Or(a, b) => a.eval(record) || b.eval(record),
+            Self::Compare {
+                left,
+                op,
+                any,
+                right,
+            } => {
+                let left = left.resolve(record);
+                let right = right.resolve(record);
+                match (&left, any) {
+                    (Value::Array(items), true) => items.iter().any(|item| op.apply(item, &right)),
+                    _ => op.apply(&left, &right),
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, record: &Value) -> Value {
+        match self {
+            Self::Literal(value) => value.clone(),
+            Self::Field(path) => path
+                .split('.')
+                .try_fold(record, |value, key| value.get(key))
+                .cloned()
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+impl Op {
+    fn apply(self, left: &Value, right: &Value) -> bool {
+        match self {
+            Self::Eq => values_equal(left, right),
+            Self::Ne => !values_equal(left, right),
+            Self::Gt => compare_values(left, right) == Some(Ordering::Greater),
+            Self::Ge => matches!(
+                compare_values(left, right),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Self::Lt => compare_values(left, right) == Some(Ordering::Less),
+            Self::Le => matches!(
+                compare_values(left, right),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            Self::Like => like(left, right),
+            Self::NotLike => !like(left, right),
+        }
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => compare_values(a, b) == Some(Ordering::Equal),
+        // Missing fields and empty strings both compare equal to `null`/`''`.
+        (Value::Null, Value::String(s)) | (Value::String(s), Value::Null) => s.is_empty(),
+        _ => a == b,
+    }
+}
+
+/// Case-insensitive match where `%` is a wildcard; without wildcards the
+/// pattern matches anywhere in the value.
+fn like(value: &Value, pattern: &Value) -> bool {
+    let text = match value {
+        Value::String(s) => s.to_lowercase(),
+        Value::Null => String::new(),
+        other => other.to_string().to_lowercase(),
+    };
+    let pattern = match pattern {
+        Value::String(s) => s.to_lowercase(),
+        other => other.to_string().to_lowercase(),
+    };
+    let pattern = if pattern.contains('%') {
+        pattern
+    } else {
+        format!("%{pattern}%")
+    };
+
+    let parts: Vec<&str> = pattern.split('%').collect();
+    let (first, rest) = parts.split_first().expect("split yields at least one part");
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+    let (last, middle) = rest.split_last().expect("pattern contains a wildcard");
+    for part in middle {
+        match remaining.find(part) {
+            Some(i) => remaining = &remaining[i + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// A parsed sort expression like `-created,title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortOrder {
+    keys: Vec<(String, bool)>,
+}
+
+impl SortOrder {
+    /// Parse comma-separated field names, each optionally prefixed with `-`
+    /// for descending or `+` for ascending order.
+    pub fn parse(input: &str) -> Self {
+        let keys = input
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| match key.strip_prefix('-') {
+                Some(field) => (field.to_string(), true),
+                None => (key.trim_start_matches('+').to_string(), false),
+            })
+            .collect();
+        Self { keys }
+    }
+
+    /// Compare two records. Missing and incomparable values sort first.
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        for (field, descending) in &self.keys {
+            let field = Operand::Field(field.clone());
+            let (a, b) = (field.resolve(a), field.resolve(b));
+            let ordering = match (a.is_null(), b.is_null()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => compare_values(&a, &b).unwrap_or(Ordering::Equal),
+            };
+            let ordering = if *descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(Op, bool),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+fn filter_error(message: String) -> CopepodError {
+    CopepodError::Query(message)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next().map(|(_, next)| next) != Some(c) {
+                    return Err(filter_error(format!("expected `{c}{c}` at {start}")));
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => s.push(escaped),
+                            None => break,
+                        },
+                        Some((_, ch)) if ch == c => {
+                            tokens.push(Token::Literal(Value::String(s)));
+                            break;
+                        }
+                        Some((_, ch)) => s.push(ch),
+                        None => {
+                            return Err(filter_error(format!(
+                                "unterminated string starting at {start}"
+                            )))
+                        }
+                    }
+                }
+            }
+            '=' | '!' | '>' | '<' | '~' | '?' => {
+                let mut op = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if matches!(ch, '=' | '!' | '>' | '<' | '~' | '?') {
+                        op.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let (any, op_str) = match op.strip_prefix('?') {
+                    Some(rest) => (true, rest),
+                    None => (false, op.as_str()),
+                };
+                let op = match op_str {
+                    "=" => Op::Eq,
+                    "!=" => Op::Ne,
+                    ">" => Op::Gt,
+                    ">=" => Op::Ge,
+                    "<" => Op::Lt,
+                    "<=" => Op::Le,
+                    "~" => Op::Like,
+                    "!~" => Op::NotLike,
+                    _ => return Err(filter_error(format!("unknown operator `{op}`"))),
+                };
+                tokens.push(Token::Op(op, any));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch.is_alphanumeric() || matches!(ch, '_' | '.' | '-' | '+' | '@') {
+                        word.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if word.is_empty() {
+                    return Err(filter_error(format!("unexpected `{c}` at {start}")));
+                }
+                tokens.push(word_token(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn word_token(word: String) -> Token {
+    match word.as_str() {
+        "true" => Token::Literal(Value::Bool(true)),
+        "false" => Token::Literal(Value::Bool(false)),
+        "null" => Token::Literal(Value::Null),
+        _ => match word.parse::<serde_json::Number>() {
+            Ok(n) => Token::Literal(Value::Number(n)),
+            Err(_) => Token::Ident(word),
+        },
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let expr = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err(filter_error("expected `)` in filter".into())),
+            };
+        }
+        let left = self.operand()?;
+        let (op, any) = match self.next() {
+            Some(Token::Op(op, any)) => (op, any),
+            other => {
+                return Err(filter_error(format!(
+                    "expected an operator in filter, found {other:?}"
+                )))
+            }
+        };
+        let right = self.operand()?;
+        Ok(Expr::Compare {
+            left,
+            op,
+            any,
+            right,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(Operand::Field(name)),
+            Some(Token::Literal(value)) => Ok(Operand::Literal(value)),
+            other => Err(filter_error(format!(
+                "expected a field or value in filter, found {other:?}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::filter_literal;
+    use serde_json::json;
+
+    fn matches(filter: &str, record: &Value) -> bool {
+        Filter::parse(filter).unwrap().matches(record)
+    }
+
+    #[test]
+    fn evaluates_comparisons_and_logic() {
+        let record = json!({
+            "title": "Hello World",
+            "views": 10,
+            "published": true,
+            "author": { "name": "Ada" },
+            "tags": ["rust", "sdk"]
+        });
+        assert!(matches("views > 5 && published = true", &record));
+        assert!(matches("views >= 10.0 && views <= 10", &record));
+        assert!(!matches("views < 10 || title = 'Nope'", &record));
+        assert!(matches(
+            "(views < 10 || title ~ 'world') && author.name = \"Ada\"",
+            &record
+        ));
+        assert!(matches("title ~ 'hello%'", &record));
+        assert!(matches("title !~ 'bye'", &record));
+        assert!(matches("tags ?= 'sdk'", &record));
+        assert!(!matches("tags ?= 'go'", &record));
+        assert!(matches("missing = null && missing = ''", &record));
+        assert!(matches("", &record));
+    }
+
+    #[test]
+    fn round_trips_filter_literals() {
+        let value = json!("it's a \\ test");
+        let filter = format!("title = {}", filter_literal(&value).unwrap());
+        assert!(matches(&filter, &json!({ "title": value })));
+    }
+
+    #[test]
+    fn rejects_malformed_filters() {
+        for filter in [
+            "views >",
+            "views = 1 &",
+            "(views = 1",
+            "title = 'open",
+            "a === b",
+        ] {
+            assert!(Filter::parse(filter).is_err(), "{filter}");
+        }
+    }
+
+    #[test]
+    fn sorts_by_multiple_keys() {
+        let order = SortOrder::parse("-views,title");
+        let mut records = [
+            json!({ "title": "b", "views": 1 }),
+            json!({ "title": "a", "views": 1 }),
+            json!({ "title": "c", "views": 5 }),
+        ];
+        records.sort_by(|a, b| order.compare(a, b));
+        let titles: Vec<_> = records
+            .iter()
+            .map(|r| r["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, ["c", "a", "b"]);
+    }
+}
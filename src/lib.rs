@@ -2,12 +2,20 @@ pub mod api;
 pub mod auth;
 pub mod client;
 pub mod error;
+pub mod filter;
+pub mod live;
 pub mod models;
+pub mod pagination;
 pub mod query;
 pub mod realtime;
+pub mod replica;
+pub mod schema;
 pub mod scoped;
+pub mod seed;
 
 pub use client::{CopepodClient, CopepodClientBuilder};
 pub use error::CopepodError;
 pub use models::*;
+pub use pagination::Paginator;
+pub use schema::CopepodRecord;
 pub use scoped::*;
@@ -0,0 +1,452 @@
+//! Query results kept current by realtime events.
+//!
+//! [`RecordQueryBuilder::live`](crate::query::RecordQueryBuilder::live)
+//! lists a query's records once, then applies the collection's realtime
+//! record events to that result set, keeping it filtered and sorted like the
+//! query. Each applied event yields a [`LiveChange`] with the new snapshot
+//! and the index changes that produced it. After the realtime connection
+//! reconnects, the query is listed again, since events may have been missed.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::stream::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::watch;
+
+use crate::error::{CopepodError, Result};
+use crate::filter::{Filter, SortOrder};
+use crate::models::{RecordAction, RecordEvent};
+use crate::realtime::{ConnectionState, RealtimeEvent, SubscriptionEvent};
+
+/// One step of a [`LiveChange`]. Steps apply in order, each to the list as
+/// left by the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveDiff {
+    /// A record was inserted at `index`.
+    Inserted { index: usize },
+    /// The record at `index` changed in place.
+    Updated { index: usize },
+    /// The record at `from` changed and moved to `to`, its index after
+    /// removal from `from`.
+    Moved { from: usize, to: usize },
+    /// The record at `index` was removed.
+    Removed { index: usize },
+    /// The result set was listed again after a reconnect, and the snapshot
+    /// replaces the previous one.
+    Reset,
+}
+
+/// A change to a [`LiveQuery`] result set.
+#[derive(Debug, Clone)]
+pub struct LiveChange<T> {
+    /// The result set after the change.
+    pub snapshot: Arc<Vec<T>>,
+    pub diff: Vec<LiveDiff>,
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<SubscriptionEvent>> + Send>>;
+type ListFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<Value>>> + Send + 'a>>;
+type ListFn<'a> = Box<dyn Fn() -> ListFuture<'a> + Send + 'a>;
+
+/// A query result set kept current by realtime events.
+///
+/// The stream yields a [`LiveChange`] for every event that changes the
+/// result set, and ends when the realtime subscription closes. Events are
+/// only applied while the stream is polled; [`LiveQuery::watch`] receivers
+/// see each new snapshot as it is applied.
+///
+/// Records are matched against the query's filter and ordered by its sort
+/// locally, so the subscription receives every event of the collection: a
+/// record that stops matching is removed. Without a sort, records keep their
+/// listed order and new ones are appended. When the query sets `per_page`,
+/// the result set holds at most that many records; records pushed past the
+/// end are removed, and removals are not backfilled. Without it, every
+/// matching record is listed and the set grows with the collection.
+///
+/// When the connection drops, the subscription reconnects with backoff; once
+/// it is connected again the query is listed again and the stream yields a
+/// [`LiveDiff::Reset`] change with the fresh result set.
+pub struct LiveQuery<'a, T> {
+    collection: String,
+    filter: Option<Filter>,
+    sort: Option<SortOrder>,
+    limit: Option<usize>,
+    records: Vec<(Value, T)>,
+    events: EventStream,
+    /// Events received while the initial list was in flight.
+    buffered: VecDeque<Result<SubscriptionEvent>>,
+    list: ListFn<'a>,
+    /// The list requested after a reconnect, while in flight.
+    relisting: Option<ListFuture<'a>>,
+    /// Whether the connection dropped since the result set was listed.
+    disconnected: bool,
+    snapshots: watch::Sender<Arc<Vec<T>>>,
+}
+
+impl<'a, T: DeserializeOwned + Clone> LiveQuery<'a, T> {
+    /// Wait for the subscription to connect, then build the live result set
+    /// from `list`. Events arriving meanwhile are applied afterwards.
+    pub(crate) async fn open(
+        collection: String,
+        filter: Option<&str>,
+        sort: Option<&str>,
+        limit: Option<usize>,
+        mut events: EventStream,
+        list: ListFn<'a>,
+    ) -> Result<Self> {
+        let filter = filter.map(Filter::parse).transpose()?;
+        let mut buffered = VecDeque::new();
+        loop {
+            match events.next().await {
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Connected))) => break,
+                // The subscription retries with backoff until it gives up.
+                Some(Ok(SubscriptionEvent::State(
+                    ConnectionState::Connecting | ConnectionState::Reconnecting { .. },
+                ))) => {}
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Closed))) | None => {
+                    return Err(CopepodError::Sse(
+                        "could not open the realtime connection for a live query".into(),
+                    ))
+                }
+                Some(Err(e)) => return Err(e),
+                Some(item) => buffered.push_back(item),
+            }
+        }
+
+        let records = decode::<T>(list().await?)?;
+        let snapshot = Arc::new(records.iter().map(|(_, item)| item.clone()).collect());
+        Ok(Self {
+            collection,
+            filter,
+            sort: sort.map(SortOrder::parse),
+            limit,
+            records,
+            events,
+            buffered,
+            list,
+            relisting: None,
+            disconnected: false,
+            snapshots: watch::channel(snapshot).0,
+        })
+    }
+
+    /// Return the current result set.
+    pub fn snapshot(&self) -> Arc<Vec<T>> {
+        self.snapshots.borrow().clone()
+    }
+
+    /// Return a receiver of result set snapshots, updated as the live query
+    /// is polled.
+    pub fn watch(&self) -> watch::Receiver<Arc<Vec<T>>> {
+        self.snapshots.subscribe()
+    }
+
+    /// Apply a record event, returning the steps that changed the result
+    /// set. Events for other collections and unknown actions are ignored.
+    pub fn apply_event(&mut self, event: &RecordEvent) -> Result<Vec<LiveDiff>> {
+        if event.collection != self.collection || matches!(event.action, RecordAction::Other(_)) {
+            return Ok(Vec::new());
+        }
+        let Some(id) = event.record.get("id").and_then(Value::as_str) else {
+            return Ok(Vec::new());
+        };
+        let existing = self
+            .records
+            .iter()
+            .position(|(value, _)| value.get("id").and_then(Value::as_str) == Some(id));
+        let matches = event.action != RecordAction::Delete
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|f| f.matches(&event.record));
+
+        let mut diff = Vec::new();
+        if !matches {
+            if let Some(index) = existing {
+                self.records.remove(index);
+                diff.push(LiveDiff::Removed { index });
+            }
+        } else {
+            let entry = (event.record.clone(), event.decode::<T>()?);
+            match existing {
+                Some(from) => {
+                    self.records.remove(from);
+                    let to = self.position(&entry.0).unwrap_or(from);
+                    self.records.insert(to, entry);
+                    diff.push(match to == from {
+                        true => LiveDiff::Updated { index: from },
+                        false => LiveDiff::Moved { from, to },
+                    });
+                }
+                None => {
+                    let index = self.position(&entry.0).unwrap_or(self.records.len());
+                    if self.limit.is_none_or(|limit| index < limit) {
+                        self.records.insert(index, entry);
+                        diff.push(LiveDiff::Inserted { index });
+                    }
+                }
+            }
+            if let Some(limit) = self.limit {
+                while self.records.len() > limit {
+                    self.records.pop();
+                    diff.push(LiveDiff::Removed {
+                        index: self.records.len(),
+                    });
+                }
+            }
+        }
+
+        if !diff.is_empty() {
+            let snapshot = self.records.iter().map(|(_, item)| item.clone()).collect();
+            self.snapshots.send_replace(Arc::new(snapshot));
+        }
+        Ok(diff)
+    }
+
+    /// Replace the result set with a fresh list.
+    fn reset(&mut self, records: Vec<Value>) -> Result<Vec<LiveDiff>> {
+        self.records = decode(records)?;
+        let snapshot = self.records.iter().map(|(_, item)| item.clone()).collect();
+        self.snapshots.send_replace(Arc::new(snapshot));
+        Ok(vec![LiveDiff::Reset])
+    }
+
+    /// Return where a record belongs by the query's sort, after any records
+    /// it ties with, or `None` if the query is unsorted.
+    fn position(&self, record: &Value) -> Option<usize> {
+        let sort = self.sort.as_ref()?;
+        Some(
+            self.records
+                .partition_point(|(value, _)| sort.compare(value, record) != Ordering::Greater),
+        )
+    }
+}
+
+fn decode<T: DeserializeOwned>(records: Vec<Value>) -> Result<Vec<(Value, T)>> {
+    records
+        .into_iter()
+        .map(|value| {
+            let item = T::deserialize(&value)?;
+            Ok((value, item))
+        })
+        .collect()
+}
+
+// Records are never pinned in place.
+impl<T> Unpin for LiveQuery<'_, T> {}
+
+impl<T: DeserializeOwned + Clone> Stream for LiveQuery<'_, T> {
+    type Item = Result<LiveChange<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            // Events wait in the subscription until the new list is in.
+            if let Some(relisting) = self.relisting.as_mut() {
+                let records = match relisting.as_mut().poll(cx) {
+                    Poll::Ready(records) => records,
+                    Poll::Pending => return Poll::Pending,
+                };
+                self.relisting = None;
+                let diff = match records.and_then(|records| self.reset(records)) {
+                    Ok(diff) => diff,
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                };
+                let snapshot = self.snapshot();
+                return Poll::Ready(Some(Ok(LiveChange { snapshot, diff })));
+            }
+            let item = match self.buffered.pop_front() {
+                Some(item) => item,
+                None => match self.events.poll_next_unpin(cx) {
+                    Poll::Ready(Some(item)) => item,
+                    Poll::Ready(None) => return Poll::Ready(None),
+                    Poll::Pending => return Poll::Pending,
+                },
+            };
+            let event = match item {
+                Ok(SubscriptionEvent::Event(RealtimeEvent::Record(event))) => event,
+                Ok(SubscriptionEvent::State(ConnectionState::Reconnecting { .. })) => {
+                    self.disconnected = true;
+                    continue;
+                }
+                Ok(SubscriptionEvent::State(ConnectionState::Connected)) if self.disconnected => {
+                    self.disconnected = false;
+                    self.relisting = Some((self.list)());
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+            match self.apply_event(&event) {
+                Ok(diff) if diff.is_empty() => {}
+                Ok(diff) => {
+                    let snapshot = self.snapshot();
+                    return Poll::Ready(Some(Ok(LiveChange { snapshot, diff })));
+                }
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for LiveQuery<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveQuery")
+            .field("collection", &self.collection)
+            .field("len", &self.records.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+    use serde_json::json;
+
+    use super::*;
+
+    fn live(
+        filter: Option<&str>,
+        sort: Option<&str>,
+        limit: Option<usize>,
+    ) -> LiveQuery<'static, Value> {
+        let records = [("a", 3), ("b", 5), ("c", 8)]
+            .map(|(id, views)| {
+                let record = json!({ "id": id, "views": views });
+                (record.clone(), record)
+            })
+            .to_vec();
+        LiveQuery {
+            collection: "posts".into(),
+            filter: filter.map(|f| Filter::parse(f).unwrap()),
+            sort: sort.map(SortOrder::parse),
+            limit,
+            records,
+            events: Box::pin(stream::empty()),
+            buffered: VecDeque::new(),
+            list: Box::new(|| Box::pin(async { Ok(Vec::new()) })),
+            relisting: None,
+            disconnected: false,
+            snapshots: watch::channel(Arc::new(Vec::new())).0,
+        }
+    }
+
+    fn event(action: RecordAction, id: &str, views: i64) -> RecordEvent {
+        RecordEvent {
+            action,
+            collection: "posts".into(),
+            record: json!({ "id": id, "views": views }),
+        }
+    }
+
+    fn ids<'a>(live: &'a LiveQuery<'_, Value>) -> Vec<&'a str> {
+        live.records
+            .iter()
+            .map(|(record, _)| record["id"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_sorted_records_in_place() {
+        let mut live = live(None, Some("views"), None);
+        let diff = live
+            .apply_event(&event(RecordAction::Create, "d", 4))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Inserted { index: 1 }]);
+        assert_eq!(ids(&live), ["a", "d", "b", "c"]);
+
+        let diff = live
+            .apply_event(&event(RecordAction::Update, "a", 9))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Moved { from: 0, to: 3 }]);
+        let diff = live
+            .apply_event(&event(RecordAction::Update, "b", 6))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Updated { index: 1 }]);
+        assert_eq!(ids(&live), ["d", "b", "c", "a"]);
+
+        let diff = live
+            .apply_event(&event(RecordAction::Delete, "c", 8))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Removed { index: 2 }]);
+        assert_eq!(live.snapshot().len(), 3);
+    }
+
+    #[test]
+    fn removes_records_that_stop_matching_the_filter() {
+        let mut live = live(Some("views > 2"), None, None);
+        let diff = live
+            .apply_event(&event(RecordAction::Update, "b", 1))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Removed { index: 1 }]);
+        let diff = live
+            .apply_event(&event(RecordAction::Create, "d", 0))
+            .unwrap();
+        assert!(diff.is_empty());
+        let diff = live
+            .apply_event(&event(RecordAction::Update, "b", 7))
+            .unwrap();
+        assert_eq!(diff, [LiveDiff::Inserted { index: 2 }]);
+        assert_eq!(ids(&live), ["a", "c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn open_waits_out_reconnects() {
+        let state = |state| Ok(SubscriptionEvent::State(state));
+        let events = stream::iter([
+            state(ConnectionState::Connecting),
+            state(ConnectionState::Reconnecting {
+                attempt: 1,
+                delay: std::time::Duration::ZERO,
+            }),
+            state(ConnectionState::Connected),
+        ]);
+        let live = LiveQuery::<Value>::open(
+            "posts".into(),
+            None,
+            None,
+            None,
+            Box::pin(events),
+            Box::new(|| Box::pin(async { Ok(vec![json!({ "id": "a" })]) })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(live.snapshot().len(), 1);
+
+        let closed = stream::iter([state(ConnectionState::Closed)]);
+        let list: ListFn<'_> = Box::new(|| Box::pin(async { Ok(Vec::new()) }));
+        let result =
+            LiveQuery::<Value>::open("posts".into(), None, None, None, Box::pin(closed), list);
+        assert!(matches!(result.await, Err(CopepodError::Sse(_))));
+    }
+
+    #[test]
+    fn trims_to_the_page_size() {
+        let mut live = live(None, Some("-views"), Some(3));
+        live.records.reverse();
+        let diff = live
+            .apply_event(&event(RecordAction::Create, "d", 6))
+            .unwrap();
+        assert_eq!(
+            diff,
+            [
+                LiveDiff::Inserted { index: 1 },
+                LiveDiff::Removed { index: 3 }
+            ]
+        );
+        assert_eq!(ids(&live), ["c", "d", "b"]);
+        let diff = live
+            .apply_event(&event(RecordAction::Create, "e", 1))
+            .unwrap();
+        assert!(diff.is_empty());
+        let diff = live
+            .apply_event(&event(RecordAction::Other("restore".into()), "f", 7))
+            .unwrap();
+        assert!(diff.is_empty());
+    }
+}
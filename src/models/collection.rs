@@ -1,5 +1,8 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 /// A collection within an application.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,21 +15,488 @@ pub struct Collection {
     #[serde(default)]
     pub fields: Vec<CollectionField>,
     #[serde(default)]
-    pub indexes: Vec<serde_json::Value>,
+    pub indexes: Vec<CollectionIndex>,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
 }
 
 /// A field definition within a collection.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `options` are parsed according to `field_type`; options that don't match
+/// the expected shape are kept as [`FieldOptions::Other`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RawCollectionField", into = "RawCollectionField")]
 pub struct CollectionField {
     pub name: String,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub unique: bool,
+    pub options: Option<FieldOptions>,
+}
+
+impl CollectionField {
+    pub fn new(name: impl Into<String>, field_type: FieldType) -> Self {
+        Self {
+            name: name.into(),
+            field_type,
+            required: false,
+            unique: false,
+            options: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawCollectionField {
+    name: String,
     #[serde(rename = "type")]
-    pub field_type: String,
+    field_type: FieldType,
     #[serde(default)]
-    pub required: bool,
+    required: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    unique: bool,
     #[serde(default)]
+    options: Option<Value>,
+}
+
+impl From<RawCollectionField> for CollectionField {
+    fn from(raw: RawCollectionField) -> Self {
+        let options = raw
+            .options
+            .map(|options| FieldOptions::parse(&raw.field_type, options));
+        Self {
+            name: raw.name,
+            field_type: raw.field_type,
+            required: raw.required,
+            unique: raw.unique,
+            options,
+        }
+    }
+}
+
+impl From<CollectionField> for RawCollectionField {
+    fn from(field: CollectionField) -> Self {
+        Self {
+            name: field.name,
+            field_type: field.field_type,
+            required: field.required,
+            unique: field.unique,
+            options: field.options.map(FieldOptions::into_value),
+        }
+    }
+}
+
+/// The type of a collection field.
+///
+/// Unknown types deserialize to [`FieldType::Other`] and serialize back
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum FieldType {
+    Text,
+    Editor,
+    Number,
+    Bool,
+    Email,
+    Url,
+    Date,
+    Autodate,
+    Select,
+    File,
+    Relation,
+    Json,
+    Other(String),
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Text => "text",
+            Self::Editor => "editor",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::Email => "email",
+            Self::Url => "url",
+            Self::Date => "date",
+            Self::Autodate => "autodate",
+            Self::Select => "select",
+            Self::File => "file",
+            Self::Relation => "relation",
+            Self::Json => "json",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<&str> for FieldType {
+    fn from(s: &str) -> Self {
+        match s {
+            "text" => Self::Text,
+            "editor" => Self::Editor,
+            "number" => Self::Number,
+            "bool" => Self::Bool,
+            "email" => Self::Email,
+            "url" => Self::Url,
+            "date" => Self::Date,
+            "autodate" => Self::Autodate,
+            "select" => Self::Select,
+            "file" => Self::File,
+            "relation" => Self::Relation,
+            "json" => Self::Json,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for FieldType {
+    fn from(s: String) -> Self {
+        match Self::from(s.as_str()) {
+            Self::Other(_) => Self::Other(s),
+            known => known,
+        }
+    }
+}
+
+impl From<FieldType> for String {
+    fn from(field_type: FieldType) -> Self {
+        match field_type {
+            FieldType::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Type-specific options of a collection field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FieldOptions {
+    /// Options of `text`, `editor`, `email` and `url` fields.
+    Text(TextOptions),
+    Number(NumberOptions),
+    Select(SelectOptions),
+    File(FileOptions),
+    Relation(RelationOptions),
+    /// Options of other field types, or options that didn't parse.
+    Other(Value),
+}
+
+impl FieldOptions {
+    /// Parse raw options for a field of the given type.
+    pub fn parse(field_type: &FieldType, value: Value) -> Self {
+        fn typed<T: serde::de::DeserializeOwned>(
+            value: Value,
+            wrap: fn(T) -> FieldOptions,
+        ) -> FieldOptions {
+            match serde_json::from_value(value.clone()) {
+                Ok(options) => wrap(options),
+                Err(_) => FieldOptions::Other(value),
+            }
+        }
+
+        if !value.is_object() {
+            return Self::Other(value);
+        }
+        match field_type {
+            FieldType::Text | FieldType::Editor | FieldType::Email | FieldType::Url => {
+                typed(value, Self::Text)
+            }
+            FieldType::Number => typed(value, Self::Number),
+            FieldType::Select => typed(value, Self::Select),
+            FieldType::File => typed(value, Self::File),
+            FieldType::Relation => typed(value, Self::Relation),
+            _ => Self::Other(value),
+        }
+    }
+
+    /// Serialize back to the raw JSON options object.
+    pub fn into_value(self) -> Value {
+        match self {
+            Self::Other(value) => value,
+            typed => serde_json::to_value(typed).unwrap_or(Value::Null),
+        }
+    }
+
+    pub fn text(&self) -> Option<&TextOptions> {
+        match self {
+            Self::Text(options) => Some(options),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> Option<&NumberOptions> {
+        match self {
+            Self::Number(options) => Some(options),
+            _ => None,
+        }
+    }
+
+    pub fn select(&self) -> Option<&SelectOptions> {
+        match self {
+            Self::Select(options) => Some(options),
+            _ => None,
+        }
+    }
+
+    pub fn file(&self) -> Option<&FileOptions> {
+        match self {
+            Self::File(options) => Some(options),
+            _ => None,
+        }
+    }
+
+    pub fn relation(&self) -> Option<&RelationOptions> {
+        match self {
+            Self::Relation(options) => Some(options),
+            _ => None,
+        }
+    }
+}
+
+/// Options of text-like fields. `min`/`max` bound the length in characters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Options not known to this SDK version.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NumberOptions {
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_number"
+    )]
+    pub min: Option<f64>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_number"
+    )]
+    pub max: Option<f64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectOptions {
+    /// The allowed values; any value when unset or empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    /// Maximum number of selected values; a single value when unset or 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_select: Option<u32>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileOptions {
+    /// Maximum number of files; multiple files when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u32>,
+    /// Maximum size of each file in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_types: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelationOptions {
+    /// Name of the target collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Maximum number of related records; multiple records when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_select: Option<u32>,
+    /// Delete this record when the related record is deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cascade_delete: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Serialize whole numbers without a fraction, as they are usually sent.
+fn serialize_number<S: serde::Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match value {
+        Some(n) if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 => {
+            serializer.serialize_i64(*n as i64)
+        }
+        other => other.serialize(serializer),
+    }
+}
+
+/// An index on a collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CollectionIndex {
+    Definition(IndexDefinition),
+    /// A raw `CREATE INDEX` statement.
+    Sql(String),
+    /// An index in a format not known to this SDK version.
+    Other(Value),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub unique: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A collection definition without server-assigned metadata.
+///
+/// Serializes as a create/update body for the collections API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionSchema {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection_type: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CollectionField>,
     #[serde(default)]
-    pub options: Option<serde_json::Value>,
+    pub indexes: Vec<CollectionIndex>,
+}
+
+impl From<&Collection> for CollectionSchema {
+    fn from(collection: &Collection) -> Self {
+        Self {
+            name: collection.name.clone(),
+            collection_type: collection.collection_type.clone(),
+            fields: collection.fields.clone(),
+            indexes: collection.indexes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fields_and_indexes_round_trip() {
+        let raw = json!({
+            "name": "posts",
+            "fields": [
+                {
+                    "name": "title", "type": "text", "required": true,
+                    "options": { "min": 3, "pattern": "^[A-Z]", "trim": true }
+                },
+                {
+                    "name": "author", "type": "relation", "required": false,
+                    "options": { "collection": "users", "max_select": 1, "cascade_delete": true }
+                },
+                {
+                    "name": "score", "type": "number", "required": false,
+                    "options": { "min": "not a number" }
+                },
+                { "name": "geo", "type": "geo_point", "required": false, "options": null }
+            ],
+            "indexes": [
+                { "name": "idx_title", "fields": ["title"], "unique": true },
+                "CREATE INDEX idx_author ON posts (author)",
+                { "expression": "lower(title)" }
+            ]
+        });
+
+        let schema: CollectionSchema = serde_json::from_value(raw.clone()).unwrap();
+        let title = schema.fields[0]
+            .options
+            .as_ref()
+            .and_then(FieldOptions::text);
+        assert_eq!(title.and_then(|o| o.min), Some(3));
+        assert_eq!(title.unwrap().extra["trim"], true);
+        let author = schema.fields[1]
+            .options
+            .as_ref()
+            .and_then(FieldOptions::relation);
+        assert_eq!(author.and_then(|o| o.collection.as_deref()), Some("users"));
+        assert_eq!(author.and_then(|o| o.cascade_delete), Some(true));
+        assert!(matches!(
+            schema.fields[2].options,
+            Some(FieldOptions::Other(_))
+        ));
+        assert_eq!(
+            schema.fields[3].field_type,
+            FieldType::Other("geo_point".into())
+        );
+        assert!(matches!(
+            &schema.indexes[0],
+            CollectionIndex::Definition(IndexDefinition { unique: true, .. })
+        ));
+        assert!(matches!(schema.indexes[1], CollectionIndex::Sql(_)));
+        assert!(matches!(schema.indexes[2], CollectionIndex::Other(_)));
+
+        assert_eq!(serde_json::to_value(&schema).unwrap(), raw);
+    }
+
+    #[test]
+    fn field_options_round_trip() {
+        let cases = [
+            (
+                "text",
+                json!({ "min": 1, "max": 80, "pattern": "^a", "trim": true }),
+            ),
+            ("number", json!({ "min": 1, "max": 2.5 })),
+            ("select", json!({ "max_select": 2 })),
+            ("select", json!({ "values": [] })),
+            ("select", json!({ "values": ["a", "b"], "max_select": 1 })),
+            ("file", json!({ "mime_types": [] })),
+            (
+                "file",
+                json!({ "max_files": 1, "max_size": 1024, "mime_types": ["image/png"] }),
+            ),
+            ("relation", json!({ "max_select": 1 })),
+            (
+                "relation",
+                json!({ "collection": "users", "cascade_delete": false }),
+            ),
+        ];
+        for (field_type, options) in cases {
+            let raw = json!({
+                "name": "f", "type": field_type, "required": false, "options": options
+            });
+            let field: CollectionField = serde_json::from_value(raw.clone()).unwrap();
+            assert!(
+                !matches!(field.options, Some(FieldOptions::Other(_))),
+                "{field_type} options {options} are not typed"
+            );
+            assert_eq!(serde_json::to_value(&field).unwrap(), raw);
+        }
+
+        let raw = json!({ "name": "f", "type": "text", "required": true, "unique": true });
+        let field: CollectionField = serde_json::from_value(raw.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&field).unwrap(),
+            json!({
+                "name": "f", "type": "text", "required": true, "unique": true, "options": null
+            })
+        );
+        let index = json!({ "fields": ["a"] });
+        let parsed: CollectionIndex = serde_json::from_value(index.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), index);
+    }
 }
@@ -186,6 +186,22 @@ pub struct DeploymentLogs {
     pub lines: Vec<String>,
 }
 
+/// One line of a deployment's container logs, as yielded by
+/// [`CopepodClient::follow_deployment_logs`](crate::CopepodClient::follow_deployment_logs).
+///
+/// The timestamp and labels are set when the server provides them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentLogLine {
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// The replica (pod) that wrote the line.
+    #[serde(default)]
+    pub replica: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    pub message: String,
+}
+
 /// Resource metrics for a deployment.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentMetrics {
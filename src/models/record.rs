@@ -1,9 +1,214 @@
-use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+
+/// What happened to the record in a [`RecordEvent`].
+///
+/// Unknown actions deserialize to [`RecordAction::Other`] and serialize back
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum RecordAction {
+    Create,
+    Update,
+    Delete,
+    Other(String),
+}
+
+impl RecordAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for RecordAction {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "create" => Self::Create,
+            "update" => Self::Update,
+            "delete" => Self::Delete,
+            _ => Self::Other(s),
+        }
+    }
+}
+
+impl From<RecordAction> for String {
+    fn from(action: RecordAction) -> Self {
+        match action {
+            RecordAction::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
 
 /// A real-time record event received via SSE.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordEvent {
-    pub action: String,
+    pub action: RecordAction,
     pub collection: String,
     pub record: serde_json::Value,
 }
+
+impl RecordEvent {
+    /// Deserialize the event's record into `T`, e.g. `Record<Post>`.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(T::deserialize(&self.record)?)
+    }
+}
+
+/// A record with its system fields, wrapping user-defined data.
+///
+/// The user data is flattened, so a `Record<Note>` deserializes from
+/// `{"id": "...", "created": "...", "updated": "...", "title": "..."}`.
+/// `Record<T>` derefs to `T` for direct field access.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record<T> {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    /// Relations expanded with `expand`, keyed by field name.
+    #[serde(default, skip_serializing_if = "Expanded::is_empty")]
+    pub expand: Expanded,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> Record<T> {
+    /// Consume the record and return the user data.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T> Deref for Record<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<T> DerefMut for Record<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+}
+
+/// The `expand` map of a record: expanded relations keyed by field name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Expanded(Map<String, Value>);
+
+impl Expanded {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return the raw expanded values.
+    pub fn as_map(&self) -> &Map<String, Value> {
+        &self.0
+    }
+
+    /// Deserialize the relation at `path` into `U`.
+    ///
+    /// `path` is a field name, or a dotted path like `author.profile` that
+    /// follows the `expand` maps of nested expanded records. Use
+    /// `Record<T>` for single relations and `Vec<Record<T>>` for multiple
+    /// ones. Returns `None` if the relation was not expanded.
+    pub fn get<U: DeserializeOwned>(&self, path: &str) -> Result<Option<U>> {
+        let mut segments = path.split('.');
+        let Some(mut value) = segments.next().and_then(|first| self.0.get(first)) else {
+            return Ok(None);
+        };
+        for segment in segments {
+            match value.get("expand").and_then(|e| e.get(segment)) {
+                Some(next) => value = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(U::deserialize(value)?))
+    }
+}
+
+/// A relation field that holds either the related record's ID or, when the
+/// relation was expanded in a query with
+/// [`inline_expand`](crate::query::RecordQueryBuilder::inline_expand), the
+/// record itself.
+///
+/// Always serializes as the ID, so it can be sent back in record bodies.
+#[derive(Debug, Clone)]
+pub enum Relation<T> {
+    Id(String),
+    Expanded(Box<Record<T>>),
+}
+
+impl<T> Relation<T> {
+    /// Return the related record ID.
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Id(id) => id,
+            Self::Expanded(record) => &record.id,
+        }
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        matches!(self, Self::Expanded(_))
+    }
+
+    /// Return the expanded record, if the relation was expanded.
+    pub fn expanded(&self) -> Option<&Record<T>> {
+        match self {
+            Self::Id(_) => None,
+            Self::Expanded(record) => Some(record),
+        }
+    }
+
+    /// Consume the relation and return the expanded record, if any.
+    pub fn into_expanded(self) -> Option<Record<T>> {
+        match self {
+            Self::Id(_) => None,
+            Self::Expanded(record) => Some(*record),
+        }
+    }
+}
+
+impl<T> From<String> for Relation<T> {
+    fn from(id: String) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl<T> From<&str> for Relation<T> {
+    fn from(id: &str) -> Self {
+        Self::Id(id.to_string())
+    }
+}
+
+impl<T> Serialize for Relation<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Relation<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(id) => Ok(Self::Id(id)),
+            value => serde_json::from_value(value)
+                .map(|record| Self::Expanded(Box::new(record)))
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+
+use crate::error::Result;
+use crate::models::ListResult;
+
+/// Default number of items requested per page.
+pub const DEFAULT_PER_PAGE: u32 = 100;
+
+type PageFetcher<'a, T> =
+    Arc<dyn Fn(u32, u32) -> BoxFuture<'a, Result<ListResult<T>>> + Send + Sync + 'a>;
+
+/// Walks every page of a `ListResult` endpoint and yields the items one by one.
+///
+/// The first page is fetched on its own to learn `total_pages`; the remaining
+/// pages are then requested with up to `prefetch` requests in flight, while
+/// items are still yielded in page order. The stream ends after the first
+/// error.
+pub struct Paginator<'a, T> {
+    fetch: PageFetcher<'a, T>,
+    start_page: u32,
+    per_page: u32,
+    prefetch: usize,
+}
+
+impl<'a, T: Send + 'a> Paginator<'a, T> {
+    /// Create a paginator from a function fetching `(page, per_page)`.
+    pub fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn(u32, u32) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = Result<ListResult<T>>> + Send + 'a,
+    {
+        Self {
+            fetch: Arc::new(move |page, per_page| Box::pin(fetch(page, per_page))),
+            start_page: 1,
+            per_page: DEFAULT_PER_PAGE,
+            prefetch: 1,
+        }
+    }
+
+    /// Set the number of items requested per page (default: 100).
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// Set how many pages may be fetched concurrently (default: 1).
+    pub fn prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages.max(1);
+        self
+    }
+
+    /// Set the page to start from (default: 1).
+    pub fn start_page(mut self, page: u32) -> Self {
+        self.start_page = page.max(1);
+        self
+    }
+
+    /// Stream every item across all pages.
+    pub fn stream(self) -> impl Stream<Item = Result<T>> + Send + 'a {
+        let Paginator {
+            fetch,
+            start_page,
+            per_page,
+            prefetch,
+        } = self;
+
+        stream::unfold(PageState::Start, move |mut state| {
+            let fetch = fetch.clone();
+            async move {
+                loop {
+                    state = match state {
+                        PageState::Start => match fetch(start_page, per_page).await {
+                            Ok(first) => {
+                                let fetch = fetch.clone();
+                                let rest = stream::iter(start_page + 1..=first.total_pages)
+                                    .map(move |page| fetch(page, per_page))
+                                    .buffered(prefetch)
+                                    .boxed();
+                                PageState::Running {
+                                    items: first.items.into_iter(),
+                                    rest,
+                                }
+                            }
+                            Err(e) => return Some((Err(e), PageState::Done)),
+                        },
+                        PageState::Running {
+                            mut items,
+                            mut rest,
+                        } => {
+                            if let Some(item) = items.next() {
+                                return Some((Ok(item), PageState::Running { items, rest }));
+                            }
+                            match rest.next().await {
+                                Some(Ok(page)) => PageState::Running {
+                                    items: page.items.into_iter(),
+                                    rest,
+                                },
+                                Some(Err(e)) => return Some((Err(e), PageState::Done)),
+                                None => return None,
+                            }
+                        }
+                        PageState::Done => return None,
+                    };
+                }
+            }
+        })
+    }
+
+    /// Collect every item across all pages into a `Vec`.
+    pub async fn all(self) -> Result<Vec<T>> {
+        self.stream().try_collect().await
+    }
+}
+
+enum PageState<'a, T> {
+    Start,
+    Running {
+        items: std::vec::IntoIter<T>,
+        rest: BoxStream<'a, Result<ListResult<T>>>,
+    },
+    Done,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CopepodError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn page_of(page: u32, per_page: u32, total: u32) -> ListResult<u32> {
+        let start = (page - 1) * per_page;
+        let end = (start + per_page).min(total);
+        ListResult {
+            page,
+            per_page,
+            total_items: total as u64,
+            total_pages: total.div_ceil(per_page),
+            items: (start..end).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn collects_all_pages_in_order() {
+        let paginator =
+            Paginator::new(|page, per_page| async move { Ok(page_of(page, per_page, 23)) })
+                .per_page(5)
+                .prefetch(3);
+
+        let items = paginator.all().await.unwrap();
+        assert_eq!(items, (0..23).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn empty_result_yields_nothing() {
+        let items = Paginator::new(|page, per_page| async move { Ok(page_of(page, per_page, 0)) })
+            .all()
+            .await
+            .unwrap();
+        assert!(items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_after_first_error() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let paginator = Paginator::new(move |page, per_page| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if page == 2 {
+                    Err(CopepodError::Sse("boom".into()))
+                } else {
+                    Ok(page_of(page, per_page, 10))
+                }
+            }
+        })
+        .per_page(2);
+
+        let results: Vec<_> = paginator.stream().collect().await;
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
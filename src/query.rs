@@ -1,11 +1,23 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::pin::pin;
+
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 
 use crate::client::CopepodClient;
-use crate::error::Result;
+use crate::error::{CopepodError, Result};
+use crate::live::LiveQuery;
 use crate::models::ListResult;
+use crate::pagination::{Paginator, DEFAULT_PER_PAGE};
 
 /// Fluent builder for querying records in a collection.
-pub struct RecordQueryBuilder<'a> {
+///
+/// Records are returned as `T`, which defaults to raw `serde_json::Value`.
+/// Use [`RecordQueryBuilder::typed`] to deserialize into your own types.
+pub struct RecordQueryBuilder<'a, T = Value> {
     client: &'a CopepodClient,
     path: String,
     filter: Option<String>,
@@ -14,6 +26,25 @@ pub struct RecordQueryBuilder<'a> {
     fields: Option<String>,
     page: Option<u32>,
     per_page: Option<u32>,
+    inline_expand: bool,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for RecordQueryBuilder<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client,
+            path: self.path.clone(),
+            filter: self.filter.clone(),
+            sort: self.sort.clone(),
+            expand: self.expand.clone(),
+            fields: self.fields.clone(),
+            page: self.page,
+            per_page: self.per_page,
+            inline_expand: self.inline_expand,
+            _record: PhantomData,
+        }
+    }
 }
 
 impl<'a> RecordQueryBuilder<'a> {
@@ -27,6 +58,49 @@ impl<'a> RecordQueryBuilder<'a> {
             fields: None,
             page: None,
             per_page: None,
+            inline_expand: false,
+            _record: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> RecordQueryBuilder<'a, T> {
+    /// Deserialize records returned by this query into `U` instead of `T`.
+    ///
+    /// Relation fields keep their raw IDs; expanded records stay in each
+    /// record's `expand` map unless [`inline_expand`](Self::inline_expand)
+    /// is set.
+    pub fn typed<U>(self) -> RecordQueryBuilder<'a, U> {
+        self.retype()
+    }
+
+    /// Copy expanded records from each record's `expand` map into the
+    /// relation fields themselves (at every level), so
+    /// [`Relation<T>`](crate::models::Relation) fields deserialize as
+    /// expanded records.
+    ///
+    /// Relation fields typed as `String` or
+    /// [`RelationId<T>`](crate::schema::RelationId) can't hold an expanded
+    /// record, so only use this when every expanded field is a `Relation<T>`
+    /// (or untyped).
+    pub fn inline_expand(mut self) -> Self {
+        self.inline_expand = true;
+        self
+    }
+
+    /// Change the record type, keeping whether expansions are inlined.
+    fn retype<U>(self) -> RecordQueryBuilder<'a, U> {
+        RecordQueryBuilder {
+            client: self.client,
+            path: self.path,
+            filter: self.filter,
+            sort: self.sort,
+            expand: self.expand,
+            fields: self.fields,
+            page: self.page,
+            per_page: self.per_page,
+            inline_expand: self.inline_expand,
+            _record: PhantomData,
         }
     }
 
@@ -48,6 +122,16 @@ impl<'a> RecordQueryBuilder<'a> {
         self
     }
 
+    /// Add a relation to expand, e.g. `author` or a multi-level path like
+    /// `author.profile`, keeping the ones already set.
+    pub fn expand_path(mut self, path: &str) -> Self {
+        self.expand = Some(match self.expand.take() {
+            Some(expand) if !expand.is_empty() => format!("{expand},{path}"),
+            _ => path.to_string(),
+        });
+        self
+    }
+
     /// Set which fields to return.
     pub fn fields(mut self, fields: &str) -> Self {
         self.fields = Some(fields.to_string());
@@ -66,6 +150,14 @@ impl<'a> RecordQueryBuilder<'a> {
         self
     }
 
+    /// Key identifying this query's path and parameters, for caching.
+    pub(crate) fn cache_key(&self) -> String {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.build_query())
+            .finish();
+        format!("{}?{query}", self.path)
+    }
+
     /// Build the query string from accumulated parameters.
     fn build_query(&self) -> Vec<(String, String)> {
         let mut params = Vec::new();
@@ -89,9 +181,11 @@ impl<'a> RecordQueryBuilder<'a> {
         }
         params
     }
+}
 
+impl<T: DeserializeOwned> RecordQueryBuilder<'_, T> {
     /// Execute the query and return a paginated list of records.
-    pub async fn list(self) -> Result<ListResult<Value>> {
+    pub async fn list(self) -> Result<ListResult<T>> {
         let query = self.build_query();
         let resp = self
             .client
@@ -100,11 +194,29 @@ impl<'a> RecordQueryBuilder<'a> {
             .query(&query)
             .send()
             .await?;
-        CopepodClient::handle_response_pub(resp).await
+        if !self.inlines_expand() {
+            return CopepodClient::handle_response_pub(resp).await;
+        }
+        let page: ListResult<Value> = CopepodClient::handle_response_pub(resp).await?;
+        let items = page
+            .items
+            .into_iter()
+            .map(|mut item| {
+                inline_expand(&mut item);
+                serde_json::from_value(item)
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(ListResult {
+            page: page.page,
+            per_page: page.per_page,
+            total_items: page.total_items,
+            total_pages: page.total_pages,
+            items,
+        })
     }
 
     /// Get a single record by ID.
-    pub async fn get_one(self, id: &str) -> Result<Value> {
+    pub async fn get_one(self, id: &str) -> Result<T> {
         let path = format!("{}/{}", self.path, id);
         let query = self.build_query();
         let resp = self
@@ -114,7 +226,417 @@ impl<'a> RecordQueryBuilder<'a> {
             .query(&query)
             .send()
             .await?;
-        CopepodClient::handle_response_pub(resp).await
+        if !self.inlines_expand() {
+            return CopepodClient::handle_response_pub(resp).await;
+        }
+        let mut record: Value = CopepodClient::handle_response_pub(resp).await?;
+        inline_expand(&mut record);
+        Ok(serde_json::from_value(record)?)
+    }
+
+    fn inlines_expand(&self) -> bool {
+        self.inline_expand && self.expand.as_deref().is_some_and(|e| !e.is_empty())
+    }
+}
+
+/// Copy each entry of a record's `expand` map over the relation field of the
+/// same name, recursing into nested expansions.
+fn inline_expand(record: &mut Value) {
+    match record {
+        Value::Array(items) => items.iter_mut().for_each(inline_expand),
+        Value::Object(map) => {
+            let Some(Value::Object(expand)) = map.get_mut("expand") else {
+                return;
+            };
+            expand.values_mut().for_each(inline_expand);
+            let expanded: Vec<(String, Value)> =
+                expand.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            map.extend(expanded);
+        }
+        _ => {}
+    }
+}
+
+impl<T: DeserializeOwned> RecordQueryBuilder<'_, T> {
+    /// Count the records matching the query.
+    ///
+    /// Requests a single `id`-only record and reads `total_items`.
+    pub async fn count(self) -> Result<u64> {
+        let page = self
+            .retype::<Value>()
+            .fields("id")
+            .page(1)
+            .per_page(1)
+            .list()
+            .await?;
+        Ok(page.total_items)
+    }
+
+    /// Check whether any record matches the query.
+    pub async fn exists(self) -> Result<bool> {
+        Ok(self.count().await? > 0)
+    }
+
+    /// Return the first record matching the query, if any.
+    pub async fn first(self) -> Result<Option<T>> {
+        let page = self.page(1).per_page(1).list().await?;
+        Ok(page.items.into_iter().next())
+    }
+}
+
+impl<'a, T: Send + 'a> RecordQueryBuilder<'a, T> {
+    /// Sum a numeric field over every matching record.
+    ///
+    /// Records where the field is missing or `null` are skipped.
+    pub async fn sum(self, field: &str) -> Result<f64> {
+        self.fold_field(field, 0.0, |sum, value| match value.as_f64() {
+            Some(n) => Ok(sum + n),
+            None => Err(CopepodError::Query(format!(
+                "cannot sum non-numeric value {value} in `{field}`"
+            ))),
+        })
+        .await
+    }
+
+    /// Return the smallest value of a field over every matching record.
+    ///
+    /// Numbers compare numerically and strings lexicographically (which
+    /// orders RFC 3339 timestamps correctly). Missing and `null` values are
+    /// skipped.
+    pub async fn min<V: DeserializeOwned>(self, field: &str) -> Result<Option<V>> {
+        self.extreme(field, Ordering::Less).await
+    }
+
+    /// Return the largest value of a field over every matching record.
+    ///
+    /// See [`RecordQueryBuilder::min`] for how values are compared.
+    pub async fn max<V: DeserializeOwned>(self, field: &str) -> Result<Option<V>> {
+        self.extreme(field, Ordering::Greater).await
+    }
+
+    /// Count matching records per distinct value of a field.
+    ///
+    /// String values are used as-is for keys; other values use their JSON
+    /// representation. Records without the field are counted under `"null"`.
+    pub async fn group_by(self, field: &str) -> Result<BTreeMap<String, u64>> {
+        let mut groups = BTreeMap::new();
+        let mut records = pin!(self.field_values(field));
+        while let Some(value) = records.try_next().await? {
+            let key = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            *groups.entry(key).or_insert(0) += 1;
+        }
+        Ok(groups)
+    }
+
+    async fn extreme<V: DeserializeOwned>(self, field: &str, keep: Ordering) -> Result<Option<V>> {
+        let best = self
+            .fold_field(field, None, |best: Option<Value>, value| match best {
+                None => Ok(Some(value.clone())),
+                Some(current) => match compare_values(value, &current) {
+                    Some(ordering) if ordering == keep => Ok(Some(value.clone())),
+                    Some(_) => Ok(Some(current)),
+                    None => Err(CopepodError::Query(format!(
+                        "cannot compare {value} with {current} in `{field}`"
+                    ))),
+                },
+            })
+            .await?;
+        best.map(|v| Ok(serde_json::from_value(v)?)).transpose()
+    }
+
+    /// Fold the non-null values of `field` across every matching record.
+    async fn fold_field<A>(
+        self,
+        field: &str,
+        init: A,
+        mut f: impl FnMut(A, &Value) -> Result<A>,
+    ) -> Result<A> {
+        let mut acc = init;
+        let mut values = pin!(self.field_values(field));
+        while let Some(value) = values.try_next().await? {
+            if !value.is_null() {
+                acc = f(acc, &value)?;
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Stream the value of `field` from every matching record, fetching only
+    /// that field.
+    fn field_values(self, field: &str) -> impl Stream<Item = Result<Value>> + Send + 'a {
+        let field = field.to_string();
+        // Aggregates see the stored value, never an inlined expansion.
+        let mut query = self.retype::<Value>();
+        query.inline_expand = false;
+        query
+            .fields(&field)
+            .stream()
+            .map_ok(move |mut record| record.get_mut(&field).map(Value::take).unwrap_or_default())
+    }
+}
+
+impl<'a, T: DeserializeOwned + Send + 'a> RecordQueryBuilder<'a, T> {
+    /// Turn the query into a [`Paginator`] over every matching record.
+    ///
+    /// The page size defaults to the query's `per_page` (or 100), and the
+    /// walk starts at the query's `page` (or 1).
+    pub fn paginate(self) -> Paginator<'a, T> {
+        let start_page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        Paginator::new(move |page, per_page| self.clone().page(page).per_page(per_page).list())
+            .start_page(start_page)
+            .per_page(per_page)
+    }
+
+    /// Stream every matching record across all pages.
+    pub fn stream(self) -> impl Stream<Item = Result<T>> + Send + 'a {
+        self.paginate().stream()
+    }
+
+    /// Fetch every matching record across all pages.
+    pub async fn all(self) -> Result<Vec<T>> {
+        self.paginate().all().await
+    }
+}
+
+impl<'a, T: DeserializeOwned + Send + 'a> RecordQueryBuilder<'a, T> {
+    /// Walk every matching record using keyset (cursor) pagination.
+    ///
+    /// Records are sorted on `(sort_field, id)` and each page is requested
+    /// with a filter continuing after the last record seen, so records
+    /// inserted during the walk can't shift others between pages. Prefix the
+    /// field with `-` to walk in descending order. The builder's `sort` and
+    /// `page` are replaced; its `filter` is kept and combined with the cursor.
+    ///
+    /// The walk ends on an empty page or one the server reports as the last,
+    /// so a server capping the page size below `per_page` still yields every
+    /// record. Fails with [`CopepodError::Query`] if the field isn't an
+    /// identifier.
+    pub fn iter_by_cursor(self, sort_field: &str) -> impl Stream<Item = Result<T>> + Send + 'a {
+        let (field, descending) = match sort_field.strip_prefix('-') {
+            Some(field) => (field.to_string(), true),
+            None => (sort_field.to_string(), false),
+        };
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        let base_filter = self.filter.clone();
+        let start = if is_field_name(&field) {
+            CursorState::Fetch(None)
+        } else {
+            CursorState::Failed(CopepodError::Query(format!(
+                "cannot walk by `{field}`: not a field name"
+            )))
+        };
+
+        let mut query = self.retype::<Value>().per_page(per_page);
+        query.page = None;
+        query.sort = Some(if field == "id" {
+            format!("{}id", if descending { "-" } else { "" })
+        } else if descending {
+            format!("-{field},-id")
+        } else {
+            format!("{field},id")
+        });
+        if let Some(ref mut fields) = query.fields {
+            for required in [field.as_str(), "id"] {
+                if !fields.split(',').any(|f| f.trim() == required) {
+                    fields.push(',');
+                    fields.push_str(required);
+                }
+            }
+        }
+
+        stream::unfold(start, move |mut state| {
+            let query = query.clone();
+            let base_filter = base_filter.clone();
+            let field = field.clone();
+            async move {
+                loop {
+                    state = match state {
+                        CursorState::Fetch(cursor) => {
+                            let filter = match cursor {
+                                None => base_filter.clone(),
+                                Some(last) => match cursor_filter(&field, descending, &last) {
+                                    Ok(cursor) => Some(match &base_filter {
+                                        Some(base) => format!("({base}) && {cursor}"),
+                                        None => cursor,
+                                    }),
+                                    Err(e) => return Some((Err(e), CursorState::Done)),
+                                },
+                            };
+                            let mut page_query = query.clone();
+                            page_query.filter = filter;
+                            match page_query.list().await {
+                                Ok(page) => {
+                                    let last_page = page.items.is_empty() || page.total_pages <= 1;
+                                    let next = match page.items.last() {
+                                        Some(last) if !last_page => Some(last.clone()),
+                                        _ => None,
+                                    };
+                                    CursorState::Yield {
+                                        items: page.items.into_iter(),
+                                        next,
+                                    }
+                                }
+                                Err(e) => return Some((Err(e), CursorState::Done)),
+                            }
+                        }
+                        CursorState::Yield { mut items, next } => match items.next() {
+                            Some(item) => {
+                                return Some((Ok(item), CursorState::Yield { items, next }))
+                            }
+                            None => match next {
+                                Some(last) => CursorState::Fetch(Some(last)),
+                                None => return None,
+                            },
+                        },
+                        CursorState::Failed(e) => return Some((Err(e), CursorState::Done)),
+                        CursorState::Done => return None,
+                    };
+                }
+            }
+        })
+        .map(|item| item.and_then(|value| Ok(serde_json::from_value(value)?)))
+    }
+}
+
+impl<'a, T: DeserializeOwned + Clone> RecordQueryBuilder<'a, T> {
+    /// List the matching records, then keep the result set current with the
+    /// collection's realtime record events.
+    ///
+    /// The subscription is connected before the list is requested, so no
+    /// change made in between is missed. The query's filter and sort are
+    /// applied locally to each event; see [`LiveQuery`] for the details.
+    /// With `per_page` set, the first page of that size is watched;
+    /// otherwise every matching record is listed across all pages.
+    /// Fails if the query sets `page`, since events can't be placed on a
+    /// later page, if the filter cannot be evaluated locally, or if the
+    /// realtime connection cannot be opened.
+    pub async fn live(self) -> Result<LiveQuery<'a, T>> {
+        let (org_id, app_id, collection) = self.record_scope().ok_or_else(|| {
+            CopepodError::Query(format!("cannot watch records at `{}`", self.path))
+        })?;
+        if let Some(page) = self.page {
+            return Err(CopepodError::Query(format!(
+                "cannot watch page {page}: a live query always starts at the first record"
+            )));
+        }
+        let events = self
+            .client
+            .subscription(&org_id, &app_id)
+            .collection(&collection)
+            .stream()?;
+        let filter = self.filter.clone();
+        let sort = self.sort.clone();
+        let limit = self.per_page.map(|n| n as usize);
+        let query = self.retype::<Value>();
+        let list = Box::new(move || {
+            let query = query.clone();
+            Box::pin(async move {
+                match limit {
+                    Some(_) => Ok(query.list().await?.items),
+                    None => query.all().await,
+                }
+            }) as _
+        });
+        LiveQuery::open(
+            collection,
+            filter.as_deref(),
+            sort.as_deref(),
+            limit,
+            Box::pin(events),
+            list,
+        )
+        .await
+    }
+
+    /// The org, app and collection of a records path.
+    fn record_scope(&self) -> Option<(String, String, String)> {
+        let rest = self.path.strip_prefix("api/platform/orgs/")?;
+        match rest.split('/').collect::<Vec<_>>()[..] {
+            [org_id, "apps", app_id, "records", collection] => Some((
+                org_id.to_string(),
+                app_id.to_string(),
+                collection.to_string(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+enum CursorState {
+    Fetch(Option<Value>),
+    Yield {
+        items: std::vec::IntoIter<Value>,
+        next: Option<Value>,
+    },
+    Failed(CopepodError),
+    Done,
+}
+
+/// Order two JSON values of the same kind; `None` for mismatched kinds.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Build the filter continuing after `last` in `(field, id)` order.
+fn cursor_filter(field: &str, descending: bool, last: &Value) -> Result<String> {
+    let op = if descending { "<" } else { ">" };
+    let id = last
+        .get("id")
+        .ok_or_else(|| CopepodError::Query("cursor record has no `id` field".into()))?;
+    let id = filter_literal(id)?;
+    if field == "id" {
+        return Ok(format!("id {op} {id}"));
+    }
+    let value = last
+        .get(field)
+        .ok_or_else(|| CopepodError::Query(format!("cursor record has no `{field}` field")))?;
+    if value.is_null() {
+        return Err(CopepodError::Query(format!(
+            "cursor record has a null `{field}`; cannot continue after it"
+        )));
+    }
+    let value = filter_literal(value)?;
+    Ok(format!(
+        "({field} {op} {value} || ({field} = {value} && id {op} {id}))"
+    ))
+}
+
+/// Whether `field` is an identifier (`[A-Za-z_][A-Za-z0-9_.]*`) that can be
+/// put into a filter as-is.
+pub(crate) fn is_field_name(field: &str) -> bool {
+    let mut chars = field.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Render a JSON value as a literal in a filter expression.
+///
+/// Strings are single-quoted with quotes and backslashes escaped, so
+/// untrusted input can't break out of the literal. Arrays and objects are
+/// rejected.
+pub fn filter_literal(value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(format!(
+            "'{}'",
+            s.replace('\\', "\\\\").replace('\'', "\\'")
+        )),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Null => Ok("null".to_string()),
+        Value::Array(_) | Value::Object(_) => Err(CopepodError::Query(format!(
+            "cannot use {value} as a filter literal"
+        ))),
     }
 }
 
@@ -159,4 +681,53 @@ mod tests {
         assert_eq!(params[4], ("page".to_string(), "2".to_string()));
         assert_eq!(params[5], ("per_page".to_string(), "50".to_string()));
     }
+
+    #[test]
+    fn test_cache_key_encodes_params() {
+        let key = make_test_builder().filter("a='x&sort=y'").cache_key();
+        assert_eq!(key, "test?filter=a%3D%27x%26sort%3Dy%27");
+        assert_ne!(
+            key,
+            make_test_builder().filter("a='x").sort("y'").cache_key()
+        );
+    }
+
+    #[test]
+    fn test_filter_literal_escapes_strings() {
+        assert_eq!(filter_literal(&Value::from("it's")).unwrap(), r"'it\'s'");
+        assert_eq!(filter_literal(&Value::from(r"a\b")).unwrap(), r"'a\\b'");
+        assert_eq!(filter_literal(&Value::from(42)).unwrap(), "42");
+        assert_eq!(filter_literal(&Value::Bool(true)).unwrap(), "true");
+        assert!(filter_literal(&serde_json::json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_cursor_filter() {
+        let last = serde_json::json!({ "id": "r9", "created": "2024-01-01" });
+        assert_eq!(
+            cursor_filter("created", false, &last).unwrap(),
+            "(created > '2024-01-01' || (created = '2024-01-01' && id > 'r9'))"
+        );
+        assert_eq!(cursor_filter("id", true, &last).unwrap(), "id < 'r9'");
+        assert!(cursor_filter("missing", false, &last).is_err());
+        let null = serde_json::json!({ "id": "r9", "created": null });
+        assert!(matches!(
+            cursor_filter("created", false, &null),
+            Err(CopepodError::Query(_))
+        ));
+    }
+
+    #[test]
+    fn test_compare_values() {
+        use serde_json::json;
+        assert_eq!(
+            compare_values(&json!(2), &json!(10.5)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare_values(&json!("2024-02-01"), &json!("2024-01-31")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare_values(&json!(1), &json!("1")), None);
+    }
 }
@@ -0,0 +1,475 @@
+use std::collections::VecDeque;
+use std::pin::{pin, Pin};
+use std::time::Duration;
+
+use chrono::Utc;
+use eventsource_stream::{Event, Eventsource};
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use reqwest::Method;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::client::CopepodClient;
+use crate::error::{is_retryable, CopepodError, Result};
+
+use super::websocket::WsStream;
+use super::RealtimeEvent;
+
+/// Transport used for realtime subscriptions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RealtimeTransport {
+    /// Server-sent events over HTTP.
+    #[default]
+    Sse,
+    /// A WebSocket, which is pinged to keep it alive and lets a
+    /// subscription change its targets without reconnecting.
+    ///
+    /// The socket is opened by an upgrade request sent through the client's
+    /// HTTP client, so it goes through the same proxy (including
+    /// `HTTP_PROXY` and `HTTPS_PROXY`) as the SSE transport.
+    WebSocket,
+}
+
+/// Options controlling how a realtime subscription reconnects.
+#[derive(Debug, Clone)]
+pub struct ReconnectOptions {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_attempts: Option<u32>,
+    pub(super) keepalive: Duration,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            keepalive: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the first reconnect attempt, doubled on each
+    /// further attempt (default: 500ms). A `retry:` field sent by the server
+    /// replaces it.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the maximum delay between reconnect attempts (default: 30s).
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Give up after this many consecutive failed attempts (default: never).
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Set how often WebSocket connections are pinged; a connection that
+    /// receives nothing between two pings is treated as dropped (default:
+    /// 30s). SSE connections rely on the server's keepalive events.
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    fn delay(&self, base: Option<Duration>, attempt: u32) -> Duration {
+        let base = base.unwrap_or(self.initial_backoff);
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        base.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// State of a realtime connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Opening the first connection.
+    Connecting,
+    /// The connection is open.
+    Connected,
+    /// The connection dropped; reconnecting after `delay`.
+    Reconnecting { attempt: u32, delay: Duration },
+    /// The subscription gave up and the stream ends.
+    Closed,
+}
+
+/// An item of a resilient realtime subscription.
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    State(ConnectionState),
+    Event(RealtimeEvent),
+    /// A [`RealtimeHub`](super::RealtimeHub) subscriber fell behind and
+    /// `skipped` events were dropped for it.
+    Lagged {
+        skipped: u64,
+    },
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
+
+/// An open connection of either transport.
+enum Events {
+    Sse(EventStream),
+    WebSocket(Box<WsStream>),
+}
+
+impl Events {
+    async fn next(&mut self) -> Option<Result<Event>> {
+        match self {
+            Self::Sse(events) => events.next().await,
+            Self::WebSocket(socket) => socket.next().await,
+        }
+    }
+}
+
+/// What woke a connected [`Connection`].
+enum Wake {
+    Event(Option<Result<Event>>),
+    Update(Option<Vec<(String, String)>>),
+    Rotate,
+}
+
+/// A realtime connection that reconnects when dropped, resuming from the
+/// last event ID it saw.
+///
+/// The access token is refreshed before connecting when it is about to
+/// expire, and an open connection is replaced with one using a refreshed
+/// token shortly before its token expires.
+pub(crate) struct Connection {
+    client: CopepodClient,
+    path: String,
+    query: Vec<(String, String)>,
+    transport: RealtimeTransport,
+    /// New targets for the connection, replacing `query`.
+    updates: Option<mpsc::UnboundedReceiver<Vec<(String, String)>>>,
+    options: ReconnectOptions,
+    last_event_id: Option<String>,
+    /// Reconnection time sent by the server in a `retry:` field.
+    server_retry: Option<Duration>,
+    attempt: u32,
+    wait: Option<Duration>,
+    events: Option<Events>,
+    /// When to reopen the connection with a refreshed token.
+    rotate_at: Option<Instant>,
+    /// Whether the token was refreshed after a rejected connection attempt,
+    /// since the last successful one.
+    refreshed: bool,
+    queue: VecDeque<Result<SubscriptionEvent>>,
+    closed: bool,
+}
+
+impl Connection {
+    pub(crate) fn new(
+        client: CopepodClient,
+        path: String,
+        query: Vec<(String, String)>,
+        options: ReconnectOptions,
+    ) -> Self {
+        Self {
+            transport: client.realtime_transport,
+            client,
+            path,
+            query,
+            updates: None,
+            options,
+            last_event_id: None,
+            server_retry: None,
+            attempt: 0,
+            wait: None,
+            events: None,
+            rotate_at: None,
+            refreshed: false,
+            queue: VecDeque::from([Ok(SubscriptionEvent::State(ConnectionState::Connecting))]),
+            closed: false,
+        }
+    }
+
+    pub(crate) fn transport(mut self, transport: RealtimeTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Take replacement targets from `updates`. WebSocket connections send
+    /// them over the open socket; SSE connections reconnect with them.
+    pub(crate) fn updates(
+        mut self,
+        updates: mpsc::UnboundedReceiver<Vec<(String, String)>>,
+    ) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    pub(crate) fn into_stream(self) -> impl Stream<Item = Result<SubscriptionEvent>> + Send {
+        stream::unfold(self, |mut connection| async move {
+            let item = connection.next().await?;
+            Some((item, connection))
+        })
+    }
+
+    async fn next(&mut self) -> Option<Result<SubscriptionEvent>> {
+        loop {
+            if let Some(item) = self.queue.pop_front() {
+                return Some(item);
+            }
+            if self.closed {
+                return None;
+            }
+            let Some(events) = &mut self.events else {
+                self.connect().await;
+                continue;
+            };
+            let updates = &mut self.updates;
+            let rotate_at = self.rotate_at;
+            let event = async { Wake::Event(events.next().await) };
+            let update = async {
+                match updates {
+                    Some(updates) => Wake::Update(updates.recv().await),
+                    None => future::pending().await,
+                }
+            };
+            let rotate = async {
+                match rotate_at {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => future::pending().await,
+                }
+                Wake::Rotate
+            };
+            let other = async {
+                future::select(pin!(update), pin!(rotate))
+                    .await
+                    .factor_first()
+                    .0
+            };
+            let wake = future::select(pin!(event), pin!(other))
+                .await
+                .factor_first()
+                .0;
+
+            match wake {
+                Wake::Event(Some(Ok(event))) => self.handle(event),
+                // Transport errors and a closed stream both mean the
+                // connection dropped.
+                Wake::Event(Some(Err(_)) | None) => {
+                    self.events = None;
+                    self.reconnect();
+                }
+                Wake::Update(Some(query)) => self.retarget(query),
+                Wake::Update(None) => self.updates = None,
+                Wake::Rotate => self.rotate().await,
+            }
+        }
+    }
+
+    /// Reopen the connection with a refreshed token. The new connection is
+    /// opened before the old one is dropped and resumes from the last event
+    /// ID, so no events are missed.
+    async fn rotate(&mut self) {
+        self.rotate_at = None;
+        match self.client.refresh_auth().await {
+            Ok(()) => match self.open().await {
+                Ok(events) => {
+                    self.events = Some(events);
+                    self.schedule_rotation().await;
+                }
+                Err(_) => {
+                    self.events = None;
+                    self.reconnect();
+                }
+            },
+            Err(e) if e.is_transient() => {
+                self.rotate_at = Some(Instant::now() + self.options.delay(None, 1));
+            }
+            // The token can't be refreshed; keep the connection until the
+            // server rejects the expired token.
+            Err(_) => {}
+        }
+    }
+
+    async fn schedule_rotation(&mut self) {
+        let pair = self.client.token_store.get().await;
+        self.rotate_at = pair
+            .filter(|_| self.client.auto_refresh)
+            .and_then(|pair| pair.refresh_at())
+            .map(|at| Instant::now() + (at - Utc::now()).to_std().unwrap_or_default());
+    }
+
+    fn retarget(&mut self, query: Vec<(String, String)>) {
+        match &mut self.events {
+            Some(Events::WebSocket(socket)) => socket.retarget(&query),
+            // SSE targets are fixed when connecting, so reopen at once.
+            _ => self.events = None,
+        }
+        self.query = query;
+    }
+
+    async fn connect(&mut self) {
+        if let Some(wait) = self.wait.take() {
+            tokio::time::sleep(wait).await;
+        }
+        // Targets changed while disconnected are used for the new connection.
+        while let Some(Ok(query)) = self.updates.as_mut().map(|u| u.try_recv()) {
+            self.query = query;
+        }
+        if let Err(e) = self.client.ensure_auth().await {
+            if e.is_transient() {
+                self.reconnect();
+            } else {
+                self.auth_failed(format!("token refresh failed: {e}"));
+            }
+            return;
+        }
+        match self.open().await {
+            Ok(events) => {
+                self.events = Some(events);
+                self.attempt = 0;
+                self.refreshed = false;
+                self.push_state(ConnectionState::Connected);
+                self.schedule_rotation().await;
+            }
+            Err(CopepodError::Api { status: 401, .. })
+                if self.client.auto_refresh && !self.refreshed =>
+            {
+                // The token expired early or was revoked: refresh it and
+                // retry at once.
+                self.refreshed = true;
+                match self.client.refresh_auth().await {
+                    Ok(()) => {}
+                    Err(e) if e.is_transient() => self.reconnect(),
+                    Err(e) => self.auth_failed(format!("token refresh failed: {e}")),
+                }
+            }
+            Err(e @ CopepodError::Api { status: 401, .. }) => {
+                let reason = match self.client.auto_refresh {
+                    true => "the refreshed token was rejected too",
+                    false => "automatic token refresh is disabled",
+                };
+                self.auth_failed(format!("{reason}: {e}"));
+            }
+            Err(e) => match &e {
+                CopepodError::Api { status, .. } if !is_retryable(*status) => {
+                    self.queue.push_back(Err(e));
+                    self.close();
+                }
+                _ => self.reconnect(),
+            },
+        }
+    }
+
+    /// Give up because the connection needs a new token and none can be
+    /// obtained.
+    fn auth_failed(&mut self, reason: String) {
+        let message = format!("realtime connection is not authorized; {reason}");
+        self.queue.push_back(Err(CopepodError::Auth(message)));
+        self.close();
+    }
+
+    // Takes `&mut self` so the future is `Send` without `Connection: Sync`.
+    async fn open(&mut self) -> Result<Events> {
+        let token = self
+            .client
+            .token_store
+            .get()
+            .await
+            .map(|p| p.token)
+            .unwrap_or_default();
+        let url = self.client.base_url.join(&self.path)?;
+        let mut request = self
+            .client
+            .http
+            .request(Method::GET, url)
+            .query(&[("access_token", &token)])
+            .query(&self.query);
+        if let Some(id) = &self.last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+        if self.transport == RealtimeTransport::WebSocket {
+            let socket = WsStream::connect(request, self.options.keepalive).await?;
+            return Ok(Events::WebSocket(Box::new(socket)));
+        }
+
+        let request = request.header(reqwest::header::ACCEPT, "text/event-stream");
+        let resp = request.send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return CopepodClient::map_error(status, resp).await;
+        }
+        let events = resp
+            .bytes_stream()
+            .map_err(std::io::Error::other)
+            .eventsource()
+            .map_err(|e| CopepodError::Sse(e.to_string()));
+        Ok(Events::Sse(Box::pin(events)))
+    }
+
+    fn handle(&mut self, event: Event) {
+        if !event.id.is_empty() {
+            self.last_event_id = Some(event.id.clone());
+        }
+        if let Some(retry) = event.retry {
+            self.server_retry = Some(retry);
+        }
+        let event = RealtimeEvent::from_sse(event).map(SubscriptionEvent::Event);
+        self.queue.push_back(event);
+    }
+
+    fn reconnect(&mut self) {
+        self.attempt += 1;
+        if self
+            .options
+            .max_attempts
+            .is_some_and(|max| self.attempt > max)
+        {
+            self.close();
+            return;
+        }
+        let delay = self.options.delay(self.server_retry, self.attempt);
+        self.wait = Some(delay);
+        self.push_state(ConnectionState::Reconnecting {
+            attempt: self.attempt,
+            delay,
+        });
+    }
+
+    fn close(&mut self) {
+        self.push_state(ConnectionState::Closed);
+        self.closed = true;
+    }
+
+    fn push_state(&mut self, state: ConnectionState) {
+        self.queue.push_back(Ok(SubscriptionEvent::State(state)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let options = ReconnectOptions::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350));
+        let delays: Vec<_> = (1..=4)
+            .map(|attempt| options.delay(None, attempt))
+            .collect();
+        assert_eq!(
+            delays,
+            [100, 200, 350, 350].map(Duration::from_millis).to_vec()
+        );
+        assert_eq!(
+            options.delay(Some(Duration::from_millis(10)), 2),
+            Duration::from_millis(20)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use eventsource_stream::Event;
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::models::RecordEvent;
+
+/// An event received on a realtime connection.
+#[derive(Debug, Clone)]
+pub enum RealtimeEvent {
+    /// A record was created, updated or deleted.
+    Record(RecordEvent),
+    /// The server accepted the connection and assigned it an ID.
+    Connected { client_id: String },
+    /// Keepalive sent by the server.
+    Ping,
+    /// Any other named event, with its raw data.
+    Custom { event: String, data: String },
+}
+
+impl RealtimeEvent {
+    /// Return the record event, if this is one.
+    pub fn as_record(&self) -> Option<&RecordEvent> {
+        match self {
+            Self::Record(event) => Some(event),
+            _ => None,
+        }
+    }
+
+    /// Decode an SSE event. Only record events with malformed data fail.
+    pub(crate) fn from_sse(event: Event) -> Result<Self> {
+        Ok(match event.event.as_str() {
+            "record" => Self::Record(serde_json::from_str(&event.data)?),
+            "connect" | "connected" => Self::Connected {
+                client_id: client_id(&event.data),
+            },
+            "ping" => Self::Ping,
+            _ => Self::Custom {
+                event: event.event,
+                data: event.data,
+            },
+        })
+    }
+}
+
+/// Read the client ID from connect event data, which is either a JSON
+/// object with a `client_id`/`clientId` field or the bare ID.
+fn client_id(data: &str) -> String {
+    let json = serde_json::from_str::<Value>(data).ok();
+    json.as_ref()
+        .and_then(|v| v.get("client_id").or_else(|| v.get("clientId")))
+        .and_then(Value::as_str)
+        .unwrap_or(data.trim())
+        .to_string()
+}
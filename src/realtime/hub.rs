@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+
+use super::connection::{Connection, ReconnectOptions, SubscriptionEvent};
+use super::subscription::{SubscriptionTargets, Target};
+
+/// Options controlling a [`RealtimeHub`].
+#[derive(Debug, Clone)]
+pub struct HubOptions {
+    capacity: usize,
+    reconnect: ReconnectOptions,
+}
+
+impl Default for HubOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            reconnect: ReconnectOptions::default(),
+        }
+    }
+}
+
+impl HubOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many events each subscriber may fall behind before it misses
+    /// events (default: 256). See [`SubscriptionEvent::Lagged`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set how the shared connections reconnect (default:
+    /// [`ReconnectOptions::default`]).
+    pub fn reconnect(mut self, options: ReconnectOptions) -> Self {
+        self.reconnect = options;
+        self
+    }
+}
+
+type SharedItem = std::result::Result<SubscriptionEvent, Arc<CopepodError>>;
+type Channels = HashMap<(String, String), Weak<Channel>>;
+type EventStream = Pin<Box<dyn Stream<Item = Result<SubscriptionEvent>> + Send>>;
+
+/// One app's shared connection and the channel its events are fanned out on.
+struct Channel {
+    sender: broadcast::Sender<SharedItem>,
+    connection: tokio::sync::Mutex<EventStream>,
+    ended: AtomicBool,
+}
+
+/// Shares one realtime connection per app between many subscribers.
+///
+/// Each app's connection is opened by its first subscriber and closed when
+/// its last subscriber is dropped. Events are read from the connection by
+/// whichever subscriber is polled and broadcast to all of them through a
+/// bounded channel, so subscribers that are not polled fall behind; once
+/// they are more than [`HubOptions::capacity`] events behind, the oldest
+/// events are dropped for them and reported with
+/// [`SubscriptionEvent::Lagged`]. Clones share the same connections.
+#[derive(Clone)]
+pub struct RealtimeHub {
+    client: CopepodClient,
+    options: HubOptions,
+    channels: Arc<Mutex<Channels>>,
+}
+
+impl RealtimeHub {
+    pub fn new(client: CopepodClient, options: HubOptions) -> Self {
+        Self {
+            client,
+            options,
+            channels: Arc::default(),
+        }
+    }
+
+    /// Start building a subscription to an app's events.
+    pub fn subscribe(&self, org_id: &str, app_id: &str) -> HubSubscriptionBuilder<'_> {
+        HubSubscriptionBuilder {
+            hub: self,
+            org_id: org_id.to_string(),
+            app_id: app_id.to_string(),
+            targets: SubscriptionTargets::default(),
+        }
+    }
+
+    /// Return the number of apps with an open shared connection.
+    pub fn connections(&self) -> usize {
+        let mut channels = self.channels.lock().unwrap();
+        channels.retain(|_, channel| channel.strong_count() > 0);
+        channels.len()
+    }
+
+    fn channel(&self, org_id: &str, app_id: &str) -> Arc<Channel> {
+        let mut channels = self.channels.lock().unwrap();
+        channels.retain(|_, channel| channel.strong_count() > 0);
+        let key = (org_id.to_string(), app_id.to_string());
+        if let Some(channel) = channels.get(&key).and_then(Weak::upgrade) {
+            return channel;
+        }
+        let connection = Connection::new(
+            self.client.clone(),
+            super::realtime_path(org_id, app_id),
+            Vec::new(),
+            self.options.reconnect.clone(),
+        );
+        let channel = Arc::new(Channel {
+            sender: broadcast::channel(self.options.capacity).0,
+            connection: tokio::sync::Mutex::new(Box::pin(connection.into_stream())),
+            ended: AtomicBool::new(false),
+        });
+        channels.insert(key, Arc::downgrade(&channel));
+        channel
+    }
+}
+
+impl std::fmt::Debug for RealtimeHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RealtimeHub")
+            .field("options", &self.options)
+            .field("connections", &self.connections())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builder for a [`RealtimeHub`] subscription, narrowed to specific
+/// collections, records or a filter.
+///
+/// The shared connection receives every event of the app; each subscriber's
+/// targets are applied locally.
+#[derive(Debug)]
+pub struct HubSubscriptionBuilder<'a> {
+    hub: &'a RealtimeHub,
+    org_id: String,
+    app_id: String,
+    targets: SubscriptionTargets,
+}
+
+impl HubSubscriptionBuilder<'_> {
+    /// Receive events from `collection`. May be called more than once; with
+    /// no collections, events from every collection are received.
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        self.targets = self.targets.collection(collection);
+        self
+    }
+
+    /// Receive events for the record `record_id` only. May be called more
+    /// than once.
+    pub fn record(mut self, record_id: impl Into<String>) -> Self {
+        self.targets = self.targets.record(record_id);
+        self
+    }
+
+    /// Receive events only for records matching a filter expression, in the
+    /// same syntax as [`RecordQueryBuilder::filter`](crate::query::RecordQueryBuilder::filter).
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.targets = self.targets.filter(filter);
+        self
+    }
+
+    /// Join the app's shared connection, opening it if this is the first
+    /// subscriber.
+    ///
+    /// Fails if the filter expression cannot be parsed. Subscribers joining
+    /// an open connection receive events from that point on, without the
+    /// earlier connection state changes. Dropping the stream leaves the
+    /// connection.
+    pub fn stream(self) -> Result<impl Stream<Item = Result<SubscriptionEvent>> + Send + 'static> {
+        let target = self.targets.target()?;
+        let channel = self.hub.channel(&self.org_id, &self.app_id);
+        let subscriber = Subscriber {
+            receiver: channel.sender.subscribe(),
+            channel,
+            target,
+        };
+        Ok(stream::unfold(subscriber, |mut subscriber| async move {
+            let item = subscriber.next().await?;
+            Some((item, subscriber))
+        }))
+    }
+}
+
+struct Subscriber {
+    channel: Arc<Channel>,
+    receiver: broadcast::Receiver<SharedItem>,
+    target: Target,
+}
+
+impl Subscriber {
+    async fn next(&mut self) -> Option<Result<SubscriptionEvent>> {
+        loop {
+            if let Some(item) = self.try_recv() {
+                return item;
+            }
+            if self.channel.ended.load(Ordering::Acquire) {
+                return None;
+            }
+
+            // Nothing buffered: read the next event from the connection
+            // ourselves, unless another subscriber did while we waited.
+            let channel = self.channel.clone();
+            let mut connection = channel.connection.lock().await;
+            if let Some(item) = self.try_recv() {
+                return item;
+            }
+            if channel.ended.load(Ordering::Acquire) {
+                return None;
+            }
+            match connection.next().await {
+                Some(item) => {
+                    let _ = channel.sender.send(item.map_err(Arc::new));
+                }
+                None => channel.ended.store(true, Ordering::Release),
+            }
+        }
+    }
+
+    /// Return the next buffered item this subscriber wants, `Some(None)` if
+    /// the channel closed, or `None` if nothing is buffered.
+    fn try_recv(&mut self) -> Option<Option<Result<SubscriptionEvent>>> {
+        loop {
+            let item = match self.receiver.try_recv() {
+                Ok(Ok(event)) => Ok(event),
+                Ok(Err(e)) => Err(copy_error(&e)),
+                Err(TryRecvError::Lagged(skipped)) => Ok(SubscriptionEvent::Lagged { skipped }),
+                Err(TryRecvError::Closed) => return Some(None),
+                Err(TryRecvError::Empty) => return None,
+            };
+            if self.target.wants(&item) {
+                return Some(Some(item));
+            }
+        }
+    }
+}
+
+/// Rebuild a broadcast error for one subscriber. Errors that carry
+/// non-cloneable sources are reduced to their message.
+fn copy_error(e: &CopepodError) -> CopepodError {
+    match e {
+        CopepodError::Api {
+            status,
+            code,
+            message,
+        } => CopepodError::Api {
+            status: *status,
+            code: code.clone(),
+            message: message.clone(),
+        },
+        CopepodError::Auth(message) => CopepodError::Auth(message.clone()),
+        CopepodError::Sse(message) => CopepodError::Sse(message.clone()),
+        CopepodError::WebSocket(message) => CopepodError::WebSocket(message.clone()),
+        other => CopepodError::Sse(other.to_string()),
+    }
+}
+
+impl CopepodClient {
+    /// Create a hub that shares one realtime connection per app between
+    /// many subscribers.
+    pub fn realtime_hub(&self, options: HubOptions) -> RealtimeHub {
+        RealtimeHub::new(self.clone(), options)
+    }
+}
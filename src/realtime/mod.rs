@@ -0,0 +1,104 @@
+mod connection;
+mod event;
+mod hub;
+mod subscription;
+mod websocket;
+
+use eventsource_stream::Eventsource;
+use futures_util::future::Either;
+use futures_util::stream::{Stream, StreamExt, TryStreamExt};
+use reqwest::Method;
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+
+use connection::Connection;
+pub use connection::{ConnectionState, RealtimeTransport, ReconnectOptions, SubscriptionEvent};
+pub use event::RealtimeEvent;
+pub use hub::{HubOptions, HubSubscriptionBuilder, RealtimeHub};
+pub use subscription::{Subscription, SubscriptionBuilder, SubscriptionTargets};
+use websocket::WsStream;
+
+impl CopepodClient {
+    /// Subscribe to real-time events for an application.
+    ///
+    /// Returns a stream of [`RealtimeEvent`] items over a single connection
+    /// of the client's realtime transport, which ends when the connection
+    /// drops (see [`CopepodClient::subscribe_with_reconnect`]).
+    pub async fn subscribe(
+        &self,
+        org_id: &str,
+        app_id: &str,
+    ) -> Result<impl Stream<Item = Result<RealtimeEvent>>> {
+        self.ensure_auth().await?;
+        let token = self
+            .token_store
+            .get()
+            .await
+            .map(|p| p.token)
+            .unwrap_or_default();
+
+        let url = self.base_url.join(&realtime_path(org_id, app_id))?;
+        let request = self
+            .http
+            .request(Method::GET, url)
+            .query(&[("access_token", &token)]);
+
+        if self.realtime_transport == RealtimeTransport::WebSocket {
+            let keepalive = ReconnectOptions::default().keepalive;
+            let socket = WsStream::connect(request, keepalive).await?;
+            let stream = socket.map(|result| result.and_then(RealtimeEvent::from_sse));
+            return Ok(Either::Left(stream));
+        }
+
+        let resp = request
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(CopepodError::Http)?;
+
+        let stream = resp
+            .bytes_stream()
+            .map_err(std::io::Error::other)
+            .eventsource()
+            .map(|result| match result {
+                Ok(event) => RealtimeEvent::from_sse(event),
+                Err(e) => Err(CopepodError::Sse(e.to_string())),
+            });
+
+        Ok(Either::Right(stream))
+    }
+
+    /// Subscribe to real-time record events for an application, reconnecting
+    /// when the connection drops.
+    ///
+    /// Reconnects use exponential backoff and send `Last-Event-ID`, so a
+    /// server that keeps event history replays the events missed while
+    /// disconnected. Connection state changes are emitted alongside records.
+    ///
+    /// With automatic refresh enabled, the connection is reopened with a
+    /// refreshed token shortly before the current one expires, and a
+    /// connection rejected with 401 is retried once after refreshing. If no
+    /// new token can be obtained, a [`CopepodError::Auth`] error is yielded
+    /// and the stream closes. The stream also ends if `options` limits the
+    /// number of attempts, or the server rejects the subscription for
+    /// another reason (e.g. 404), in which case the error is yielded first.
+    pub fn subscribe_with_reconnect(
+        &self,
+        org_id: &str,
+        app_id: &str,
+        options: ReconnectOptions,
+    ) -> impl Stream<Item = Result<SubscriptionEvent>> + Send + 'static {
+        Connection::new(
+            self.clone(),
+            realtime_path(org_id, app_id),
+            Vec::new(),
+            options,
+        )
+        .into_stream()
+    }
+}
+
+fn realtime_path(org_id: &str, app_id: &str) -> String {
+    format!("api/platform/orgs/{}/apps/{}/realtime", org_id, app_id)
+}
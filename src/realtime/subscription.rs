@@ -0,0 +1,262 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures_util::future::ready;
+use futures_util::stream::{Stream, StreamExt};
+use tokio::sync::mpsc;
+
+use crate::client::CopepodClient;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::models::RecordEvent;
+
+use super::connection::{Connection, RealtimeTransport, ReconnectOptions, SubscriptionEvent};
+use super::RealtimeEvent;
+
+/// The collections, records and filter a subscription receives events for.
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionTargets {
+    collections: Vec<String>,
+    record_ids: Vec<String>,
+    filter: Option<String>,
+}
+
+impl SubscriptionTargets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive events from `collection`. May be called more than once; with
+    /// no collections, events from every collection are received.
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        self.collections.push(collection.into());
+        self
+    }
+
+    /// Receive events for the record `record_id` only. May be called more
+    /// than once.
+    pub fn record(mut self, record_id: impl Into<String>) -> Self {
+        self.record_ids.push(record_id.into());
+        self
+    }
+
+    /// Receive events only for records matching a filter expression, in the
+    /// same syntax as [`RecordQueryBuilder::filter`](crate::query::RecordQueryBuilder::filter).
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+        if !self.collections.is_empty() {
+            query.push(("collections".to_string(), self.collections.join(",")));
+        }
+        if !self.record_ids.is_empty() {
+            query.push(("records".to_string(), self.record_ids.join(",")));
+        }
+        if let Some(expr) = &self.filter {
+            query.push(("filter".to_string(), expr.clone()));
+        }
+        query
+    }
+
+    pub(super) fn target(&self) -> Result<Target> {
+        Target::new(
+            self.collections.clone(),
+            self.record_ids.clone(),
+            self.filter.as_deref(),
+        )
+    }
+}
+
+/// Builder for a realtime subscription narrowed to specific collections,
+/// records or a filter.
+///
+/// The targets are sent to the server as query parameters
+/// (`collections`, `records`, `filter`) so it can narrow events itself; they
+/// are also checked locally, so servers that ignore them still deliver only
+/// matching events.
+#[derive(Debug, Clone)]
+pub struct SubscriptionBuilder<'a> {
+    client: &'a CopepodClient,
+    org_id: String,
+    app_id: String,
+    targets: SubscriptionTargets,
+    reconnect: ReconnectOptions,
+    transport: RealtimeTransport,
+}
+
+impl<'a> SubscriptionBuilder<'a> {
+    pub(crate) fn new(
+        client: &'a CopepodClient,
+        org_id: impl Into<String>,
+        app_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            org_id: org_id.into(),
+            app_id: app_id.into(),
+            targets: SubscriptionTargets::default(),
+            reconnect: ReconnectOptions::default(),
+            transport: client.realtime_transport,
+        }
+    }
+
+    /// Receive events from `collection`. May be called more than once; with
+    /// no collections, events from every collection are received.
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        self.targets = self.targets.collection(collection);
+        self
+    }
+
+    /// Receive events for the record `record_id` only. May be called more
+    /// than once.
+    pub fn record(mut self, record_id: impl Into<String>) -> Self {
+        self.targets = self.targets.record(record_id);
+        self
+    }
+
+    /// Receive events only for records matching a filter expression, in the
+    /// same syntax as [`RecordQueryBuilder::filter`](crate::query::RecordQueryBuilder::filter).
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.targets = self.targets.filter(filter);
+        self
+    }
+
+    /// Set how the subscription reconnects (default: [`ReconnectOptions::default`]).
+    pub fn reconnect(mut self, options: ReconnectOptions) -> Self {
+        self.reconnect = options;
+        self
+    }
+
+    /// Set the transport for this subscription (default: the client's
+    /// [`realtime_transport`](crate::CopepodClientBuilder::realtime_transport)).
+    pub fn transport(mut self, transport: RealtimeTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Open the subscription.
+    ///
+    /// Fails if the filter expression cannot be parsed. The stream does not
+    /// borrow the client.
+    pub fn stream(self) -> Result<impl Stream<Item = Result<SubscriptionEvent>> + Send + 'static> {
+        self.open()
+    }
+
+    /// Open the subscription, keeping the ability to change its targets
+    /// with [`Subscription::update`].
+    ///
+    /// Fails if the filter expression cannot be parsed.
+    pub fn open(self) -> Result<Subscription> {
+        let target = Arc::new(Mutex::new(self.targets.target()?));
+        let (updates, receiver) = mpsc::unbounded_channel();
+        let connection = Connection::new(
+            self.client.clone(),
+            super::realtime_path(&self.org_id, &self.app_id),
+            self.targets.query(),
+            self.reconnect,
+        )
+        .transport(self.transport)
+        .updates(receiver);
+
+        let wanted = target.clone();
+        let events = connection
+            .into_stream()
+            .filter(move |item| ready(wanted.lock().unwrap().wants(item)));
+        Ok(Subscription {
+            events: Box::pin(events),
+            target,
+            updates,
+        })
+    }
+}
+
+/// An open realtime subscription: a stream of events whose targets can be
+/// changed while it runs.
+pub struct Subscription {
+    events: Pin<Box<dyn Stream<Item = Result<SubscriptionEvent>> + Send>>,
+    target: Arc<Mutex<Target>>,
+    updates: mpsc::UnboundedSender<Vec<(String, String)>>,
+}
+
+impl Subscription {
+    /// Replace the subscription's targets.
+    ///
+    /// Over a WebSocket the new targets are sent on the open socket; an SSE
+    /// subscription reconnects with them. Either way this happens the next
+    /// time the stream is polled, and events already received are checked
+    /// against the new targets. Fails if the filter expression cannot be
+    /// parsed, leaving the targets unchanged.
+    pub fn update(&self, targets: SubscriptionTargets) -> Result<()> {
+        *self.target.lock().unwrap() = targets.target()?;
+        // The receiver lives as long as the stream, which `self` owns.
+        let _ = self.updates.send(targets.query());
+        Ok(())
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<SubscriptionEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription").finish_non_exhaustive()
+    }
+}
+
+/// The events a subscription asked for.
+pub(super) struct Target {
+    collections: Vec<String>,
+    record_ids: Vec<String>,
+    filter: Option<Filter>,
+}
+
+impl Target {
+    /// Fails if the filter expression cannot be parsed.
+    pub(super) fn new(
+        collections: Vec<String>,
+        record_ids: Vec<String>,
+        filter: Option<&str>,
+    ) -> Result<Self> {
+        Ok(Self {
+            collections,
+            record_ids,
+            filter: filter.map(Filter::parse).transpose()?,
+        })
+    }
+
+    /// Whether a subscription item should be delivered. Only record events
+    /// are narrowed; everything else always passes.
+    pub(super) fn wants(&self, item: &Result<SubscriptionEvent>) -> bool {
+        match item {
+            Ok(SubscriptionEvent::Event(RealtimeEvent::Record(event))) => self.matches(event),
+            _ => true,
+        }
+    }
+
+    fn matches(&self, event: &RecordEvent) -> bool {
+        let id = event.record.get("id").and_then(|id| id.as_str());
+        (self.collections.is_empty() || self.collections.contains(&event.collection))
+            && (self.record_ids.is_empty()
+                || id.is_some_and(|id| self.record_ids.iter().any(|r| r == id)))
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|f| f.matches(&event.record))
+    }
+}
+
+impl CopepodClient {
+    /// Start building a realtime subscription for an application.
+    pub fn subscription(&self, org_id: &str, app_id: &str) -> SubscriptionBuilder<'_> {
+        SubscriptionBuilder::new(self, org_id, app_id)
+    }
+}
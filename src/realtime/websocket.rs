@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use eventsource_stream::Event;
+use futures_util::stream::Stream;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::header::{
+    CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE,
+};
+use reqwest::{RequestBuilder, StatusCode, Upgraded, Version};
+use serde_json::{json, Map, Value};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+
+/// A realtime WebSocket connection, read as a stream of the same events the
+/// SSE transport produces.
+///
+/// Server frames are JSON text messages of the form
+/// `{"event": "record", "id": "...", "data": {...}, "retry": 1000}`; text
+/// that isn't such an object is delivered as a `message` event. The socket
+/// is pinged every keepalive interval, and fails with an error if nothing
+/// was received since the previous ping. The upgrade request is sent by the
+/// client's HTTP client, so it uses the same proxy, TLS settings and default
+/// headers as every other request.
+pub(crate) struct WsStream {
+    socket: WebSocketStream<Upgraded>,
+    keepalive: Interval,
+    /// Whether a ping is outstanding with nothing received since.
+    awaiting_pong: bool,
+    outgoing: VecDeque<Message>,
+}
+
+impl WsStream {
+    /// Open a WebSocket connection by sending `request`, an `http(s)` GET
+    /// built on the client's HTTP client, as an upgrade request.
+    pub(crate) async fn connect(request: RequestBuilder, keepalive: Duration) -> Result<Self> {
+        let key = generate_key();
+        let resp = request
+            .version(Version::HTTP_11)
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_VERSION, "13")
+            .header(SEC_WEBSOCKET_KEY, &key)
+            .send()
+            .await?;
+        let status = resp.status();
+        if status != StatusCode::SWITCHING_PROTOCOLS {
+            if !status.is_success() {
+                return CopepodClient::map_error(status, resp).await;
+            }
+            let message = format!("server answered the WebSocket upgrade with {status}");
+            return Err(CopepodError::WebSocket(message));
+        }
+        let accept = resp.headers().get(SEC_WEBSOCKET_ACCEPT);
+        if accept
+            .is_none_or(|accept| accept.as_bytes() != derive_accept_key(key.as_bytes()).as_bytes())
+        {
+            let message = "server sent a missing or wrong Sec-WebSocket-Accept".to_string();
+            return Err(CopepodError::WebSocket(message));
+        }
+        let upgraded = resp.upgrade().await?;
+        let socket = WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+
+        // A stream that isn't polled for a while pings once when it resumes
+        // rather than firing the missed ticks back to back.
+        let mut keepalive = tokio::time::interval_at(Instant::now() + keepalive, keepalive);
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Ok(Self {
+            socket,
+            keepalive,
+            awaiting_pong: false,
+            outgoing: VecDeque::new(),
+        })
+    }
+
+    /// Queue a message replacing the connection's targets with `query`,
+    /// sent the next time the stream is polled.
+    pub(crate) fn retarget(&mut self, query: &[(String, String)]) {
+        self.outgoing
+            .push_back(Message::text(subscribe_message(query).to_string()));
+    }
+
+    fn flush(&mut self, cx: &mut Context<'_>) -> Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.socket.poll_ready_unpin(cx) {
+                Poll::Ready(Ok(())) => {
+                    let message = self.outgoing.pop_front().expect("queue is not empty");
+                    self.socket.start_send_unpin(message).map_err(ws_error)?;
+                }
+                Poll::Ready(Err(e)) => return Err(ws_error(e)),
+                Poll::Pending => return Ok(()),
+            }
+        }
+        match self.socket.poll_flush_unpin(cx) {
+            Poll::Ready(Err(e)) => Err(ws_error(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Stream for WsStream {
+    type Item = Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Err(e) = this.flush(cx) {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        loop {
+            let message = match this.socket.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(ws_error(e)))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => break,
+            };
+            this.awaiting_pong = false;
+            match message {
+                Message::Text(text) => return Poll::Ready(Some(Ok(parse_frame(&text)))),
+                Message::Close(_) => return Poll::Ready(None),
+                // Pings are answered by the socket itself.
+                _ => {}
+            }
+        }
+
+        // Frames that arrived before the tick count as an answer, so the
+        // keepalive is only checked once nothing is left to read.
+        while this.keepalive.poll_tick(cx).is_ready() {
+            if this.awaiting_pong {
+                let error = CopepodError::WebSocket("keepalive ping was not answered".into());
+                return Poll::Ready(Some(Err(error)));
+            }
+            this.awaiting_pong = true;
+            this.outgoing.push_back(Message::Ping(Default::default()));
+            if let Err(e) = this.flush(cx) {
+                return Poll::Ready(Some(Err(e)));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// The message asking the server to replace a connection's targets, with
+/// the same keys as the subscription query parameters.
+fn subscribe_message(query: &[(String, String)]) -> Value {
+    let mut message = Map::new();
+    message.insert("type".into(), json!("subscribe"));
+    for (key, value) in query {
+        message.insert(key.clone(), json!(value));
+    }
+    Value::Object(message)
+}
+
+fn parse_frame(text: &str) -> Event {
+    let frame = serde_json::from_str::<Map<String, Value>>(text)
+        .ok()
+        .filter(|frame| frame.get("event").is_some_and(Value::is_string));
+    let Some(frame) = frame else {
+        return Event {
+            event: "message".into(),
+            data: text.to_string(),
+            id: String::new(),
+            retry: None,
+        };
+    };
+    let string = |key: &str| match frame.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    Event {
+        event: string("event"),
+        data: string("data"),
+        id: string("id"),
+        retry: frame
+            .get("retry")
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis),
+    }
+}
+
+fn ws_error(e: tungstenite::Error) -> CopepodError {
+    CopepodError::WebSocket(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_frames_into_events() {
+        let event =
+            parse_frame(r#"{"event":"record","id":"7","retry":250,"data":{"action":"create"}}"#);
+        assert_eq!(event.event, "record");
+        assert_eq!(event.id, "7");
+        assert_eq!(event.data, r#"{"action":"create"}"#);
+        assert_eq!(event.retry, Some(Duration::from_millis(250)));
+
+        let ping = parse_frame(r#"{"event":"ping"}"#);
+        assert_eq!((ping.event.as_str(), ping.data.as_str()), ("ping", ""));
+
+        let text = parse_frame("hello");
+        assert_eq!(
+            (text.event.as_str(), text.data.as_str()),
+            ("message", "hello")
+        );
+    }
+
+    #[test]
+    fn subscribe_message_uses_query_keys() {
+        let query = [("collections".to_string(), "posts,tags".to_string())];
+        assert_eq!(
+            subscribe_message(&query),
+            json!({ "type": "subscribe", "collections": "posts,tags" })
+        );
+    }
+}
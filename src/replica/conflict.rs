@@ -0,0 +1,174 @@
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use super::store::PendingWrite;
+
+/// A queued write whose record was changed on the server since the write
+/// was made.
+#[derive(Debug, Clone, Copy)]
+pub struct Conflict<'a> {
+    pub write: &'a PendingWrite,
+    /// The current server record.
+    pub server: &'a Value,
+}
+
+impl Conflict<'_> {
+    /// The server record's `updated` timestamp, if present and parseable.
+    pub fn server_updated(&self) -> Option<DateTime<Utc>> {
+        self.server
+            .get("updated")
+            .and_then(Value::as_str)
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Whether the server changed `field` since the write's base record.
+    pub fn server_changed(&self, field: &str) -> bool {
+        let base = self.write.base.as_ref().and_then(|base| base.get(field));
+        self.server.get(field) != base
+    }
+}
+
+/// How to resolve a [`Conflict`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// Drop the local write and keep the server record.
+    KeepServer,
+    /// Push the local write unchanged.
+    KeepLocal,
+    /// Push this update body instead of the local write.
+    Merged(Value),
+}
+
+type Resolver = Arc<dyn Fn(&Conflict<'_>) -> Resolution + Send + Sync>;
+
+/// Policy used by a replica to resolve conflicting writes.
+#[derive(Clone, Default)]
+pub enum ConflictPolicy {
+    /// The server record wins; conflicting local writes are dropped.
+    #[default]
+    ServerWins,
+    /// The local write wins and overwrites server changes.
+    ClientWins,
+    /// Merge per field: local values win for fields the server has not
+    /// changed, and otherwise whichever change is newer, comparing the
+    /// server record's `updated` timestamp with when the write was queued.
+    FieldMerge,
+    /// Resolve with a custom function.
+    Custom(Resolver),
+}
+
+impl ConflictPolicy {
+    /// Build a policy from a resolver function.
+    pub fn custom(f: impl Fn(&Conflict<'_>) -> Resolution + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Resolve a conflict under this policy.
+    pub fn resolve(&self, conflict: &Conflict<'_>) -> Resolution {
+        match self {
+            Self::ServerWins => Resolution::KeepServer,
+            Self::ClientWins => Resolution::KeepLocal,
+            Self::FieldMerge => field_merge(conflict),
+            Self::Custom(f) => f(conflict),
+        }
+    }
+}
+
+impl fmt::Debug for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ServerWins => f.write_str("ServerWins"),
+            Self::ClientWins => f.write_str("ClientWins"),
+            Self::FieldMerge => f.write_str("FieldMerge"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+fn field_merge(conflict: &Conflict<'_>) -> Resolution {
+    let local_newer = conflict
+        .server_updated()
+        .is_none_or(|updated| conflict.write.queued_at > updated);
+    // Deletes have no body and are resolved as a whole.
+    let Some(body) = conflict.write.body.as_object() else {
+        return if local_newer {
+            Resolution::KeepLocal
+        } else {
+            Resolution::KeepServer
+        };
+    };
+
+    let merged: Map<String, Value> = body
+        .iter()
+        .filter(|(field, _)| local_newer || !conflict.server_changed(field))
+        .map(|(field, value)| (field.clone(), value.clone()))
+        .collect();
+    if merged.is_empty() {
+        Resolution::KeepServer
+    } else if merged.len() == body.len() {
+        Resolution::KeepLocal
+    } else {
+        Resolution::Merged(Value::Object(merged))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replica::WriteOp;
+    use serde_json::json;
+
+    fn write(queued_at: &str) -> PendingWrite {
+        PendingWrite {
+            op: WriteOp::Update,
+            collection: "posts".into(),
+            id: "p1".into(),
+            body: json!({ "title": "Local", "views": 5 }),
+            base: Some(json!({
+                "id": "p1", "title": "Base", "views": 1,
+                "updated": "2024-01-01T00:00:00Z"
+            })),
+            seq: 0,
+            queued_at: queued_at.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn field_merge_keeps_untouched_fields_and_newer_changes() {
+        let server = json!({
+            "id": "p1", "title": "Server", "views": 1,
+            "updated": "2024-01-02T00:00:00Z"
+        });
+
+        // Server change is newer: keep its title, still push local views.
+        let older = write("2024-01-01T12:00:00Z");
+        let conflict = Conflict {
+            write: &older,
+            server: &server,
+        };
+        assert!(conflict.server_changed("title"));
+        assert_eq!(
+            ConflictPolicy::FieldMerge.resolve(&conflict),
+            Resolution::Merged(json!({ "views": 5 }))
+        );
+
+        // Local change is newer: push everything.
+        let newer = write("2024-01-03T00:00:00Z");
+        let conflict = Conflict {
+            write: &newer,
+            server: &server,
+        };
+        assert_eq!(
+            ConflictPolicy::FieldMerge.resolve(&conflict),
+            Resolution::KeepLocal
+        );
+        assert_eq!(
+            ConflictPolicy::ServerWins.resolve(&conflict),
+            Resolution::KeepServer
+        );
+    }
+}
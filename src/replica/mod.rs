@@ -0,0 +1,639 @@
+//! Offline-first local replicas of app collections.
+//!
+//! A [`Replica`] mirrors selected collections into a [`ReplicaStore`],
+//! bootstraps them with paginated `list` calls, keeps them current from
+//! realtime events, and queues local writes until they can be pushed.
+//! Conflicting writes are resolved with a [`ConflictPolicy`].
+
+mod conflict;
+mod store;
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{CopepodError, Result};
+use crate::filter::{Filter, SortOrder};
+use crate::models::{RecordAction, RecordEvent};
+use crate::realtime::{ConnectionState, RealtimeEvent, ReconnectOptions, SubscriptionEvent};
+use crate::scoped::{ScopedAppClient, ScopedRecordCollectionClient};
+
+pub use conflict::{Conflict, ConflictPolicy, Resolution};
+pub use store::{JsonFileStore, MemoryStore, PendingWrite, ReplicaStore, WriteOp};
+
+/// Counts of what a [`Replica::push`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PushReport {
+    /// Writes sent to the server.
+    pub pushed: usize,
+    /// Writes whose record had changed on the server.
+    pub conflicts: usize,
+    /// Writes dropped, because the record was deleted on the server or the
+    /// conflict was resolved in the server's favour.
+    pub dropped: usize,
+}
+
+struct Shared<S> {
+    collections: BTreeSet<String>,
+    store: Mutex<S>,
+    /// Serializes pushes and pulls, so queued writes are never sent twice
+    /// and a pull never drops a record pushed while its list was in flight.
+    push_lock: tokio::sync::Mutex<()>,
+}
+
+impl<S: ReplicaStore> Shared<S> {
+    fn store(&self) -> MutexGuard<'_, S> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn apply_event(&self, event: &RecordEvent) -> Result<()> {
+        if !self.collections.contains(&event.collection)
+            || matches!(event.action, RecordAction::Other(_))
+        {
+            return Ok(());
+        }
+        let Some(id) = record_id(&event.record) else {
+            return Ok(());
+        };
+        let server = (event.action != RecordAction::Delete).then(|| event.record.clone());
+        let mut store = self.store();
+        let pending = store.pending()?;
+        refresh(&mut *store, &pending, &event.collection, id, server)?;
+        store.flush()
+    }
+
+    /// Replace the local copy of every mirrored collection with the server's
+    /// records, keeping pending local writes applied on top.
+    async fn pull(&self, app: &ScopedAppClient<'_>) -> Result<()> {
+        let _guard = self.push_lock.lock().await;
+        for collection in &self.collections {
+            let records: Vec<Value> = app.records(collection).query().all().await?;
+            let mut store = self.store();
+            let pending = store.pending()?;
+            store.clear(collection)?;
+            for record in records {
+                if let Some(id) = record_id(&record) {
+                    let id = id.to_string();
+                    refresh(&mut *store, &pending, collection, &id, Some(record))?;
+                }
+            }
+            for write in &pending {
+                if write.op == WriteOp::Create && &write.collection == collection {
+                    refresh(&mut *store, &pending, collection, &write.id, None)?;
+                }
+            }
+            store.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// A local replica of one or more collections of an app.
+///
+/// Reads are served from the local store. Writes are applied locally and
+/// queued, then sent by [`Replica::push`]. Build one with
+/// [`ScopedAppClient::replica`]:
+///
+/// ```ignore
+/// let replica = client
+///     .app("org", "app")
+///     .replica(JsonFileStore::open("replica.json")?)
+///     .collection("posts")
+///     .conflict_policy(ConflictPolicy::FieldMerge);
+/// replica.bootstrap().await?;
+/// tokio::spawn(replica.realtime_sync().await?);
+/// ```
+pub struct Replica<'a, S: ReplicaStore> {
+    app: ScopedAppClient<'a>,
+    policy: ConflictPolicy,
+    shared: Arc<Shared<S>>,
+}
+
+impl<'a, S: ReplicaStore> Replica<'a, S> {
+    pub(crate) fn new(app: ScopedAppClient<'a>, store: S) -> Self {
+        Self {
+            app,
+            policy: ConflictPolicy::default(),
+            shared: Arc::new(Shared {
+                collections: BTreeSet::new(),
+                store: Mutex::new(store),
+                push_lock: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Mirror `collection` in this replica.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after [`Replica::realtime_sync`] has been started.
+    pub fn collection(mut self, collection: impl Into<String>) -> Self {
+        Arc::get_mut(&mut self.shared)
+            .expect("collections must be added before starting realtime sync")
+            .collections
+            .insert(collection.into());
+        self
+    }
+
+    /// Set how conflicting writes are resolved (default: server wins).
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Return the mirrored collection names.
+    pub fn collections(&self) -> impl Iterator<Item = &str> {
+        self.shared.collections.iter().map(String::as_str)
+    }
+
+    fn records(&self, collection: &str) -> ScopedRecordCollectionClient<'a> {
+        self.app.records(collection)
+    }
+
+    fn check_collection(&self, collection: &str) -> Result<()> {
+        if self.shared.collections.contains(collection) {
+            Ok(())
+        } else {
+            Err(CopepodError::Query(format!(
+                "collection `{collection}` is not replicated"
+            )))
+        }
+    }
+
+    /// Replace the local copy of every mirrored collection with the server's
+    /// records, keeping pending local writes applied on top.
+    pub async fn bootstrap(&self) -> Result<()> {
+        self.shared.pull(&self.app).await
+    }
+
+    /// Apply a realtime record event to the local copy.
+    ///
+    /// Events for collections that are not mirrored and unknown actions are
+    /// ignored.
+    pub fn apply_event(&self, event: &RecordEvent) -> Result<()> {
+        self.shared.apply_event(event)
+    }
+
+    /// Subscribe to the app's realtime events and return a future that
+    /// applies them to this replica.
+    ///
+    /// The subscription reconnects with backoff when the connection drops,
+    /// like [`subscribe_with_reconnect`]. Events missed while disconnected
+    /// can't be seen, so every mirrored collection is pulled again (as by
+    /// [`Replica::bootstrap`]) once the connection is back. Fails if the
+    /// first connection can't be opened; the future completes only if the
+    /// subscription gives up.
+    ///
+    /// The future does not borrow the client; spawn it on your runtime.
+    ///
+    /// [`subscribe_with_reconnect`]: crate::client::CopepodClient::subscribe_with_reconnect
+    pub async fn realtime_sync(&self) -> Result<impl Future<Output = ()> + Send + 'static> {
+        let client = self.app.client().clone();
+        let (org_id, app_id) = (self.app.org_id().to_string(), self.app.app_id().to_string());
+        let mut events = Box::pin(client.subscribe_with_reconnect(
+            &org_id,
+            &app_id,
+            ReconnectOptions::default(),
+        ));
+        loop {
+            match events.next().await {
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Connected))) => break,
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Closed))) | None => {
+                    return Err(CopepodError::Sse(
+                        "could not open the realtime connection for replica sync".into(),
+                    ))
+                }
+                Some(Err(e)) => return Err(e),
+                Some(Ok(_)) => {}
+            }
+        }
+        let shared = Arc::clone(&self.shared);
+        Ok(async move {
+            let app = client.app(&org_id, &app_id);
+            let mut disconnected = false;
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(SubscriptionEvent::Event(RealtimeEvent::Record(event))) => {
+                        if let Err(e) = shared.apply_event(&event) {
+                            tracing::warn!("failed to apply realtime event to replica: {e}");
+                        }
+                    }
+                    Ok(SubscriptionEvent::State(ConnectionState::Reconnecting { .. })) => {
+                        disconnected = true;
+                    }
+                    Ok(SubscriptionEvent::State(ConnectionState::Connected)) if disconnected => {
+                        disconnected = false;
+                        if let Err(e) = shared.pull(&app).await {
+                            tracing::warn!("failed to pull replica after reconnecting: {e}");
+                        }
+                    }
+                    Ok(SubscriptionEvent::Lagged { .. }) => {
+                        if let Err(e) = shared.pull(&app).await {
+                            tracing::warn!("failed to pull replica after missing events: {e}");
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("replica sync subscription error: {e}"),
+                }
+            }
+        })
+    }
+
+    /// Get a record from the local copy.
+    pub fn get(&self, collection: &str, record_id: &str) -> Result<Option<Value>> {
+        self.shared.store().get(collection, record_id)
+    }
+
+    /// Start a query over the local copy of `collection`.
+    pub fn query(&self, collection: impl Into<String>) -> LocalQuery<'_, S> {
+        LocalQuery {
+            shared: &self.shared,
+            collection: collection.into(),
+            filter: None,
+            sort: None,
+            offset: 0,
+            limit: None,
+        }
+    }
+
+    /// Return the queued local writes, oldest first.
+    pub fn pending_writes(&self) -> Result<Vec<PendingWrite>> {
+        self.shared.store().pending()
+    }
+
+    /// Create a record locally and queue it for pushing.
+    ///
+    /// The record gets a temporary `local-` ID unless `body` has an `id`;
+    /// the temporary ID is replaced with the server's when pushed.
+    pub fn create(&self, collection: &str, body: &impl Serialize) -> Result<Value> {
+        self.check_collection(collection)?;
+        let body = to_object(body)?;
+        let id = match body.get("id").and_then(Value::as_str) {
+            Some(id) => id.to_string(),
+            None => temporary_id(),
+        };
+        let mut store = self.shared.store();
+        let mut pending = store.pending()?;
+        pending.push(PendingWrite {
+            op: WriteOp::Create,
+            collection: collection.to_string(),
+            id: id.clone(),
+            body: Value::Object(body),
+            base: None,
+            seq: next_seq(&pending),
+            queued_at: Utc::now(),
+        });
+        let record = refresh(&mut *store, &pending, collection, &id, None)?;
+        store.set_pending(pending)?;
+        store.flush()?;
+        Ok(record.unwrap_or_default())
+    }
+
+    /// Update a record locally and queue the change for pushing.
+    pub fn update(
+        &self,
+        collection: &str,
+        record_id: &str,
+        body: &impl Serialize,
+    ) -> Result<Value> {
+        self.check_collection(collection)?;
+        let body = to_object(body)?;
+        let mut store = self.shared.store();
+        let Some(current) = store.get(collection, record_id)? else {
+            return Err(not_found(collection, record_id));
+        };
+        let mut pending = store.pending()?;
+        let queued = pending
+            .iter_mut()
+            .rev()
+            .find(|w| w.collection == collection && w.id == record_id);
+        match queued {
+            Some(write) if write.op != WriteOp::Delete => {
+                merge(&mut write.body, &body);
+                write.queued_at = Utc::now();
+            }
+            _ => pending.push(PendingWrite {
+                op: WriteOp::Update,
+                collection: collection.to_string(),
+                id: record_id.to_string(),
+                body: Value::Object(body.clone()),
+                base: Some(current.clone()),
+                seq: next_seq(&pending),
+                queued_at: Utc::now(),
+            }),
+        }
+        let mut record = current;
+        merge(&mut record, &body);
+        store.put(collection, record_id, record.clone())?;
+        store.set_pending(pending)?;
+        store.flush()?;
+        Ok(record)
+    }
+
+    /// Delete a record locally and queue the deletion for pushing.
+    pub fn delete(&self, collection: &str, record_id: &str) -> Result<()> {
+        self.check_collection(collection)?;
+        let mut store = self.shared.store();
+        let Some(current) = store.get(collection, record_id)? else {
+            return Ok(());
+        };
+        let mut pending = store.pending()?;
+        let is_ours = |w: &PendingWrite| w.collection == collection && w.id == record_id;
+        let created_locally = pending
+            .iter()
+            .any(|w| is_ours(w) && w.op == WriteOp::Create);
+        // Deleting supersedes earlier queued updates; the base is the server
+        // record the first of them was made against.
+        let base = pending
+            .iter()
+            .find(|w| is_ours(w))
+            .and_then(|w| w.base.clone())
+            .unwrap_or(current);
+        pending.retain(|w| !is_ours(w));
+        if !created_locally {
+            pending.push(PendingWrite {
+                op: WriteOp::Delete,
+                collection: collection.to_string(),
+                id: record_id.to_string(),
+                body: Value::Null,
+                base: Some(base),
+                seq: next_seq(&pending),
+                queued_at: Utc::now(),
+            });
+        }
+        store.remove(collection, record_id)?;
+        store.set_pending(pending)?;
+        store.flush()
+    }
+
+    /// Send queued local writes to the server, oldest first.
+    ///
+    /// Updates and deletes are checked against the current server record;
+    /// if it changed since the write was made, the conflict policy decides
+    /// the outcome. Stops at the first error, keeping the remaining writes
+    /// queued.
+    pub async fn push(&self) -> Result<PushReport> {
+        let _guard = self.shared.push_lock.lock().await;
+        let mut report = PushReport::default();
+        loop {
+            let next = self.shared.store().pending()?.into_iter().next();
+            let Some(write) = next else {
+                break;
+            };
+            let records = self.records(&write.collection);
+            let (id, server) = match write.op {
+                WriteOp::Create => {
+                    let record = records.create(&write.body).await?;
+                    report.pushed += 1;
+                    let id = record_id(&record).unwrap_or(&write.id).to_string();
+                    (id, Some(record))
+                }
+                WriteOp::Update | WriteOp::Delete => {
+                    let server = match records.query().get_one(&write.id).await {
+                        Ok(record) => record,
+                        Err(CopepodError::Api { status: 404, .. }) => {
+                            report.dropped += 1;
+                            self.finish(&write, &write.id, None)?;
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    let resolution = if updated(&server) == write.base.as_ref().and_then(updated) {
+                        Resolution::KeepLocal
+                    } else {
+                        report.conflicts += 1;
+                        self.policy.resolve(&Conflict {
+                            write: &write,
+                            server: &server,
+                        })
+                    };
+                    let body = match resolution {
+                        Resolution::KeepServer => {
+                            report.dropped += 1;
+                            self.finish(&write, &write.id, Some(server))?;
+                            continue;
+                        }
+                        Resolution::KeepLocal => &write.body,
+                        Resolution::Merged(ref body) => body,
+                    };
+                    report.pushed += 1;
+                    if write.op == WriteOp::Delete {
+                        records.delete(&write.id).await?;
+                        (write.id.clone(), None)
+                    } else {
+                        (
+                            write.id.clone(),
+                            Some(records.update(&write.id, body).await?),
+                        )
+                    }
+                }
+            };
+            self.finish(&write, &id, server)?;
+        }
+        Ok(report)
+    }
+
+    /// Push queued writes, then refresh every mirrored collection.
+    pub async fn sync(&self) -> Result<PushReport> {
+        let report = self.push().await?;
+        self.bootstrap().await?;
+        Ok(report)
+    }
+
+    /// Dequeue a handled write and store the resulting server record under
+    /// `id`, which differs from the write's ID for pushed creates.
+    fn finish(&self, write: &PendingWrite, id: &str, server: Option<Value>) -> Result<()> {
+        let mut store = self.shared.store();
+        let mut pending = store.pending()?;
+        let position = pending.iter().position(|w| {
+            w.seq == write.seq && w.collection == write.collection && w.id == write.id
+        });
+        match position {
+            Some(i) if pending[i].body == write.body => {
+                pending.remove(i);
+            }
+            // Changed locally while being pushed: send the rest as an update
+            // against the record we just got back.
+            Some(i) => {
+                pending[i].op = WriteOp::Update;
+                pending[i].base = server.clone();
+                pending[i].queued_at = Utc::now();
+            }
+            // A record created here was deleted locally while being pushed.
+            None if write.op == WriteOp::Create => pending.push(PendingWrite {
+                op: WriteOp::Delete,
+                collection: write.collection.clone(),
+                id: id.to_string(),
+                body: Value::Null,
+                base: server.clone(),
+                seq: next_seq(&pending),
+                queued_at: Utc::now(),
+            }),
+            None => {}
+        }
+        if id != write.id {
+            for queued in &mut pending {
+                if queued.collection == write.collection && queued.id == write.id {
+                    queued.id = id.to_string();
+                }
+            }
+            store.remove(&write.collection, &write.id)?;
+        }
+        refresh(&mut *store, &pending, &write.collection, id, server)?;
+        store.set_pending(pending)?;
+        store.flush()
+    }
+}
+
+/// A query over the local copy of a collection, using the same filter and
+/// sort syntax as server queries.
+pub struct LocalQuery<'r, S> {
+    shared: &'r Shared<S>,
+    collection: String,
+    filter: Option<String>,
+    sort: Option<String>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl<S: ReplicaStore> LocalQuery<'_, S> {
+    /// Set the filter expression.
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Set the sort expression (e.g. `-created,title`).
+    pub fn sort(mut self, sort: impl Into<String>) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    /// Skip the first `offset` matching records.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Return at most `limit` records.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Return the matching records.
+    pub fn all(self) -> Result<Vec<Value>> {
+        let filter = Filter::parse(self.filter.as_deref().unwrap_or_default())?;
+        let mut records: Vec<Value> = self
+            .shared
+            .store()
+            .records(&self.collection)?
+            .into_iter()
+            .filter(|record| filter.matches(record))
+            .collect();
+        if let Some(sort) = &self.sort {
+            let order = SortOrder::parse(sort);
+            records.sort_by(|a, b| order.compare(a, b));
+        }
+        Ok(records
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Return the first matching record.
+    pub fn first(self) -> Result<Option<Value>> {
+        Ok(self.limit(1).all()?.into_iter().next())
+    }
+
+    /// Count the matching records.
+    pub fn count(self) -> Result<usize> {
+        Ok(self.all()?.len())
+    }
+}
+
+fn record_id(record: &Value) -> Option<&str> {
+    record.get("id").and_then(Value::as_str)
+}
+
+fn updated(record: &Value) -> Option<&Value> {
+    record.get("updated")
+}
+
+fn to_object(body: &impl Serialize) -> Result<Map<String, Value>> {
+    Ok(serde_json::from_value(serde_json::to_value(body)?)?)
+}
+
+fn merge(target: &mut Value, patch: &Map<String, Value>) {
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn not_found(collection: &str, id: &str) -> CopepodError {
+    CopepodError::Query(format!(
+        "record `{id}` not found in replica of `{collection}`"
+    ))
+}
+
+/// Sequence number for a newly queued write: past every queued write and
+/// every sequence number handed out before.
+fn next_seq(pending: &[PendingWrite]) -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let floor = pending.iter().map(|w| w.seq + 1).max().unwrap_or(0);
+    NEXT.fetch_max(floor, Ordering::Relaxed);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+fn temporary_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "local-{:x}{:04x}",
+        Utc::now().timestamp_micros(),
+        COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff
+    )
+}
+
+/// Store the local view of a record: the server record (if any) with queued
+/// writes for it applied. Returns the stored view.
+fn refresh<S: ReplicaStore + ?Sized>(
+    store: &mut S,
+    pending: &[PendingWrite],
+    collection: &str,
+    id: &str,
+    server: Option<Value>,
+) -> Result<Option<Value>> {
+    let mut view = server;
+    for write in pending
+        .iter()
+        .filter(|w| w.collection == collection && w.id == id)
+    {
+        match write.op {
+            WriteOp::Create => {
+                let mut record = write.body.clone();
+                merge(&mut record, &Map::from_iter([("id".into(), id.into())]));
+                view = Some(record);
+            }
+            WriteOp::Update => {
+                if let (Some(record), Some(body)) = (&mut view, write.body.as_object()) {
+                    merge(record, body);
+                }
+            }
+            WriteOp::Delete => view = None,
+        }
+    }
+    match &view {
+        Some(record) => store.put(collection, id, record.clone())?,
+        None => store.remove(collection, id)?,
+    }
+    Ok(view)
+}
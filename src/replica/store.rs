@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{CopepodError, Result};
+
+/// The kind of a queued local write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteOp {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A local write waiting to be pushed to the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingWrite {
+    pub op: WriteOp,
+    pub collection: String,
+    /// The record ID. Records created offline use a temporary `local-` ID
+    /// until they are pushed.
+    pub id: String,
+    /// The fields to write. `null` for deletes.
+    pub body: Value,
+    /// The server record the write was made against, used to detect
+    /// conflicting server changes. `None` for creates.
+    pub base: Option<Value>,
+    /// Identifies the write while it is queued. Increases with each write
+    /// queued, and is kept when later local changes are merged into it.
+    #[serde(default)]
+    pub seq: u64,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Local storage backing a [`Replica`](super::Replica).
+///
+/// Records are stored per collection and keyed by ID, as the replica's
+/// current view (server state with pending local writes applied).
+pub trait ReplicaStore: Send + 'static {
+    /// Return every record stored for `collection`.
+    fn records(&self, collection: &str) -> Result<Vec<Value>>;
+
+    /// Return the stored record `id` in `collection`.
+    fn get(&self, collection: &str, id: &str) -> Result<Option<Value>>;
+
+    /// Insert or replace a record.
+    fn put(&mut self, collection: &str, id: &str, record: Value) -> Result<()>;
+
+    /// Remove a record, if stored.
+    fn remove(&mut self, collection: &str, id: &str) -> Result<()>;
+
+    /// Remove every record stored for `collection`.
+    fn clear(&mut self, collection: &str) -> Result<()>;
+
+    /// Return the queued local writes, oldest first.
+    fn pending(&self) -> Result<Vec<PendingWrite>>;
+
+    /// Replace the queued local writes.
+    fn set_pending(&mut self, pending: Vec<PendingWrite>) -> Result<()>;
+
+    /// Persist changes made since the last flush.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An in-memory [`ReplicaStore`]. Contents are lost when dropped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemoryStore {
+    collections: BTreeMap<String, BTreeMap<String, Value>>,
+    pending: Vec<PendingWrite>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplicaStore for MemoryStore {
+    fn records(&self, collection: &str) -> Result<Vec<Value>> {
+        Ok(self
+            .collections
+            .get(collection)
+            .map(|records| records.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn get(&self, collection: &str, id: &str) -> Result<Option<Value>> {
+        Ok(self
+            .collections
+            .get(collection)
+            .and_then(|records| records.get(id))
+            .cloned())
+    }
+
+    fn put(&mut self, collection: &str, id: &str, record: Value) -> Result<()> {
+        self.collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(id.to_string(), record);
+        Ok(())
+    }
+
+    fn remove(&mut self, collection: &str, id: &str) -> Result<()> {
+        if let Some(records) = self.collections.get_mut(collection) {
+            records.remove(id);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, collection: &str) -> Result<()> {
+        self.collections.remove(collection);
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingWrite>> {
+        Ok(self.pending.clone())
+    }
+
+    fn set_pending(&mut self, pending: Vec<PendingWrite>) -> Result<()> {
+        self.pending = pending;
+        Ok(())
+    }
+}
+
+/// A [`ReplicaStore`] kept in memory and persisted to a single JSON file.
+///
+/// The file is read on [`JsonFileStore::open`] and rewritten atomically on
+/// every flush.
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    data: MemoryStore,
+    dirty: bool,
+}
+
+impl JsonFileStore {
+    /// Open the store at `path`, loading its contents if the file exists.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => MemoryStore::new(),
+            Err(e) => {
+                return Err(CopepodError::Io(format!(
+                    "failed to read {}: {e}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path,
+            data,
+            dirty: false,
+        })
+    }
+
+    /// Return the backing file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl ReplicaStore for JsonFileStore {
+    fn records(&self, collection: &str) -> Result<Vec<Value>> {
+        self.data.records(collection)
+    }
+
+    fn get(&self, collection: &str, id: &str) -> Result<Option<Value>> {
+        self.data.get(collection, id)
+    }
+
+    fn put(&mut self, collection: &str, id: &str, record: Value) -> Result<()> {
+        self.dirty = true;
+        self.data.put(collection, id, record)
+    }
+
+    fn remove(&mut self, collection: &str, id: &str) -> Result<()> {
+        self.dirty = true;
+        self.data.remove(collection, id)
+    }
+
+    fn clear(&mut self, collection: &str) -> Result<()> {
+        self.dirty = true;
+        self.data.clear(collection)
+    }
+
+    fn pending(&self) -> Result<Vec<PendingWrite>> {
+        self.data.pending()
+    }
+
+    fn set_pending(&mut self, pending: Vec<PendingWrite>) -> Result<()> {
+        self.dirty = true;
+        self.data.set_pending(pending)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let tmp = self.path.with_extension("tmp");
+        let bytes = serde_json::to_vec(&self.data)?;
+        fs::write(&tmp, bytes)
+            .and_then(|()| fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                CopepodError::Io(format!("failed to write {}: {e}", self.path.display()))
+            })?;
+        self.dirty = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+use crate::models::{CollectionField, CollectionSchema, FieldOptions, FieldType, SelectOptions};
+
+use super::SYSTEM_FIELDS;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+impl CopepodClient {
+    /// Fetch the collection schemas of an app across every page, e.g. to
+    /// commit as a snapshot.
+    pub async fn collection_schemas(
+        &self,
+        org_id: &str,
+        app_id: &str,
+    ) -> Result<Vec<CollectionSchema>> {
+        let collections = self.paginate_collections(org_id, app_id).all().await?;
+        Ok(collections.iter().map(CollectionSchema::from).collect())
+    }
+
+    /// Generate a Rust module with types for the live collections of an app.
+    ///
+    /// See [`generate_module`] for the shape of the generated code.
+    pub async fn generate_types(&self, org_id: &str, app_id: &str) -> Result<String> {
+        let schemas = self.collection_schemas(org_id, app_id).await?;
+        Ok(generate_module(&schemas))
+    }
+}
+
+/// Read a schema snapshot: a JSON array of collections, or a list response
+/// with an `items` array (as returned by `list_collections`).
+pub fn load_snapshot(path: &Path) -> Result<Vec<CollectionSchema>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Snapshot {
+        List(Vec<CollectionSchema>),
+        Items { items: Vec<CollectionSchema> },
+    }
+
+    let data = std::fs::read(path).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to read schema snapshot {}: {e}",
+            path.display()
+        ))
+    })?;
+    Ok(match serde_json::from_slice(&data)? {
+        Snapshot::List(schemas) | Snapshot::Items { items: schemas } => schemas,
+    })
+}
+
+/// Write collections to a pretty-printed JSON schema snapshot.
+pub fn write_snapshot(path: &Path, schemas: &[CollectionSchema]) -> Result<()> {
+    let data = serde_json::to_vec_pretty(schemas)?;
+    std::fs::write(path, data).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to write schema snapshot {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Generate code from a committed schema snapshot, for use in `build.rs`.
+///
+/// ```ignore
+/// // build.rs
+/// let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("copepod.rs");
+/// copepod_sdk::schema::codegen::build_from_snapshot("copepod-schema.json", &out).unwrap();
+///
+/// // src/lib.rs
+/// include!(concat!(env!("OUT_DIR"), "/copepod.rs"));
+/// ```
+pub fn build_from_snapshot(snapshot: impl AsRef<Path>, out_file: impl AsRef<Path>) -> Result<()> {
+    let snapshot = snapshot.as_ref();
+    println!("cargo:rerun-if-changed={}", snapshot.display());
+    let code = generate_module(&load_snapshot(snapshot)?);
+    let out_file = out_file.as_ref();
+    std::fs::write(out_file, code).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to write generated code {}: {e}",
+            out_file.display()
+        ))
+    })
+}
+
+/// Generate Rust source for a set of collections.
+///
+/// For each collection this emits a serde struct (named after the singular
+/// collection name, without the system fields) implementing
+/// [`CopepodRecord`](super::CopepodRecord), plus a `Collections` wrapper
+/// around `ScopedAppClient` with one typed accessor per collection. The
+/// generated code only depends on `copepod_sdk`.
+///
+/// Names that would collide after conversion (e.g. collections `post` and
+/// `posts`, or fields `viewCount` and `view_count`) get a numeric suffix in
+/// the order they appear, e.g. `Post` and `Post2`.
+pub fn generate_module(schemas: &[CollectionSchema]) -> String {
+    let mut taken_types = HashSet::from(["Collections".to_string()]);
+    let type_names: HashMap<&str, String> = schemas
+        .iter()
+        .map(|s| {
+            let name = unique(type_name(&s.name), "", &mut taken_types);
+            (s.name.as_str(), name)
+        })
+        .collect();
+
+    let mut out = String::new();
+    out.push_str("// @generated by copepod-sdk codegen. Do not edit by hand.\n\n");
+
+    for schema in schemas {
+        let ty = &type_names[schema.name.as_str()];
+        let fields: Vec<&CollectionField> = schema
+            .fields
+            .iter()
+            .filter(|f| !SYSTEM_FIELDS.contains(&f.name.as_str()))
+            .collect();
+
+        let _ = writeln!(out, "/// A record in the `{}` collection.", schema.name);
+        out.push_str("#[derive(Debug, Clone, PartialEq, ::copepod_sdk::schema::__private::serde::Serialize, ::copepod_sdk::schema::__private::serde::Deserialize)]\n");
+        out.push_str("#[serde(crate = \"::copepod_sdk::schema::__private::serde\")]\n");
+        let _ = writeln!(out, "pub struct {ty} {{");
+        let mut taken_fields = HashSet::new();
+        for field in &fields {
+            let ident = unique(field_ident(&field.name), "_", &mut taken_fields);
+            let (rust_type, is_list) = rust_type(field, &type_names);
+            let mut serde_attrs = Vec::new();
+            if ident.trim_start_matches("r#") != field.name {
+                serde_attrs.push(format!("rename = \"{}\"", field.name.escape_default()));
+            }
+            let rust_type = if is_list {
+                if !field.required {
+                    serde_attrs.push("default".to_string());
+                }
+                rust_type
+            } else if field.required {
+                rust_type
+            } else {
+                serde_attrs.push("default".to_string());
+                serde_attrs.push("skip_serializing_if = \"Option::is_none\"".to_string());
+                format!("Option<{rust_type}>")
+            };
+            if !serde_attrs.is_empty() {
+                let _ = writeln!(out, "    #[serde({})]", serde_attrs.join(", "));
+            }
+            let _ = writeln!(out, "    pub {ident}: {rust_type},");
+        }
+        out.push_str("}\n\n");
+
+        let fields_json = serde_json::to_string(&schema.fields).unwrap_or_else(|_| "[]".into());
+        let _ = writeln!(out, "impl ::copepod_sdk::schema::CopepodRecord for {ty} {{");
+        let _ = writeln!(
+            out,
+            "    const COLLECTION: &'static str = \"{}\";\n",
+            schema.name.escape_default()
+        );
+        out.push_str(
+            "    fn fields() -> ::std::vec::Vec<::copepod_sdk::models::CollectionField> {\n",
+        );
+        let _ = writeln!(
+            out,
+            "        ::copepod_sdk::schema::__private::fields_from_json({})",
+            raw_string(&fields_json)
+        );
+        out.push_str("    }\n}\n\n");
+    }
+
+    out.push_str("/// Typed accessors for the generated collections of an app.\n");
+    out.push_str("pub struct Collections<'a> {\n");
+    out.push_str("    app: ::copepod_sdk::ScopedAppClient<'a>,\n}\n\n");
+    out.push_str("impl<'a> Collections<'a> {\n");
+    out.push_str("    pub fn new(app: ::copepod_sdk::ScopedAppClient<'a>) -> Self {\n");
+    out.push_str("        Self { app }\n    }\n");
+    let mut taken_accessors = HashSet::from(["new".to_string()]);
+    for schema in schemas {
+        let ty = &type_names[schema.name.as_str()];
+        let mut accessor = field_ident(&schema.name);
+        if accessor == "new" {
+            accessor.push('_');
+        }
+        let accessor = unique(accessor, "_", &mut taken_accessors);
+        let _ = writeln!(
+            out,
+            "\n    /// Records in the `{}` collection.",
+            schema.name
+        );
+        let _ = writeln!(
+            out,
+            "    pub fn {accessor}(&self) -> ::copepod_sdk::TypedCollection<'a, {ty}> {{"
+        );
+        let _ = writeln!(out, "        self.app.typed::<{ty}>()\n    }}");
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Map a field to its Rust type, returning whether it is a list.
+fn rust_type(field: &CollectionField, type_names: &HashMap<&str, String>) -> (String, bool) {
+    let options = field.options.as_ref();
+
+    match field.field_type {
+        FieldType::Text | FieldType::Email | FieldType::Url | FieldType::Editor => {
+            ("String".into(), false)
+        }
+        FieldType::Number => ("f64".into(), false),
+        FieldType::Bool => ("bool".into(), false),
+        FieldType::Date | FieldType::Autodate => (
+            "::copepod_sdk::schema::__private::chrono::DateTime<::copepod_sdk::schema::__private::chrono::Utc>"
+                .into(),
+            false,
+        ),
+        FieldType::Select => match options.and_then(FieldOptions::select) {
+            Some(SelectOptions {
+                max_select: Some(n),
+                ..
+            }) if *n > 1 => ("Vec<String>".into(), true),
+            _ => ("String".into(), false),
+        },
+        FieldType::Relation => {
+            let relation = options.and_then(FieldOptions::relation);
+            let id = match relation
+                .and_then(|r| r.collection.as_deref())
+                .and_then(|c| type_names.get(c)) {
+                Some(ty) => format!("::copepod_sdk::schema::RelationId<{ty}>"),
+                None => "String".into(),
+            };
+            if relation.and_then(|r| r.max_select).is_some_and(|n| n <= 1) {
+                (id, false)
+            } else {
+                (format!("Vec<{id}>"), true)
+            }
+        }
+        FieldType::File => {
+            let name = "::copepod_sdk::schema::FileName".to_string();
+            let max_files = options
+                .and_then(FieldOptions::file)
+                .and_then(|f| f.max_files);
+            if max_files.is_some_and(|n| n <= 1) {
+                (name, false)
+            } else {
+                (format!("Vec<{name}>"), true)
+            }
+        }
+        _ => (
+            "::copepod_sdk::schema::__private::serde_json::Value".into(),
+            false,
+        ),
+    }
+}
+
+/// Convert a collection name into a singular PascalCase type name.
+fn type_name(collection: &str) -> String {
+    let mut words: Vec<String> = collection
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(String::from)
+        .collect();
+    if let Some(last) = words.last_mut() {
+        *last = singular(last);
+    }
+    let mut name: String = words
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'C');
+    }
+    name
+}
+
+fn singular(word: &str) -> String {
+    let lower = word.to_ascii_lowercase();
+    if lower.len() > 3 && lower.ends_with("ies") {
+        format!("{}y", &word[..word.len() - 3])
+    } else if ["sses", "xes", "ches", "shes"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        word[..word.len() - 2].to_string()
+    } else if lower.len() > 1
+        && lower.ends_with('s')
+        && !["ss", "us", "is"]
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+    {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+/// Convert a record field name into a valid snake_case Rust identifier.
+fn field_ident(name: &str) -> String {
+    let mut ident = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 && !ident.ends_with('_') {
+                ident.push('_');
+            }
+            ident.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            ident.push(c);
+        } else {
+            ident.push('_');
+        }
+    }
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        if matches!(ident.as_str(), "self" | "Self" | "super" | "crate") {
+            ident.push('_');
+        } else {
+            ident.insert_str(0, "r#");
+        }
+    }
+    ident
+}
+
+/// Return `name`, or the first of `name{separator}2`, `name{separator}3`,
+/// ... not yet taken, and mark it taken.
+fn unique(name: String, separator: &str, taken: &mut HashSet<String>) -> String {
+    let base = name.trim_start_matches("r#");
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while taken.contains(candidate.trim_start_matches("r#")) {
+        candidate = format!("{base}{separator}{n}");
+        n += 1;
+    }
+    taken.insert(candidate.trim_start_matches("r#").to_string());
+    candidate
+}
+
+/// Quote `s` as a raw string literal with enough `#`s to be unambiguous.
+fn raw_string(s: &str) -> String {
+    let mut hashes = 1;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{hashes}\"{s}\"{hashes}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schemas() -> Vec<CollectionSchema> {
+        serde_json::from_value(json!([
+            {
+                "name": "categories",
+                "fields": [{ "name": "name", "type": "text", "required": true }]
+            },
+            {
+                "name": "blog_posts",
+                "fields": [
+                    { "name": "id", "type": "text" },
+                    { "name": "title", "type": "text", "required": true },
+                    { "name": "type", "type": "select", "options": { "values": ["a", "b"] } },
+                    { "name": "viewCount", "type": "number" },
+                    {
+                        "name": "category", "type": "relation", "required": true,
+                        "options": { "collection": "categories", "max_select": 1 }
+                    },
+                    { "name": "images", "type": "file" },
+                    { "name": "meta", "type": "json" }
+                ]
+            }
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn generates_structs_and_accessors() {
+        let code = generate_module(&schemas());
+        assert!(code.contains("pub struct Category {"));
+        assert!(code.contains("pub struct BlogPost {"));
+        assert!(code.contains("    pub title: String,"));
+        assert!(!code.contains("pub id:"));
+        assert!(code.contains(
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub r#type: Option<String>,"
+        ));
+        assert!(code.contains("    #[serde(rename = \"viewCount\", default, skip_serializing_if = \"Option::is_none\")]\n    pub view_count: Option<f64>,"));
+        assert!(code.contains("    pub category: ::copepod_sdk::schema::RelationId<Category>,"));
+        assert!(code.contains(
+            "    #[serde(default)]\n    pub images: Vec<::copepod_sdk::schema::FileName>,"
+        ));
+        assert!(code.contains("const COLLECTION: &'static str = \"blog_posts\";"));
+        assert!(code.contains(
+            "    pub fn blog_posts(&self) -> ::copepod_sdk::TypedCollection<'a, BlogPost> {"
+        ));
+    }
+
+    #[test]
+    fn colliding_names_are_made_unique() {
+        let schemas: Vec<CollectionSchema> = serde_json::from_value(json!([
+            {
+                "name": "post",
+                "fields": [
+                    { "name": "viewCount", "type": "number", "required": true },
+                    { "name": "view_count", "type": "number", "required": true },
+                    { "name": "view-count", "type": "number", "required": true }
+                ]
+            },
+            { "name": "posts", "fields": [] },
+            { "name": "collections", "fields": [] },
+            { "name": "new", "fields": [] }
+        ]))
+        .unwrap();
+        let code = generate_module(&schemas);
+
+        let declared = |prefix: &str| -> Vec<String> {
+            code.lines()
+                .filter_map(|line| line.trim().strip_prefix(prefix))
+                .map(|rest| rest.split([' ', ':', '(', '<']).next().unwrap().to_string())
+                .collect()
+        };
+        let types = declared("pub struct ");
+        assert_eq!(types, ["Post", "Post2", "Collection", "New", "Collections"]);
+        let fields: Vec<_> = declared("pub ")
+            .into_iter()
+            .filter(|f| f.starts_with("view"))
+            .collect();
+        assert_eq!(fields, ["view_count", "view_count_2", "view_count_3"]);
+        assert!(code.contains("    #[serde(rename = \"view-count\")]\n    pub view_count_3: f64,"));
+        let accessors = declared("pub fn ");
+        assert_eq!(accessors, ["new", "post", "posts", "collections", "new_"]);
+    }
+
+    #[test]
+    fn names_are_sanitized() {
+        assert_eq!(type_name("addresses"), "Address");
+        assert_eq!(type_name("status"), "Status");
+        assert_eq!(type_name("user-profiles"), "UserProfile");
+        assert_eq!(field_ident("self"), "self_");
+        assert_eq!(field_ident("2fa"), "_2fa");
+        assert_eq!(raw_string("a\"#b"), "r##\"a\"#b\"##");
+    }
+}
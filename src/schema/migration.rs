@@ -0,0 +1,520 @@
+//! Generate SQL migrations from collection schema snapshots.
+//!
+//! Collections map to PostgreSQL tables named after the collection, with
+//! `id`, `created` and `updated` columns plus one column per field. The
+//! written files have a checksum header and are numbered to sort after the
+//! existing files in the directory, so they can be synced with
+//! `sync_migrations_dir` like hand-written migrations.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{CopepodError, Result};
+use crate::models::{CollectionField, CollectionIndex, CollectionSchema, FieldOptions, FieldType};
+
+use super::plan::diff_collection;
+use super::SYSTEM_FIELDS;
+
+const CHECKSUM_PREFIX: &str = "-- checksum: sha256:";
+
+/// Generate the DDL that turns the `old` collections into the `new` ones.
+///
+/// Returns an empty string if the snapshots are equivalent, and an error if
+/// a collection's type changed, which has no DDL equivalent.
+///
+/// Existing rows have no value for a column that is added as required, or
+/// that becomes required, so the migration leaves it nullable and notes the
+/// `SET NOT NULL` to run in a later migration once the rows are backfilled.
+pub fn diff_sql(old: &[CollectionSchema], new: &[CollectionSchema]) -> Result<String> {
+    let mut out = String::new();
+    for schema in new {
+        match old.iter().find(|o| o.name == schema.name) {
+            None => create_table(&mut out, schema),
+            Some(before) => alter_table(&mut out, schema, before)?,
+        }
+    }
+    for schema in old {
+        if !new.iter().any(|n| n.name == schema.name) {
+            let _ = writeln!(out, "DROP TABLE {};\n", quote(&schema.name));
+        }
+    }
+    out.truncate(out.trim_end().len());
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Hex-encoded SHA-256 of a migration body.
+pub fn migration_checksum(sql: &str) -> String {
+    Sha256::digest(sql.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Check that the body of a generated migration matches its checksum header,
+/// i.e. that it wasn't edited after generation.
+pub fn verify_migration(contents: &str) -> bool {
+    let Some((header, body)) = contents.split_once("\n\n") else {
+        return false;
+    };
+    header
+        .lines()
+        .find_map(|line| line.strip_prefix(CHECKSUM_PREFIX))
+        .is_some_and(|checksum| checksum.trim() == migration_checksum(body))
+}
+
+/// Write a migration for the changes from `old` to `new` into `dir`.
+///
+/// The file is named `<number>_<name>.sql`, numbered one past the highest
+/// numbered `.sql` file in `dir`. Returns `None` without writing anything if
+/// there are no changes.
+pub fn write_migration(
+    dir: &Path,
+    name: &str,
+    old: &[CollectionSchema],
+    new: &[CollectionSchema],
+) -> Result<Option<PathBuf>> {
+    let body = diff_sql(old, new)?;
+    if body.is_empty() {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(dir)
+        .map_err(|e| CopepodError::Io(format!("failed to create migrations dir: {e}")))?;
+    let (last, width) = last_migration_number(dir)?;
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = dir.join(format!("{:0width$}_{slug}.sql", last + 1));
+
+    let contents = format!(
+        "-- Generated by copepod-sdk from a collection schema diff.\n{CHECKSUM_PREFIX}{}\n\n{body}",
+        migration_checksum(&body)
+    );
+    std::fs::write(&path, contents).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to write migration file {}: {e}",
+            path.display()
+        ))
+    })?;
+    Ok(Some(path))
+}
+
+/// Highest numeric prefix among `.sql` files in `dir`, and the width to
+/// zero-pad the next number to.
+fn last_migration_number(dir: &Path) -> Result<(u64, usize)> {
+    let mut last = (0, 3);
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| CopepodError::Io(format!("failed to read migrations dir: {e}")))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("sql") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let digits: String = stem.chars().take_while(char::is_ascii_digit).collect();
+        if let Ok(number) = digits.parse::<u64>() {
+            if number >= last.0 {
+                last = (number, digits.len().max(3));
+            }
+        }
+    }
+    Ok(last)
+}
+
+fn create_table(out: &mut String, schema: &CollectionSchema) {
+    let table = quote(&schema.name);
+    let _ = writeln!(out, "CREATE TABLE {table} (");
+    out.push_str("    \"id\" TEXT PRIMARY KEY,\n");
+    out.push_str("    \"created\" TIMESTAMPTZ NOT NULL DEFAULT now(),\n");
+    out.push_str("    \"updated\" TIMESTAMPTZ NOT NULL DEFAULT now()");
+    let fields: Vec<&CollectionField> = schema
+        .fields
+        .iter()
+        .filter(|f| !SYSTEM_FIELDS.contains(&f.name.as_str()))
+        .collect();
+    for field in &fields {
+        let _ = write!(out, ",\n    {}", column_definition(field));
+    }
+    out.push_str("\n);\n");
+    for field in fields.iter().filter(|f| f.unique) {
+        create_unique_index(out, &schema.name, &field.name);
+    }
+    for index in &schema.indexes {
+        create_index(out, &schema.name, index);
+    }
+    out.push('\n');
+}
+
+fn alter_table(out: &mut String, new: &CollectionSchema, old: &CollectionSchema) -> Result<()> {
+    let (diff, _) = diff_collection(new, old, true);
+    if diff.is_empty() {
+        return Ok(());
+    }
+    let name = &new.name;
+    let table = quote(name);
+
+    if let Some((before, after)) = &diff.collection_type {
+        return Err(CopepodError::Schema(format!(
+            "collection `{name}` changed type from {} to {}, which cannot be migrated",
+            before.as_deref().unwrap_or("none"),
+            after.as_deref().unwrap_or("none")
+        )));
+    }
+    for field in &diff.added_fields {
+        let column = quote(&field.name);
+        let _ = writeln!(
+            out,
+            "ALTER TABLE {table} ADD COLUMN {column} {};",
+            column_type(field)
+        );
+        if field.required {
+            defer_not_null(out, &table, &column);
+        }
+        if field.unique {
+            create_unique_index(out, name, &field.name);
+        }
+    }
+    for field in &diff.removed_fields {
+        if field.unique {
+            drop_index(out, &unique_index_name(name, &field.name));
+        }
+        let _ = writeln!(
+            out,
+            "ALTER TABLE {table} DROP COLUMN {};",
+            quote(&field.name)
+        );
+    }
+    for change in &diff.changed_fields {
+        let (before, after) = (&change.before, &change.after);
+        let column = quote(&after.name);
+        let column_type = column_type(after);
+        let before_type = self::column_type(before);
+        if column_type != before_type {
+            // Single values of a field that became multi-valued are wrapped
+            // in an array, and the reverse keeps the first value.
+            let using = match (before_type, column_type) {
+                ("TEXT", "JSONB") if after.field_type == before.field_type => {
+                    format!(
+                        "CASE WHEN {column} IS NULL THEN NULL ELSE jsonb_build_array({column}) END"
+                    )
+                }
+                ("JSONB", "TEXT") if after.field_type == before.field_type => {
+                    format!("{column}->>0")
+                }
+                _ => format!("{column}::{column_type}"),
+            };
+            let _ = writeln!(
+                out,
+                "ALTER TABLE {table} ALTER COLUMN {column} TYPE {column_type} USING {using};"
+            );
+        }
+        if after.required && !before.required {
+            defer_not_null(out, &table, &column);
+        } else if before.required && !after.required {
+            let _ = writeln!(
+                out,
+                "ALTER TABLE {table} ALTER COLUMN {column} DROP NOT NULL;"
+            );
+        }
+        if after.unique && !before.unique {
+            create_unique_index(out, name, &after.name);
+        } else if before.unique && !after.unique {
+            drop_index(out, &unique_index_name(name, &after.name));
+        }
+    }
+    for index in &diff.removed_indexes {
+        match index_name(name, index) {
+            Some(index_name) => drop_index(out, &index_name),
+            None => {
+                let _ = writeln!(out, "-- cannot drop unnamed index {index:?}");
+            }
+        }
+    }
+    for index in &diff.added_indexes {
+        create_index(out, name, index);
+    }
+    out.push('\n');
+    Ok(())
+}
+
+/// Note that `column` became required, leaving the constraint to a later
+/// migration since existing rows may not have a value yet.
+fn defer_not_null(out: &mut String, table: &str, column: &str) {
+    let _ = writeln!(
+        out,
+        "-- {column} is required: once existing rows are backfilled, run in a later migration\n\
+         -- ALTER TABLE {table} ALTER COLUMN {column} SET NOT NULL;"
+    );
+}
+
+fn column_definition(field: &CollectionField) -> String {
+    let not_null = if field.required { " NOT NULL" } else { "" };
+    format!("{} {}{not_null}", quote(&field.name), column_type(field))
+}
+
+/// The PostgreSQL column type of a field. Multi-valued selects, relations
+/// and files are stored as JSON arrays.
+fn column_type(field: &CollectionField) -> &'static str {
+    let options = field.options.as_ref();
+    let multiple = match field.field_type {
+        FieldType::Select => options
+            .and_then(FieldOptions::select)
+            .and_then(|o| o.max_select)
+            .is_some_and(|n| n > 1),
+        FieldType::Relation => options
+            .and_then(FieldOptions::relation)
+            .and_then(|o| o.max_select)
+            .is_none_or(|n| n > 1),
+        FieldType::File => options
+            .and_then(FieldOptions::file)
+            .and_then(|o| o.max_files)
+            .is_none_or(|n| n > 1),
+        _ => false,
+    };
+    if multiple {
+        return "JSONB";
+    }
+    match field.field_type {
+        FieldType::Text
+        | FieldType::Editor
+        | FieldType::Email
+        | FieldType::Url
+        | FieldType::Select
+        | FieldType::Relation
+        | FieldType::File => "TEXT",
+        FieldType::Number => "DOUBLE PRECISION",
+        FieldType::Bool => "BOOLEAN",
+        FieldType::Date | FieldType::Autodate => "TIMESTAMPTZ",
+        FieldType::Json | FieldType::Other(_) => "JSONB",
+    }
+}
+
+fn unique_index_name(table: &str, column: &str) -> String {
+    format!("idx_{table}_{column}_unique")
+}
+
+fn create_unique_index(out: &mut String, table: &str, column: &str) {
+    let _ = writeln!(
+        out,
+        "CREATE UNIQUE INDEX {} ON {} ({});",
+        quote(&unique_index_name(table, column)),
+        quote(table),
+        quote(column)
+    );
+}
+
+fn create_index(out: &mut String, table: &str, index: &CollectionIndex) {
+    match index {
+        CollectionIndex::Definition(def) => {
+            let columns: Vec<String> = def.fields.iter().map(|f| quote(f)).collect();
+            let _ = writeln!(
+                out,
+                "CREATE {}INDEX {} ON {} ({});",
+                if def.unique { "UNIQUE " } else { "" },
+                quote(&index_name(table, index).unwrap_or_default()),
+                quote(table),
+                columns.join(", ")
+            );
+        }
+        CollectionIndex::Sql(sql) => {
+            let _ = writeln!(out, "{};", sql.trim().trim_end_matches(';'));
+        }
+        CollectionIndex::Other(value) => {
+            let _ = writeln!(out, "-- unsupported index definition: {value}");
+        }
+    }
+}
+
+fn drop_index(out: &mut String, name: &str) {
+    let _ = writeln!(out, "DROP INDEX IF EXISTS {};", quote(name));
+}
+
+/// Name of an index: explicit, derived from its fields, or parsed from a
+/// `CREATE INDEX` statement.
+fn index_name(table: &str, index: &CollectionIndex) -> Option<String> {
+    match index {
+        CollectionIndex::Definition(def) => Some(
+            def.name
+                .clone()
+                .unwrap_or_else(|| format!("idx_{table}_{}", def.fields.join("_"))),
+        ),
+        CollectionIndex::Sql(sql) => {
+            let mut words = sql
+                .split_whitespace()
+                .skip_while(|w| !w.eq_ignore_ascii_case("index"))
+                .skip(1)
+                .filter(|w| {
+                    !["if", "not", "exists", "concurrently"]
+                        .iter()
+                        .any(|k| w.eq_ignore_ascii_case(k))
+                });
+            words
+                .next()
+                .filter(|w| !w.eq_ignore_ascii_case("on"))
+                .map(|w| w.trim_matches('"').to_string())
+        }
+        CollectionIndex::Other(_) => None,
+    }
+}
+
+fn quote(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot(value: serde_json::Value) -> Vec<CollectionSchema> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn old() -> Vec<CollectionSchema> {
+        snapshot(json!([
+            {
+                "name": "posts",
+                "fields": [
+                    { "name": "title", "type": "text", "required": true },
+                    { "name": "views", "type": "text" },
+                    { "name": "legacy", "type": "json" }
+                ],
+                "indexes": ["CREATE INDEX idx_posts_title ON posts (title)"]
+            },
+            { "name": "drafts", "fields": [] }
+        ]))
+    }
+
+    fn new() -> Vec<CollectionSchema> {
+        snapshot(json!([
+            {
+                "name": "posts",
+                "fields": [
+                    { "name": "title", "type": "text", "required": true },
+                    { "name": "views", "type": "number", "required": true },
+                    { "name": "slug", "type": "text", "unique": true }
+                ],
+                "indexes": [{ "fields": ["slug", "title"] }]
+            },
+            {
+                "name": "tags",
+                "fields": [
+                    { "name": "label", "type": "text", "required": true },
+                    { "name": "posts", "type": "relation", "options": { "collection": "posts" } }
+                ]
+            }
+        ]))
+    }
+
+    #[test]
+    fn generates_ddl_for_schema_diff() {
+        let sql = diff_sql(&old(), &new()).unwrap();
+        assert_eq!(
+            sql,
+            r#"ALTER TABLE "posts" ADD COLUMN "slug" TEXT;
+CREATE UNIQUE INDEX "idx_posts_slug_unique" ON "posts" ("slug");
+ALTER TABLE "posts" DROP COLUMN "legacy";
+ALTER TABLE "posts" ALTER COLUMN "views" TYPE DOUBLE PRECISION USING "views"::DOUBLE PRECISION;
+-- "views" is required: once existing rows are backfilled, run in a later migration
+-- ALTER TABLE "posts" ALTER COLUMN "views" SET NOT NULL;
+DROP INDEX IF EXISTS "idx_posts_title";
+CREATE INDEX "idx_posts_slug_title" ON "posts" ("slug", "title");
+
+CREATE TABLE "tags" (
+    "id" TEXT PRIMARY KEY,
+    "created" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    "updated" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    "label" TEXT NOT NULL,
+    "posts" JSONB
+);
+
+DROP TABLE "drafts";
+"#
+        );
+        assert_eq!(diff_sql(&new(), &new()).unwrap(), "");
+    }
+
+    #[test]
+    fn leaves_required_columns_nullable_until_a_later_migration() {
+        let old = snapshot(json!([{ "name": "posts", "fields": [] }]));
+        let new = snapshot(json!([{
+            "name": "posts",
+            "fields": [{ "name": "title", "type": "text", "required": true }]
+        }]));
+        assert_eq!(
+            diff_sql(&old, &new).unwrap(),
+            r#"ALTER TABLE "posts" ADD COLUMN "title" TEXT;
+-- "title" is required: once existing rows are backfilled, run in a later migration
+-- ALTER TABLE "posts" ALTER COLUMN "title" SET NOT NULL;
+"#
+        );
+        assert_eq!(
+            diff_sql(&new, &old).unwrap(),
+            "ALTER TABLE \"posts\" DROP COLUMN \"title\";\n"
+        );
+    }
+
+    #[test]
+    fn diffs_field_options_and_rejects_type_changes() {
+        let old = snapshot(json!([{
+            "name": "tags",
+            "fields": [
+                { "name": "posts", "type": "relation", "options": { "collection": "posts", "max_select": 1 } },
+                { "name": "color", "type": "select", "options": { "values": ["red"] } }
+            ]
+        }]));
+        let new = snapshot(json!([{
+            "name": "tags",
+            "fields": [
+                { "name": "posts", "type": "relation" },
+                { "name": "color", "type": "select" }
+            ]
+        }]));
+        assert_eq!(
+            diff_sql(&old, &new).unwrap(),
+            "ALTER TABLE \"tags\" ALTER COLUMN \"posts\" TYPE JSONB USING CASE WHEN \"posts\" IS NULL THEN NULL ELSE jsonb_build_array(\"posts\") END;\n"
+        );
+
+        let mut auth = new.clone();
+        auth[0].collection_type = Some("auth".into());
+        let err = diff_sql(&new, &auth).unwrap_err();
+        assert!(matches!(err, CopepodError::Schema(msg) if msg.contains("`tags`")));
+    }
+
+    #[test]
+    fn writes_numbered_migration_with_checksum() {
+        let dir = std::env::temp_dir().join(format!("copepod-migrations-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("007_init.sql"), "SELECT 1;").unwrap();
+
+        let path = write_migration(&dir, "Add slug", &old(), &new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(path.file_name().unwrap(), "008_add_slug.sql");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(verify_migration(&contents));
+        assert!(!verify_migration(&contents.replace("slug", "name")));
+
+        assert!(write_migration(&dir, "noop", &new(), &new())
+            .unwrap()
+            .is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{CollectionField, CollectionSchema};
+
+pub mod codegen;
+pub mod migration;
+mod plan;
+mod validate;
+
+pub use plan::{load_schema_files, CollectionChange, CollectionDiff, FieldChange, SchemaPlan};
+pub use validate::{FieldError, SchemaValidator};
+
+#[cfg(feature = "derive")]
+pub use copepod_derive::CopepodRecord;
+
+/// Record fields managed by the server rather than declared in a schema.
+pub(crate) const SYSTEM_FIELDS: &[&str] = &["id", "created", "updated"];
+
+/// A Rust type stored in a Copepod collection.
+///
+/// Usually implemented with `#[derive(CopepodRecord)]` (requires the
+/// `derive` feature), which also generates an associated constant per field
+/// holding its record field name, for use in filter expressions.
+pub trait CopepodRecord {
+    /// Name of the collection records of this type live in.
+    const COLLECTION: &'static str;
+
+    /// Field definitions for the collection, excluding system fields.
+    fn fields() -> Vec<CollectionField>;
+
+    /// The full collection definition, usable as a create body.
+    fn schema() -> CollectionSchema {
+        CollectionSchema {
+            name: Self::COLLECTION.to_string(),
+            collection_type: None,
+            fields: Self::fields(),
+            indexes: Vec::new(),
+        }
+    }
+}
+
+/// The ID of a record in the collection of `T`, stored in a relation field.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RelationId<T> {
+    id: String,
+    #[serde(skip)]
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T> RelationId<T> {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            _target: PhantomData,
+        }
+    }
+
+    /// Return the referenced record ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<T> Clone for RelationId<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.id.clone())
+    }
+}
+
+impl<T> fmt::Debug for RelationId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RelationId").field(&self.id).finish()
+    }
+}
+
+impl<T> fmt::Display for RelationId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl<T> PartialEq for RelationId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for RelationId<T> {}
+
+impl<T> Hash for RelationId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> From<String> for RelationId<T> {
+    fn from(id: String) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<T> From<&str> for RelationId<T> {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+/// The stored name of a file uploaded to a file field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FileName(pub String);
+
+impl fmt::Display for FileName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Helpers called by code generated by `#[derive(CopepodRecord)]` and
+/// [`codegen`].
+#[doc(hidden)]
+pub mod __private {
+    pub use chrono;
+    pub use serde;
+    pub use serde_json;
+
+    use crate::models::{CollectionField, FieldOptions, FieldType, FileOptions, RelationOptions};
+
+    pub fn fields_from_json(json: &str) -> Vec<CollectionField> {
+        serde_json::from_str(json).expect("generated field schema is valid JSON")
+    }
+
+    pub fn field(name: &str, field_type: &str, required: bool, unique: bool) -> CollectionField {
+        CollectionField {
+            required,
+            unique,
+            ..CollectionField::new(name, FieldType::from(field_type))
+        }
+    }
+
+    pub fn relation_field(
+        name: &str,
+        collection: &str,
+        multiple: bool,
+        required: bool,
+        unique: bool,
+    ) -> CollectionField {
+        CollectionField {
+            options: Some(FieldOptions::Relation(RelationOptions {
+                collection: Some(collection.to_string()),
+                max_select: (!multiple).then_some(1),
+                ..Default::default()
+            })),
+            ..field(name, "relation", required, unique)
+        }
+    }
+
+    pub fn file_field(name: &str, multiple: bool, required: bool, unique: bool) -> CollectionField {
+        CollectionField {
+            options: (!multiple).then(|| {
+                FieldOptions::File(FileOptions {
+                    max_files: Some(1),
+                    ..Default::default()
+                })
+            }),
+            ..field(name, "file", required, unique)
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+use crate::models::{Collection, CollectionField, CollectionIndex, CollectionSchema};
+
+use super::SYSTEM_FIELDS;
+
+/// Load desired collections from a YAML or JSON file, or from every
+/// `.yaml`/`.yml`/`.json` file in a directory (in file name order).
+///
+/// Each file holds a single collection, a list of collections, or a
+/// `collections:` list. A collection defined more than once is rejected.
+pub fn load_schema_files(path: &Path) -> Result<Vec<CollectionSchema>> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|e| CopepodError::Io(format!("failed to read schema dir: {e}")))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("yaml" | "yml" | "json")
+                )
+                .then_some(path)
+            })
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut schemas = Vec::new();
+    let mut defined_in: HashMap<String, PathBuf> = HashMap::new();
+    for file in files {
+        for schema in load_schema_file(&file)? {
+            if let Some(first) = defined_in.insert(schema.name.clone(), file.clone()) {
+                return Err(CopepodError::Schema(format!(
+                    "collection `{}` is defined in both {} and {}",
+                    schema.name,
+                    first.display(),
+                    file.display()
+                )));
+            }
+            schemas.push(schema);
+        }
+    }
+    Ok(schemas)
+}
+
+fn load_schema_file(path: &Path) -> Result<Vec<CollectionSchema>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SchemaFile {
+        List(Vec<CollectionSchema>),
+        Collections { collections: Vec<CollectionSchema> },
+        Single(CollectionSchema),
+    }
+
+    let data = std::fs::read_to_string(path).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to read schema file {}: {e}",
+            path.display()
+        ))
+    })?;
+    let invalid = |e: &dyn fmt::Display| {
+        CopepodError::Schema(format!("invalid schema file {}: {e}", path.display()))
+    };
+    let file: SchemaFile = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::from_str(&data).map_err(|e| invalid(&e))?
+    } else {
+        serde_norway::from_str(&data).map_err(|e| invalid(&e))?
+    };
+    Ok(match file {
+        SchemaFile::List(schemas)
+        | SchemaFile::Collections {
+            collections: schemas,
+        } => schemas,
+        SchemaFile::Single(schema) => vec![schema],
+    })
+}
+
+/// The changes needed to bring live collections in line with desired ones.
+///
+/// Built with [`SchemaPlan::new`] or [`CopepodClient::plan_schema`]; its
+/// `Display` output is a human-readable plan. Deleting collections or fields
+/// and changing collection or field types are destructive and must be
+/// allowed explicitly when applying.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaPlan {
+    pub changes: Vec<CollectionChange>,
+}
+
+/// A change to a single collection.
+#[derive(Debug, Clone)]
+pub enum CollectionChange {
+    Create(CollectionSchema),
+    Update {
+        id: String,
+        /// The update body, merging the desired fields into the live ones.
+        schema: CollectionSchema,
+        diff: CollectionDiff,
+    },
+    Delete {
+        id: String,
+        name: String,
+    },
+}
+
+/// Differences between a live collection and its desired definition.
+#[derive(Debug, Clone, Default)]
+pub struct CollectionDiff {
+    pub collection_type: Option<(Option<String>, Option<String>)>,
+    pub added_fields: Vec<CollectionField>,
+    pub removed_fields: Vec<CollectionField>,
+    pub changed_fields: Vec<FieldChange>,
+    pub added_indexes: Vec<CollectionIndex>,
+    pub removed_indexes: Vec<CollectionIndex>,
+}
+
+/// A field present in both definitions with different settings.
+#[derive(Debug, Clone)]
+pub struct FieldChange {
+    pub before: CollectionField,
+    pub after: CollectionField,
+}
+
+impl FieldChange {
+    /// Whether the field type changes, which may drop stored values.
+    pub fn is_destructive(&self) -> bool {
+        self.before.field_type != self.after.field_type
+    }
+}
+
+impl CollectionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.collection_type.is_none()
+            && self.added_fields.is_empty()
+            && self.removed_fields.is_empty()
+            && self.changed_fields.is_empty()
+            && self.added_indexes.is_empty()
+            && self.removed_indexes.is_empty()
+    }
+
+    /// Whether the diff removes fields or changes the collection's type or
+    /// a field's type.
+    pub fn is_destructive(&self) -> bool {
+        self.collection_type.is_some()
+            || !self.removed_fields.is_empty()
+            || self.changed_fields.iter().any(|c| c.is_destructive())
+    }
+}
+
+impl CollectionChange {
+    /// Name of the collection the change applies to.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Create(schema) | Self::Update { schema, .. } => &schema.name,
+            Self::Delete { name, .. } => name,
+        }
+    }
+
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            Self::Create(_) => false,
+            Self::Update { diff, .. } => diff.is_destructive(),
+            Self::Delete { .. } => true,
+        }
+    }
+}
+
+impl SchemaPlan {
+    /// Diff `desired` collections against `live` ones.
+    ///
+    /// Live collections missing from `desired` are deleted. Field options are
+    /// only compared when the desired field specifies them, so server-filled
+    /// defaults don't show up as drift.
+    pub fn new(desired: &[CollectionSchema], live: &[Collection]) -> Self {
+        let live_by_name: HashMap<&str, &Collection> =
+            live.iter().map(|c| (c.name.as_str(), c)).collect();
+
+        let mut changes = Vec::new();
+        for schema in desired {
+            match live_by_name.get(schema.name.as_str()) {
+                None => changes.push(CollectionChange::Create(schema.clone())),
+                Some(collection) => {
+                    let (diff, merged) =
+                        diff_collection(schema, &CollectionSchema::from(*collection), false);
+                    if !diff.is_empty() {
+                        changes.push(CollectionChange::Update {
+                            id: collection.id.clone(),
+                            schema: merged,
+                            diff,
+                        });
+                    }
+                }
+            }
+        }
+        for collection in live {
+            if !desired.iter().any(|s| s.name == collection.name) {
+                changes.push(CollectionChange::Delete {
+                    id: collection.id.clone(),
+                    name: collection.name.clone(),
+                });
+            }
+        }
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether applying the plan deletes collections or fields or changes
+    /// collection or field types.
+    pub fn is_destructive(&self) -> bool {
+        self.changes.iter().any(CollectionChange::is_destructive)
+    }
+}
+
+/// Diff one collection, returning the diff and the merged update body.
+///
+/// Unless `strict`, a desired field without options or a collection without
+/// a type keeps the live one, as when diffing against a server that fills in
+/// defaults. Snapshot-to-snapshot diffs are strict.
+pub(crate) fn diff_collection(
+    desired: &CollectionSchema,
+    live: &CollectionSchema,
+    strict: bool,
+) -> (CollectionDiff, CollectionSchema) {
+    let mut diff = CollectionDiff::default();
+    if (strict || desired.collection_type.is_some())
+        && desired.collection_type != live.collection_type
+    {
+        diff.collection_type = Some((
+            live.collection_type.clone(),
+            desired.collection_type.clone(),
+        ));
+    }
+
+    // Keep the live field order; system fields are never touched.
+    let mut fields = Vec::new();
+    for before in &live.fields {
+        if SYSTEM_FIELDS.contains(&before.name.as_str()) {
+            fields.push(before.clone());
+            continue;
+        }
+        match desired.fields.iter().find(|f| f.name == before.name) {
+            None => diff.removed_fields.push(before.clone()),
+            Some(after) => {
+                let mut after = after.clone();
+                if !strict && after.options.is_none() && after.field_type == before.field_type {
+                    after.options = before.options.clone();
+                }
+                if after != *before {
+                    diff.changed_fields.push(FieldChange {
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+                fields.push(after);
+            }
+        }
+    }
+    for field in &desired.fields {
+        if !live.fields.iter().any(|f| f.name == field.name) {
+            diff.added_fields.push(field.clone());
+            fields.push(field.clone());
+        }
+    }
+
+    diff.added_indexes = desired
+        .indexes
+        .iter()
+        .filter(|i| !live.indexes.contains(i))
+        .cloned()
+        .collect();
+    diff.removed_indexes = live
+        .indexes
+        .iter()
+        .filter(|i| !desired.indexes.contains(i))
+        .cloned()
+        .collect();
+
+    let merged = CollectionSchema {
+        name: desired.name.clone(),
+        collection_type: desired
+            .collection_type
+            .clone()
+            .or_else(|| live.collection_type.clone()),
+        fields,
+        indexes: desired.indexes.clone(),
+    };
+    (diff, merged)
+}
+
+impl fmt::Display for SchemaPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes.");
+        }
+        let mut counts = [0; 3];
+        let mut destructive = 0;
+        for change in &self.changes {
+            match change {
+                CollectionChange::Create(schema) => {
+                    counts[0] += 1;
+                    writeln!(f, "+ create collection {}", schema.name)?;
+                    for field in &schema.fields {
+                        writeln!(f, "    + field {}", describe_field(field))?;
+                    }
+                    for index in &schema.indexes {
+                        writeln!(f, "    + index {}", describe_index(index))?;
+                    }
+                }
+                CollectionChange::Update { schema, diff, .. } => {
+                    counts[1] += 1;
+                    writeln!(f, "~ update collection {}", schema.name)?;
+                    if let Some((before, after)) = &diff.collection_type {
+                        destructive += 1;
+                        writeln!(
+                            f,
+                            "    ~ type: {} -> {}  [destructive]",
+                            before.as_deref().unwrap_or("none"),
+                            after.as_deref().unwrap_or("none")
+                        )?;
+                    }
+                    for field in &diff.added_fields {
+                        writeln!(f, "    + field {}", describe_field(field))?;
+                    }
+                    for field in &diff.removed_fields {
+                        destructive += 1;
+                        writeln!(f, "    - field {}  [destructive]", describe_field(field))?;
+                    }
+                    for change in &diff.changed_fields {
+                        let marker = if change.is_destructive() {
+                            destructive += 1;
+                            "  [destructive]"
+                        } else {
+                            ""
+                        };
+                        writeln!(
+                            f,
+                            "    ~ field {} -> {}{marker}",
+                            describe_field(&change.before),
+                            describe_field(&change.after)
+                        )?;
+                    }
+                    for index in &diff.added_indexes {
+                        writeln!(f, "    + index {}", describe_index(index))?;
+                    }
+                    for index in &diff.removed_indexes {
+                        writeln!(f, "    - index {}", describe_index(index))?;
+                    }
+                }
+                CollectionChange::Delete { name, .. } => {
+                    counts[2] += 1;
+                    destructive += 1;
+                    writeln!(f, "- delete collection {name}  [destructive]")?;
+                }
+            }
+        }
+        writeln!(
+            f,
+            "\nPlan: {} to create, {} to update, {} to delete ({destructive} destructive).",
+            counts[0], counts[1], counts[2]
+        )
+    }
+}
+
+fn describe_field(field: &CollectionField) -> String {
+    let mut flags = vec![field.field_type.to_string()];
+    if field.required {
+        flags.push("required".into());
+    }
+    if field.unique {
+        flags.push("unique".into());
+    }
+    if let Some(options) = &field.options {
+        flags.push(options.clone().into_value().to_string());
+    }
+    format!("{} ({})", field.name, flags.join(", "))
+}
+
+fn describe_index(index: &CollectionIndex) -> String {
+    match index {
+        CollectionIndex::Definition(def) => format!(
+            "{}{}({})",
+            def.name
+                .as_deref()
+                .map(|n| format!("{n} "))
+                .unwrap_or_default(),
+            if def.unique { "unique " } else { "" },
+            def.fields.join(", ")
+        ),
+        CollectionIndex::Sql(sql) => sql.clone(),
+        CollectionIndex::Other(value) => value.to_string(),
+    }
+}
+
+impl CopepodClient {
+    /// Diff `desired` collections against every live collection of an app.
+    pub async fn plan_schema(
+        &self,
+        org_id: &str,
+        app_id: &str,
+        desired: &[CollectionSchema],
+    ) -> Result<SchemaPlan> {
+        let live = self.paginate_collections(org_id, app_id).all().await?;
+        Ok(SchemaPlan::new(desired, &live))
+    }
+
+    /// Apply a schema plan: creates first, then updates, then deletes.
+    ///
+    /// Fails without changing anything if the plan is destructive and
+    /// `allow_destructive` is false. Returns the created and updated
+    /// collections.
+    pub async fn apply_schema_plan(
+        &self,
+        org_id: &str,
+        app_id: &str,
+        plan: &SchemaPlan,
+        allow_destructive: bool,
+    ) -> Result<Vec<Collection>> {
+        if plan.is_destructive() && !allow_destructive {
+            let names: Vec<&str> = plan
+                .changes
+                .iter()
+                .filter(|c| c.is_destructive())
+                .map(CollectionChange::name)
+                .collect();
+            return Err(CopepodError::Schema(format!(
+                "plan has destructive changes to {}; allow destructive changes to apply it",
+                names.join(", ")
+            )));
+        }
+
+        let mut applied = Vec::new();
+        for change in &plan.changes {
+            if let CollectionChange::Create(schema) = change {
+                applied.push(self.create_collection(org_id, app_id, schema).await?);
+            }
+        }
+        for change in &plan.changes {
+            if let CollectionChange::Update { id, schema, .. } = change {
+                applied.push(self.update_collection(org_id, app_id, id, schema).await?);
+            }
+        }
+        for change in &plan.changes {
+            if let CollectionChange::Delete { id, .. } = change {
+                self.delete_collection(org_id, app_id, id).await?;
+            }
+        }
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn live() -> Vec<Collection> {
+        serde_json::from_value(json!([
+            {
+                "id": "c1", "name": "posts", "app_id": "a1",
+                "fields": [
+                    { "name": "id", "type": "text" },
+                    { "name": "title", "type": "text", "required": true, "options": { "max": 200 } },
+                    { "name": "views", "type": "number" },
+                    { "name": "legacy", "type": "json" }
+                ],
+                "indexes": [{ "fields": ["title"] }],
+                "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z"
+            },
+            {
+                "id": "c2", "name": "old", "app_id": "a1",
+                "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z"
+            }
+        ]))
+        .unwrap()
+    }
+
+    fn desired() -> Vec<CollectionSchema> {
+        serde_norway::from_str(
+            r#"
+- name: posts
+  fields:
+    - { name: title, type: text, required: true }
+    - { name: views, type: text }
+    - { name: slug, type: text, unique: true }
+  indexes:
+    - { fields: [slug], unique: true }
+- name: tags
+  fields:
+    - { name: label, type: text }
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn diffs_desired_against_live() {
+        let plan = SchemaPlan::new(&desired(), &live());
+        assert_eq!(plan.changes.len(), 3);
+        assert!(plan.is_destructive());
+
+        let CollectionChange::Update { id, schema, diff } = &plan.changes[0] else {
+            panic!("expected update, got {:?}", plan.changes[0]);
+        };
+        assert_eq!(id, "c1");
+        assert_eq!(diff.added_fields[0].name, "slug");
+        assert_eq!(diff.removed_fields[0].name, "legacy");
+        assert_eq!(diff.changed_fields.len(), 1);
+        assert!(diff.changed_fields[0].is_destructive());
+        assert_eq!(diff.added_indexes.len(), 1);
+        assert_eq!(diff.removed_indexes.len(), 1);
+
+        // The live options of `title` are kept since the desired field has none.
+        let names: Vec<_> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["id", "title", "views", "slug"]);
+        assert!(schema.fields[1].options.is_some());
+
+        assert!(matches!(&plan.changes[1], CollectionChange::Create(s) if s.name == "tags"));
+        assert!(matches!(&plan.changes[2], CollectionChange::Delete { id, .. } if id == "c2"));
+    }
+
+    #[test]
+    fn schema_files_reject_duplicates_and_name_bad_files() {
+        let dir = std::env::temp_dir().join(format!("copepod-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let posts = "name: posts\nfields:\n  - { name: title, type: text }\n";
+        std::fs::write(dir.join("a.yaml"), posts).unwrap();
+        std::fs::write(dir.join("b.json"), r#"[{ "name": "posts" }]"#).unwrap();
+
+        let err = load_schema_files(&dir).unwrap_err().to_string();
+        assert!(err.contains("`posts`") && err.contains("a.yaml") && err.contains("b.json"));
+
+        std::fs::write(dir.join("b.json"), "{ not json").unwrap();
+        let err = load_schema_files(&dir).unwrap_err();
+        assert!(matches!(&err, CopepodError::Schema(m) if m.contains("b.json")));
+
+        std::fs::remove_file(dir.join("b.json")).unwrap();
+        assert_eq!(load_schema_files(&dir).unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn renders_plan() {
+        let text = SchemaPlan::new(&desired(), &live()).to_string();
+        assert!(text.contains("~ update collection posts\n"));
+        assert!(text.contains("    - field legacy (json)  [destructive]\n"));
+        assert!(text.contains("    ~ field views (number) -> views (text)  [destructive]\n"));
+        assert!(text.contains("+ create collection tags\n    + field label (text)\n"));
+        assert!(text.contains("- delete collection old  [destructive]\n"));
+        assert!(text.ends_with("Plan: 1 to create, 1 to update, 1 to delete (3 destructive).\n"));
+
+        assert_eq!(SchemaPlan::default().to_string(), "No changes.\n");
+    }
+
+    #[test]
+    fn collection_type_changes_are_destructive() {
+        let mut desired = desired();
+        desired[0].collection_type = Some("auth".into());
+        let plan = SchemaPlan::new(&desired[..1], &live()[..1]);
+        let CollectionChange::Update { diff, .. } = &plan.changes[0] else {
+            panic!("expected update, got {:?}", plan.changes[0]);
+        };
+        assert!(diff.collection_type.is_some());
+        assert!(plan.changes[0].is_destructive());
+        assert!(plan
+            .to_string()
+            .contains("    ~ type: none -> auth  [destructive]\n"));
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{CopepodError, Result};
+use crate::models::{
+    Collection, CollectionField, CollectionSchema, FieldOptions, FieldType, SelectOptions,
+};
+
+use super::{CopepodRecord, SYSTEM_FIELDS};
+
+/// A single field that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Checks record bodies against a collection schema before they are sent.
+///
+/// Validates field types, `required`, select `values`, `min`/`max` (number
+/// range or text length), `pattern` and the relation/file count limits, and
+/// reports every failing field at once. Fields unknown to the schema are left
+/// for the server to handle.
+#[derive(Debug, Clone)]
+pub struct SchemaValidator {
+    collection: String,
+    fields: Vec<FieldRule>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldRule {
+    field: CollectionField,
+    pattern: Option<Regex>,
+}
+
+impl SchemaValidator {
+    /// Build a validator from a collection definition.
+    ///
+    /// Fails with [`CopepodError::Validation`] if a `pattern` option is not a
+    /// valid regular expression.
+    pub fn new(schema: &CollectionSchema) -> Result<Self> {
+        let mut errors = Vec::new();
+        let fields = schema
+            .fields
+            .iter()
+            .filter(|f| !SYSTEM_FIELDS.contains(&f.name.as_str()))
+            .map(|field| {
+                let pattern = field
+                    .options
+                    .as_ref()
+                    .and_then(FieldOptions::text)
+                    .and_then(|o| o.pattern.as_deref())
+                    .and_then(|p| match Regex::new(p) {
+                        Ok(re) => Some(re),
+                        Err(e) => {
+                            errors.push(FieldError::new(
+                                &field.name,
+                                format!("invalid pattern option: {e}"),
+                            ));
+                            None
+                        }
+                    });
+                FieldRule {
+                    field: field.clone(),
+                    pattern,
+                }
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(CopepodError::Validation(errors));
+        }
+        Ok(Self {
+            collection: schema.name.clone(),
+            fields,
+        })
+    }
+
+    /// Build a validator from a collection fetched with `get_collection`.
+    pub fn from_collection(collection: &Collection) -> Result<Self> {
+        Self::new(&CollectionSchema::from(collection))
+    }
+
+    /// Build a validator from the schema of a [`CopepodRecord`] type.
+    pub fn for_record<T: CopepodRecord>() -> Result<Self> {
+        Self::new(&T::schema())
+    }
+
+    /// Return the collection name the validator was built from.
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// Validate a full record body, as sent on create.
+    pub fn validate(&self, record: &Value) -> std::result::Result<(), Vec<FieldError>> {
+        self.check(record, false)
+    }
+
+    /// Validate a partial record body, as sent on update.
+    ///
+    /// Missing fields are not reported; fields present are checked as usual.
+    pub fn validate_partial(&self, record: &Value) -> std::result::Result<(), Vec<FieldError>> {
+        self.check(record, true)
+    }
+
+    /// Serialize `body` and validate it as a full record.
+    pub fn validate_record(&self, body: &impl Serialize) -> Result<()> {
+        self.validate(&serde_json::to_value(body)?)
+            .map_err(CopepodError::Validation)
+    }
+
+    /// Serialize `body` and validate it as a partial record.
+    pub fn validate_partial_record(&self, body: &impl Serialize) -> Result<()> {
+        self.validate_partial(&serde_json::to_value(body)?)
+            .map_err(CopepodError::Validation)
+    }
+
+    fn check(&self, record: &Value, partial: bool) -> std::result::Result<(), Vec<FieldError>> {
+        let Value::Object(map) = record else {
+            return Err(vec![FieldError::new(
+                &self.collection,
+                "record body must be a JSON object",
+            )]);
+        };
+        let mut errors = Vec::new();
+        for rule in &self.fields {
+            rule.check(map, partial, &mut errors);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl FieldRule {
+    fn check(&self, map: &Map<String, Value>, partial: bool, errors: &mut Vec<FieldError>) {
+        let field = &self.field;
+        let name = field.name.as_str();
+        let value = match map.get(name) {
+            None if partial => return,
+            None | Some(Value::Null) => {
+                if field.required {
+                    errors.push(FieldError::new(name, "is required"));
+                }
+                return;
+            }
+            Some(value) => value,
+        };
+        if field.required && is_blank(value) {
+            errors.push(FieldError::new(name, "is required"));
+            return;
+        }
+
+        let options = field.options.as_ref();
+        let result = match field.field_type {
+            FieldType::Text | FieldType::Editor => self.check_text(value).map(drop),
+            FieldType::Email => self.check_text(value).and_then(|s| {
+                let valid = s.split_once('@').is_some_and(|(local, domain)| {
+                    !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+                });
+                if valid || s.is_empty() {
+                    Ok(())
+                } else {
+                    Err("must be a valid email address".to_string())
+                }
+            }),
+            FieldType::Url => self.check_text(value).and_then(|s| {
+                if s.is_empty() || url::Url::parse(s).is_ok() {
+                    Ok(())
+                } else {
+                    Err("must be a valid URL".to_string())
+                }
+            }),
+            FieldType::Number => self.check_number(value),
+            FieldType::Bool => value
+                .as_bool()
+                .map(drop)
+                .ok_or_else(|| "must be a boolean".to_string()),
+            FieldType::Date | FieldType::Autodate => check_date(value),
+            FieldType::Select => check_select(value, options.and_then(FieldOptions::select)),
+            FieldType::Relation => check_ids(
+                value,
+                options
+                    .and_then(FieldOptions::relation)
+                    .and_then(|o| o.max_select),
+                "record ID",
+            ),
+            FieldType::File => check_ids(
+                value,
+                options
+                    .and_then(FieldOptions::file)
+                    .and_then(|o| o.max_files),
+                "file name",
+            ),
+            _ => Ok(()),
+        };
+        if let Err(message) = result {
+            errors.push(FieldError::new(name, message));
+        }
+    }
+
+    fn check_text<'v>(&self, value: &'v Value) -> std::result::Result<&'v str, String> {
+        let s = value.as_str().ok_or("must be a string")?;
+        let len = s.chars().count() as u64;
+        let options = self.field.options.as_ref().and_then(FieldOptions::text);
+        if let Some(min) = options.and_then(|o| o.min) {
+            if len < min {
+                return Err(format!("must be at least {min} characters"));
+            }
+        }
+        if let Some(max) = options.and_then(|o| o.max) {
+            if len > max {
+                return Err(format!("must be at most {max} characters"));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !s.is_empty() && !pattern.is_match(s) {
+                return Err(format!("must match pattern {}", pattern.as_str()));
+            }
+        }
+        Ok(s)
+    }
+
+    fn check_number(&self, value: &Value) -> std::result::Result<(), String> {
+        let n = value.as_f64().ok_or("must be a number")?;
+        let options = self.field.options.as_ref().and_then(FieldOptions::number);
+        if let Some(min) = options.and_then(|o| o.min) {
+            if n < min {
+                return Err(format!("must be at least {min}"));
+            }
+        }
+        if let Some(max) = options.and_then(|o| o.max) {
+            if n > max {
+                return Err(format!("must be at most {max}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_select(value: &Value, options: Option<&SelectOptions>) -> std::result::Result<(), String> {
+    let selected = string_list(value, "a string or a list of strings")?;
+    let max = options.and_then(|o| o.max_select).unwrap_or(1);
+    if value.is_array() && max <= 1 {
+        return Err("must be a single value".into());
+    }
+    if selected.len() > max as usize {
+        return Err(format!("must have at most {max} values"));
+    }
+    if let Some(values) = options
+        .and_then(|o| o.values.as_ref())
+        .filter(|v| !v.is_empty())
+    {
+        let allowed: HashSet<&str> = values.iter().map(String::as_str).collect();
+        if let Some(bad) = selected.iter().find(|s| !allowed.contains(*s)) {
+            return Err(format!("'{bad}' is not one of the allowed values"));
+        }
+    }
+    Ok(())
+}
+
+fn check_ids(value: &Value, max: Option<u32>, what: &str) -> std::result::Result<(), String> {
+    let ids = string_list(value, &format!("a {what} or a list of them"))?;
+    if let Some(max) = max {
+        if max <= 1 && value.is_array() && ids.len() > 1 {
+            return Err(format!("must be a single {what}"));
+        }
+        if ids.len() > max as usize {
+            return Err(format!("must have at most {max} items"));
+        }
+    }
+    Ok(())
+}
+
+fn check_date(value: &Value) -> std::result::Result<(), String> {
+    let s = value.as_str().ok_or("must be a date string")?;
+    let valid = s.is_empty()
+        || DateTime::parse_from_rfc3339(s).is_ok()
+        || NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.fZ").is_ok()
+        || NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").is_ok()
+        || NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok();
+    if valid {
+        Ok(())
+    } else {
+        Err("must be a valid date".into())
+    }
+}
+
+/// Accept a string or an array of strings.
+fn string_list<'v>(value: &'v Value, expected: &str) -> std::result::Result<Vec<&'v str>, String> {
+    match value {
+        Value::String(s) => Ok(vec![s.as_str()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| v.as_str().ok_or_else(|| format!("must be {expected}")))
+            .collect(),
+        _ => Err(format!("must be {expected}")),
+    }
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn validator() -> SchemaValidator {
+        let schema: CollectionSchema = serde_json::from_value(json!({
+            "name": "posts",
+            "fields": [
+                {
+                    "name": "title", "type": "text", "required": true,
+                    "options": { "min": 3, "max": 20, "pattern": "^[A-Z]" }
+                },
+                { "name": "rating", "type": "number", "options": { "min": 1, "max": 5 } },
+                { "name": "status", "type": "select", "options": { "values": ["draft", "live"] } },
+                { "name": "author", "type": "relation", "options": { "max_select": 1 } },
+                { "name": "contact", "type": "email" },
+                { "name": "published", "type": "date" },
+                { "name": "meta", "type": "json" }
+            ]
+        }))
+        .unwrap();
+        SchemaValidator::new(&schema).unwrap()
+    }
+
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn accepts_valid_record() {
+        let record = json!({
+            "id": 1,
+            "title": "Hello",
+            "rating": 4,
+            "status": "live",
+            "author": "u1",
+            "contact": "a@example.com",
+            "published": "2024-05-01 10:00:00.000Z",
+            "meta": [1, 2],
+            "unknown": true
+        });
+        assert_eq!(validator().validate(&record), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let record = json!({
+            "rating": 9,
+            "status": "archived",
+            "author": ["u1", "u2"],
+            "contact": "nope",
+            "published": "yesterday"
+        });
+        let errors = validator().validate(&record).unwrap_err();
+        assert_eq!(
+            fields(errors),
+            [
+                "title",
+                "rating",
+                "status",
+                "author",
+                "contact",
+                "published"
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_text_length_and_pattern() {
+        let v = validator();
+        let check = |title: &str| v.validate(&json!({ "title": title })).map_err(fields);
+        assert!(check("Hi").is_err());
+        assert!(check("hello").is_err());
+        assert!(check("A very long title that exceeds the limit").is_err());
+        assert_eq!(check("Hello"), Ok(()));
+    }
+
+    #[test]
+    fn partial_skips_missing_required() {
+        let v = validator();
+        assert_eq!(v.validate_partial(&json!({ "rating": 2 })), Ok(()));
+        assert_eq!(
+            fields(v.validate_partial(&json!({ "title": "" })).unwrap_err()),
+            ["title"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_pattern() {
+        let schema: CollectionSchema = serde_json::from_value(json!({
+            "name": "posts",
+            "fields": [{ "name": "code", "type": "text", "options": { "pattern": "(" } }]
+        }))
+        .unwrap();
+        assert!(matches!(
+            SchemaValidator::new(&schema),
+            Err(CopepodError::Validation(errors)) if errors[0].field == "code"
+        ));
+    }
+}
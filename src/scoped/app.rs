@@ -1,6 +1,14 @@
 use crate::client::CopepodClient;
+use crate::error::Result;
+use crate::models::{Collection, CollectionSchema};
+use crate::realtime::SubscriptionBuilder;
+use crate::replica::{Replica, ReplicaStore};
+use crate::schema::{CopepodRecord, SchemaPlan};
+use crate::seed::{Fixtures, SeedReport};
 
-use super::{ScopedAppAuthClient, ScopedMigrationClient, ScopedRecordCollectionClient};
+use super::{
+    ScopedAppAuthClient, ScopedMigrationClient, ScopedRecordCollectionClient, TypedCollection,
+};
 
 /// Application-scoped client helpers.
 #[derive(Debug, Clone)]
@@ -33,6 +41,10 @@ impl<'a> ScopedAppClient<'a> {
         &self.app_id
     }
 
+    pub(crate) fn client(&self) -> &'a CopepodClient {
+        self.client
+    }
+
     /// Return auth helpers bound to a specific app auth collection.
     pub fn auth(&self, collection: impl Into<String>) -> ScopedAppAuthClient<'a> {
         ScopedAppAuthClient::new(self.client, &self.org_id, &self.app_id, collection)
@@ -43,8 +55,61 @@ impl<'a> ScopedAppClient<'a> {
         ScopedRecordCollectionClient::new(self.client, &self.org_id, &self.app_id, collection)
     }
 
+    /// Return typed record helpers bound to a specific collection.
+    pub fn collection<T>(&self, collection: impl Into<String>) -> TypedCollection<'a, T> {
+        self.records(collection).typed()
+    }
+
+    /// Return typed record helpers for the collection bound to `T`.
+    pub fn typed<T: CopepodRecord>(&self) -> TypedCollection<'a, T> {
+        self.collection(T::COLLECTION)
+    }
+
+    /// Return an offline-first replica of this app's collections, kept in
+    /// `store`. Add collections to mirror with [`Replica::collection`].
+    pub fn replica<S: ReplicaStore>(&self, store: S) -> Replica<'a, S> {
+        Replica::new(self.clone(), store)
+    }
+
+    /// Create or update fixture records in this app.
+    pub async fn seed(&self, fixtures: &Fixtures) -> Result<SeedReport> {
+        self.client
+            .seed_fixtures(&self.org_id, &self.app_id, fixtures)
+            .await
+    }
+
+    /// Delete fixture records from this app.
+    pub async fn teardown(&self, fixtures: &Fixtures) -> Result<usize> {
+        self.client
+            .teardown_fixtures(&self.org_id, &self.app_id, fixtures)
+            .await
+    }
+
+    /// Start building a realtime subscription to this app's events.
+    pub fn subscription(&self) -> SubscriptionBuilder<'a> {
+        self.client.subscription(&self.org_id, &self.app_id)
+    }
+
     /// Return migration helpers bound to this application.
     pub fn migrations(&self) -> ScopedMigrationClient<'a> {
         ScopedMigrationClient::new(self.client, &self.org_id, &self.app_id)
     }
+
+    /// Diff `desired` collections against the live collections of this app.
+    pub async fn plan_schema(&self, desired: &[CollectionSchema]) -> Result<SchemaPlan> {
+        self.client
+            .plan_schema(&self.org_id, &self.app_id, desired)
+            .await
+    }
+
+    /// Apply a schema plan to this app.
+    pub async fn apply_schema_plan(
+        &self,
+        plan: &SchemaPlan,
+        allow_destructive: bool,
+    ) -> Result<Vec<Collection>> {
+        self.client
+            .apply_schema_plan(&self.org_id, &self.app_id, plan, allow_destructive)
+            .await
+    }
 }
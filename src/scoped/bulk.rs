@@ -0,0 +1,349 @@
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::client::RETRY_AFTER;
+use crate::error::{CopepodError, Result};
+
+use super::ScopedRecordCollectionClient;
+
+/// Options controlling a bulk record operation.
+#[derive(Debug, Clone)]
+pub struct BulkOptions {
+    concurrency: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+    resume_from: Option<BulkCheckpoint>,
+    checkpoint_file: Option<PathBuf>,
+    checkpoint_every: usize,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            resume_from: None,
+            checkpoint_file: None,
+            checkpoint_every: 100,
+        }
+    }
+}
+
+impl BulkOptions {
+    /// Create options with the defaults noted on each setter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of requests in flight (default: 8).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set how often a rate-limited (429) or unavailable (503) request is
+    /// retried before it is reported as failed (default: 3).
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Set the initial retry delay, doubled on each attempt (default: 500ms).
+    /// A `Retry-After` delay sent by the server is used instead when present.
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Skip items already completed in a previous run.
+    pub fn resume_from(mut self, checkpoint: BulkCheckpoint) -> Self {
+        self.resume_from = Some(checkpoint);
+        self
+    }
+
+    /// Persist progress to a JSON checkpoint file, resuming from it if it
+    /// already exists.
+    ///
+    /// The file is removed once a run completes without failures, so the
+    /// next run starts over.
+    pub fn checkpoint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint_file = Some(path.into());
+        self
+    }
+
+    /// Set how many completed items trigger a checkpoint file write (default: 100).
+    pub fn checkpoint_every(mut self, items: usize) -> Self {
+        self.checkpoint_every = items.max(1);
+        self
+    }
+}
+
+/// Progress of a bulk operation, keyed by the index of each input item.
+///
+/// A checkpoint is only meaningful when resuming with the same input in the
+/// same order; resuming with different input fails.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkCheckpoint {
+    pub completed: BTreeSet<usize>,
+    /// A hash of the input the checkpoint was recorded for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl BulkCheckpoint {
+    /// Whether the item at `index` has already been processed successfully.
+    pub fn is_completed(&self, index: usize) -> bool {
+        self.completed.contains(&index)
+    }
+
+    /// Load a checkpoint from a JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).map_err(|e| {
+            CopepodError::Io(format!("failed to read checkpoint {}: {e}", path.display()))
+        })?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Write the checkpoint to a JSON file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec(self)?;
+        std::fs::write(path, data).map_err(|e| {
+            CopepodError::Io(format!(
+                "failed to write checkpoint {}: {e}",
+                path.display()
+            ))
+        })
+    }
+}
+
+/// A successfully processed bulk item.
+#[derive(Debug, Clone)]
+pub struct BulkSuccess {
+    /// Index of the item in the input.
+    pub index: usize,
+    /// The record returned by the server (`None` for deletes).
+    pub record: Option<Value>,
+}
+
+/// A bulk item that failed after all retries.
+#[derive(Debug)]
+pub struct BulkFailure {
+    /// Index of the item in the input.
+    pub index: usize,
+    pub error: CopepodError,
+}
+
+/// Per-item outcome of a bulk operation.
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    pub succeeded: Vec<BulkSuccess>,
+    pub failed: Vec<BulkFailure>,
+    /// Number of items skipped because the checkpoint marked them completed.
+    pub skipped: usize,
+    /// Progress including this run, for resuming after failures.
+    pub checkpoint: BulkCheckpoint,
+}
+
+impl BulkReport {
+    /// Whether every attempted item succeeded.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl ScopedRecordCollectionClient<'_> {
+    /// Create many records with bounded concurrency.
+    pub async fn create_many<B>(&self, items: &[B], options: &BulkOptions) -> Result<BulkReport>
+    where
+        B: Serialize,
+    {
+        run_bulk(items.len(), items, options, |i| async move {
+            self.create(&items[i]).await.map(Some)
+        })
+        .await
+    }
+
+    /// Update many records, given as `(record_id, body)` pairs, with bounded
+    /// concurrency.
+    pub async fn update_many<S, B>(
+        &self,
+        items: &[(S, B)],
+        options: &BulkOptions,
+    ) -> Result<BulkReport>
+    where
+        S: AsRef<str>,
+        B: Serialize,
+    {
+        let input = items
+            .iter()
+            .map(|(id, body)| Ok((id.as_ref(), serde_json::to_value(body)?)))
+            .collect::<Result<Vec<_>>>()?;
+        run_bulk(items.len(), &input, options, |i| async move {
+            let (id, body) = &items[i];
+            self.update(id.as_ref(), body).await.map(Some)
+        })
+        .await
+    }
+
+    /// Create or update many records, matching existing records on the value
+    /// of `key_field` in each body (see [`Self::upsert_by`]).
+    pub async fn upsert_many<B>(
+        &self,
+        key_field: &str,
+        items: &[B],
+        options: &BulkOptions,
+    ) -> Result<BulkReport>
+    where
+        B: Serialize,
+    {
+        run_bulk(items.len(), items, options, |i| async move {
+            let body = serde_json::to_value(&items[i])?;
+            let key = body.get(key_field).ok_or_else(|| {
+                CopepodError::Query(format!("item {i} has no `{key_field}` field"))
+            })?;
+            let upserted = self.upsert_by(key_field, key.clone(), &body).await?;
+            Ok(Some(upserted.record))
+        })
+        .await
+    }
+
+    /// Delete many records by ID with bounded concurrency.
+    pub async fn delete_many<S>(&self, ids: &[S], options: &BulkOptions) -> Result<BulkReport>
+    where
+        S: AsRef<str>,
+    {
+        let input: Vec<&str> = ids.iter().map(AsRef::as_ref).collect();
+        run_bulk(ids.len(), &input, options, |i| async move {
+            self.delete(ids[i].as_ref()).await.map(|_| None)
+        })
+        .await
+    }
+}
+
+/// Run `op` for every index not yet in the checkpoint and collect a report.
+///
+/// `input` is what the checkpoint is fingerprinted from.
+pub(super) async fn run_bulk<I, F, Fut>(
+    len: usize,
+    input: &I,
+    options: &BulkOptions,
+    op: F,
+) -> Result<BulkReport>
+where
+    I: Serialize + ?Sized,
+    F: Fn(usize) -> Fut,
+    Fut: Future<Output = Result<Option<Value>>>,
+{
+    let fingerprint = fingerprint(input)?;
+    let mut checkpoint = match (&options.resume_from, &options.checkpoint_file) {
+        (Some(checkpoint), _) => checkpoint.clone(),
+        (None, Some(path)) if path.exists() => BulkCheckpoint::load(path)?,
+        _ => BulkCheckpoint::default(),
+    };
+    if checkpoint
+        .fingerprint
+        .as_ref()
+        .is_some_and(|f| *f != fingerprint)
+    {
+        return Err(CopepodError::Query(
+            "bulk checkpoint was recorded for different input".into(),
+        ));
+    }
+    checkpoint.fingerprint = Some(fingerprint);
+
+    let pending: Vec<usize> = (0..len).filter(|i| !checkpoint.is_completed(*i)).collect();
+    let mut report = BulkReport {
+        skipped: len - pending.len(),
+        ..Default::default()
+    };
+
+    let op = &op;
+    let mut results = stream::iter(pending)
+        .map(|index| async move { (index, with_retry(options, || op(index)).await) })
+        .buffer_unordered(options.concurrency);
+
+    let mut since_save = 0;
+    while let Some((index, result)) = results.next().await {
+        match result {
+            Ok(record) => {
+                checkpoint.completed.insert(index);
+                report.succeeded.push(BulkSuccess { index, record });
+                since_save += 1;
+            }
+            Err(error) => report.failed.push(BulkFailure { index, error }),
+        }
+        if let Some(path) = &options.checkpoint_file {
+            if since_save >= options.checkpoint_every {
+                checkpoint.save(path)?;
+                since_save = 0;
+            }
+        }
+    }
+
+    if let Some(path) = &options.checkpoint_file {
+        if report.failed.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path).map_err(|e| {
+                    CopepodError::Io(format!(
+                        "failed to remove checkpoint {}: {e}",
+                        path.display()
+                    ))
+                })?;
+            }
+        } else {
+            checkpoint.save(path)?;
+        }
+    }
+    report.succeeded.sort_by_key(|s| s.index);
+    report.failed.sort_by_key(|f| f.index);
+    report.checkpoint = checkpoint;
+    Ok(report)
+}
+
+/// Hash the JSON form of a bulk operation's input (64-bit FNV-1a, which is
+/// stable across runs and builds).
+fn fingerprint<I: Serialize + ?Sized>(input: &I) -> Result<String> {
+    let hash = serde_json::to_vec(input)?
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    Ok(format!("{hash:016x}"))
+}
+
+/// Retry `op` with exponential backoff while the server reports rate
+/// limiting (429) or temporary unavailability (503), waiting as long as the
+/// server's `Retry-After` asks when it sends one.
+async fn with_retry<F, Fut, T>(options: &BulkOptions, op: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = options.retry_backoff;
+    let mut attempt = 0;
+    loop {
+        let (result, retry_after) = RETRY_AFTER
+            .scope(Cell::new(None), async {
+                let result = op().await;
+                (result, RETRY_AFTER.with(Cell::get))
+            })
+            .await;
+        match result {
+            Err(CopepodError::Api {
+                status: 429 | 503, ..
+            }) if attempt < options.max_retries => {}
+            result => return result,
+        }
+        tokio::time::sleep(retry_after.unwrap_or(delay)).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,398 @@
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use lru::LruCache;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{CopepodError, Result};
+use crate::models::{ListResult, RecordEvent};
+use crate::query::RecordQueryBuilder;
+use crate::realtime::{ConnectionState, RealtimeEvent, ReconnectOptions, SubscriptionEvent};
+
+use super::ScopedRecordCollectionClient;
+
+/// Options controlling a [`CachedCollection`].
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    capacity: NonZeroUsize,
+    ttl: Duration,
+    query_ttl: Duration,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            capacity: NonZeroUsize::new(1000).unwrap(),
+            ttl: Duration::from_secs(60),
+            query_ttl: Duration::from_secs(10),
+        }
+    }
+}
+
+impl CacheOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of cached records and query results
+    /// (default: 1000). The least recently used entry is evicted first.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        self
+    }
+
+    /// Set how long a record fetched by ID stays cached (default: 60s).
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set how long a query result stays cached (default: 10s).
+    pub fn query_ttl(mut self, ttl: Duration) -> Self {
+        self.query_ttl = ttl;
+        self
+    }
+}
+
+/// A snapshot of cache counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room for new ones.
+    pub evictions: u64,
+    /// Entries dropped because of writes or realtime events.
+    pub invalidations: u64,
+}
+
+impl CacheMetrics {
+    /// Fraction of lookups served from the cache.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Record(String),
+    Query(String),
+}
+
+enum CachedValue {
+    Record(Value),
+    List(ListResult<Value>),
+}
+
+struct CacheEntry {
+    value: CachedValue,
+    expires_at: Instant,
+}
+
+struct CacheShared {
+    collection: String,
+    options: CacheOptions,
+    entries: Mutex<LruCache<CacheKey, CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+    /// Bumped on every invalidation, so fetches that raced with one are not
+    /// cached.
+    generation: AtomicU64,
+}
+
+impl CacheShared {
+    fn entries(&self) -> MutexGuard<'_, LruCache<CacheKey, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lookup<T>(&self, key: &CacheKey, read: impl FnOnce(&CachedValue) -> Option<T>) -> Option<T> {
+        let mut entries = self.entries();
+        let found = match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => read(&entry.value),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    fn store(&self, key: CacheKey, value: CachedValue, ttl: Duration, generation: u64) {
+        let entry = CacheEntry {
+            value,
+            expires_at: Instant::now() + ttl,
+        };
+        let mut entries = self.entries();
+        if self.generation() != generation {
+            return;
+        }
+        if let Some((evicted, _)) = entries.push(key.clone(), entry) {
+            if evicted != key {
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Drop every cached query result, and the record `id` if given.
+    fn invalidate(&self, id: Option<&str>) {
+        let mut entries = self.entries();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        let stale: Vec<CacheKey> = entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| match key {
+                CacheKey::Record(record_id) => Some(record_id.as_str()) == id,
+                CacheKey::Query(_) => true,
+            })
+            .cloned()
+            .collect();
+        for key in &stale {
+            entries.pop(key);
+        }
+        self.invalidations
+            .fetch_add(stale.len() as u64, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        let mut entries = self.entries();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.invalidations
+            .fetch_add(entries.len() as u64, Ordering::Relaxed);
+        entries.clear();
+    }
+
+    fn apply_event(&self, event: &RecordEvent) {
+        if event.collection != self.collection {
+            return;
+        }
+        let id = event.record.get("id").and_then(Value::as_str);
+        self.invalidate(id);
+    }
+}
+
+/// Read-through cache over a record collection.
+///
+/// Records fetched by ID and query results are kept in an in-memory LRU with
+/// a TTL. Writes made through the cache invalidate the affected entries;
+/// writes made elsewhere are picked up from the app's realtime events via
+/// [`CachedCollection::realtime_invalidation`]. Clones share the same cache.
+#[derive(Clone)]
+pub struct CachedCollection<'a> {
+    records: ScopedRecordCollectionClient<'a>,
+    shared: Arc<CacheShared>,
+}
+
+impl<'a> CachedCollection<'a> {
+    pub(crate) fn new(records: ScopedRecordCollectionClient<'a>, options: CacheOptions) -> Self {
+        let shared = CacheShared {
+            collection: records.collection().to_string(),
+            entries: Mutex::new(LruCache::new(options.capacity)),
+            options,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+        };
+        Self {
+            records,
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Return the bound collection name.
+    pub fn collection(&self) -> &str {
+        self.records.collection()
+    }
+
+    /// Return the uncached record helpers for this collection.
+    pub fn uncached(&self) -> &ScopedRecordCollectionClient<'a> {
+        &self.records
+    }
+
+    /// Start building a query to pass to [`CachedCollection::list`].
+    pub fn query(&self) -> RecordQueryBuilder<'a> {
+        self.records.query()
+    }
+
+    /// Get a record by ID, from the cache if present.
+    pub async fn get(&self, record_id: &str) -> Result<Value> {
+        let key = CacheKey::Record(record_id.to_string());
+        let cached = self.shared.lookup(&key, |value| match value {
+            CachedValue::Record(record) => Some(record.clone()),
+            CachedValue::List(_) => None,
+        });
+        if let Some(record) = cached {
+            return Ok(record);
+        }
+        let generation = self.shared.generation();
+        let record = self.records.query().get_one(record_id).await?;
+        self.shared.store(
+            key,
+            CachedValue::Record(record.clone()),
+            self.shared.options.ttl,
+            generation,
+        );
+        Ok(record)
+    }
+
+    /// Run a query, from the cache if the same query was run recently.
+    ///
+    /// Results are keyed by the full query (filter, sort, expand, fields
+    /// and page), and invalidated by any write to the collection.
+    pub async fn list(&self, query: RecordQueryBuilder<'a>) -> Result<ListResult<Value>> {
+        let key = CacheKey::Query(query.cache_key());
+        let cached = self.shared.lookup(&key, |value| match value {
+            CachedValue::List(page) => Some(page.clone()),
+            CachedValue::Record(_) => None,
+        });
+        if let Some(page) = cached {
+            return Ok(page);
+        }
+        let generation = self.shared.generation();
+        let page = query.list().await?;
+        self.shared.store(
+            key,
+            CachedValue::List(page.clone()),
+            self.shared.options.query_ttl,
+            generation,
+        );
+        Ok(page)
+    }
+
+    /// Create a record and invalidate cached query results.
+    pub async fn create(&self, body: &impl Serialize) -> Result<Value> {
+        let record = self.records.create(body).await?;
+        self.shared.invalidate(None);
+        Ok(record)
+    }
+
+    /// Update a record and invalidate it and cached query results.
+    pub async fn update(&self, record_id: &str, body: &impl Serialize) -> Result<Value> {
+        let record = self.records.update(record_id, body).await?;
+        self.shared.invalidate(Some(record_id));
+        Ok(record)
+    }
+
+    /// Delete a record and invalidate it and cached query results.
+    pub async fn delete(&self, record_id: &str) -> Result<()> {
+        self.records.delete(record_id).await?;
+        self.shared.invalidate(Some(record_id));
+        Ok(())
+    }
+
+    /// Drop a cached record and all cached query results.
+    pub fn invalidate(&self, record_id: &str) {
+        self.shared.invalidate(Some(record_id));
+    }
+
+    /// Drop every cached entry.
+    pub fn clear(&self) {
+        self.shared.clear();
+    }
+
+    /// Number of cached entries, including expired ones not yet dropped.
+    pub fn len(&self) -> usize {
+        self.shared.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply a realtime record event, invalidating what it changed.
+    ///
+    /// Events for other collections are ignored.
+    pub fn apply_event(&self, event: &RecordEvent) {
+        self.shared.apply_event(event);
+    }
+
+    /// Return the current cache counters.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.shared.hits.load(Ordering::Relaxed),
+            misses: self.shared.misses.load(Ordering::Relaxed),
+            evictions: self.shared.evictions.load(Ordering::Relaxed),
+            invalidations: self.shared.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Subscribe to the app's realtime events and return a future that
+    /// applies them to this cache.
+    ///
+    /// The subscription reconnects with backoff when the connection drops,
+    /// like [`subscribe_with_reconnect`]. Events missed while disconnected
+    /// can't be seen, so the cache is cleared whenever the connection drops
+    /// or reconnects. Fails if the first connection can't be opened; the
+    /// future completes, clearing the cache, only if the subscription gives
+    /// up.
+    ///
+    /// The future does not borrow the client; spawn it on your runtime:
+    ///
+    /// ```ignore
+    /// let cache = client.app("org", "app").records("posts").cached(CacheOptions::new());
+    /// tokio::spawn(cache.realtime_invalidation().await?);
+    /// ```
+    ///
+    /// [`subscribe_with_reconnect`]: crate::client::CopepodClient::subscribe_with_reconnect
+    pub async fn realtime_invalidation(&self) -> Result<impl Future<Output = ()> + Send + 'static> {
+        let mut events = Box::pin(self.records.client().subscribe_with_reconnect(
+            self.records.org_id(),
+            self.records.app_id(),
+            ReconnectOptions::default(),
+        ));
+        loop {
+            match events.next().await {
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Connected))) => break,
+                Some(Ok(SubscriptionEvent::State(ConnectionState::Closed))) | None => {
+                    return Err(CopepodError::Sse(
+                        "could not open the realtime connection for cache invalidation".into(),
+                    ))
+                }
+                Some(Err(e)) => return Err(e),
+                Some(Ok(_)) => {}
+            }
+        }
+        // Anything cached before the subscription connected may be stale.
+        self.shared.clear();
+        let shared = Arc::clone(&self.shared);
+        Ok(async move {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(SubscriptionEvent::Event(RealtimeEvent::Record(event))) => {
+                        shared.apply_event(&event);
+                    }
+                    Ok(SubscriptionEvent::State(
+                        ConnectionState::Reconnecting { .. } | ConnectionState::Connected,
+                    ))
+                    | Ok(SubscriptionEvent::Lagged { .. }) => shared.clear(),
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("cache invalidation subscription error: {e}"),
+                }
+            }
+            shared.clear();
+        })
+    }
+}
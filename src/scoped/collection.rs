@@ -0,0 +1,98 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::models::{ListResult, Record};
+use crate::query::RecordQueryBuilder;
+use crate::schema::SchemaValidator;
+
+use super::ScopedRecordCollectionClient;
+
+/// Typed record helpers bound to a specific collection.
+///
+/// Records are deserialized into [`Record<T>`], which carries the system
+/// fields (`id`, `created`, `updated`) alongside the user data `T`.
+pub struct TypedCollection<'a, T> {
+    records: ScopedRecordCollectionClient<'a>,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TypedCollection<'a, T> {
+    pub(crate) fn new(records: ScopedRecordCollectionClient<'a>) -> Self {
+        Self {
+            records,
+            _record: PhantomData,
+        }
+    }
+
+    /// Return the bound collection name.
+    pub fn collection(&self) -> &str {
+        self.records.collection()
+    }
+
+    /// Return the untyped record helpers for this collection.
+    pub fn untyped(&self) -> &ScopedRecordCollectionClient<'a> {
+        &self.records
+    }
+
+    /// Validate bodies against `validator` before `create` and `update`.
+    pub fn with_validator(self, validator: SchemaValidator) -> Self {
+        Self::new(self.records.with_validator(validator))
+    }
+
+    /// Fetch this collection's schema and validate bodies against it.
+    pub async fn validated(self) -> Result<Self> {
+        Ok(Self::new(self.records.validated().await?))
+    }
+
+    /// Start building a typed query for this collection.
+    pub fn query(&self) -> RecordQueryBuilder<'a, Record<T>> {
+        self.records.query().typed()
+    }
+
+    /// Delete a record from this collection.
+    pub async fn delete(&self, record_id: &str) -> Result<()> {
+        self.records.delete(record_id).await
+    }
+}
+
+impl<T: DeserializeOwned> TypedCollection<'_, T> {
+    /// List the first page of records in this collection.
+    pub async fn list(&self) -> Result<ListResult<Record<T>>> {
+        self.query().list().await
+    }
+
+    /// Get a single record by ID.
+    pub async fn get(&self, record_id: &str) -> Result<Record<T>> {
+        self.query().get_one(record_id).await
+    }
+
+    /// Create a new record in this collection.
+    pub async fn create(&self, body: &impl Serialize) -> Result<Record<T>> {
+        let value = self.records.create(body).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Update an existing record in this collection.
+    pub async fn update(&self, record_id: &str, body: &impl Serialize) -> Result<Record<T>> {
+        let value = self.records.update(record_id, body).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl<T> Clone for TypedCollection<'_, T> {
+    fn clone(&self) -> Self {
+        Self::new(self.records.clone())
+    }
+}
+
+impl<T> fmt::Debug for TypedCollection<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedCollection")
+            .field("records", &self.records)
+            .finish()
+    }
+}
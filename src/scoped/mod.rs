@@ -1,11 +1,19 @@
 mod app;
 mod auth;
+mod bulk;
+mod cache;
+mod collection;
 mod migrations;
 mod org;
 mod records;
+mod transfer;
 
 pub use app::ScopedAppClient;
 pub use auth::ScopedAppAuthClient;
+pub use bulk::{BulkCheckpoint, BulkFailure, BulkOptions, BulkReport, BulkSuccess};
+pub use cache::{CacheMetrics, CacheOptions, CachedCollection};
+pub use collection::TypedCollection;
 pub use migrations::ScopedMigrationClient;
 pub use org::ScopedOrgClient;
-pub use records::ScopedRecordCollectionClient;
+pub use records::{ScopedRecordCollectionClient, UpsertAction, UpsertResult};
+pub use transfer::{DataFormat, ImportOptions};
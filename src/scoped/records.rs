@@ -1,9 +1,29 @@
+use std::sync::Arc;
+
 use serde::Serialize;
 use serde_json::Value;
 
 use crate::client::CopepodClient;
-use crate::error::Result;
-use crate::query::RecordQueryBuilder;
+use crate::error::{CopepodError, Result};
+use crate::query::{filter_literal, is_field_name, RecordQueryBuilder};
+use crate::realtime::SubscriptionBuilder;
+use crate::schema::SchemaValidator;
+
+use super::{CacheOptions, CachedCollection, TypedCollection};
+
+/// Which branch an upsert took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertAction {
+    Created,
+    Updated,
+}
+
+/// Result of [`ScopedRecordCollectionClient::upsert_by`].
+#[derive(Debug, Clone)]
+pub struct UpsertResult {
+    pub action: UpsertAction,
+    pub record: Value,
+}
 
 /// Record helpers bound to a specific collection.
 #[derive(Debug, Clone)]
@@ -12,6 +32,7 @@ pub struct ScopedRecordCollectionClient<'a> {
     org_id: String,
     app_id: String,
     collection: String,
+    validator: Option<Arc<SchemaValidator>>,
 }
 
 impl<'a> ScopedRecordCollectionClient<'a> {
@@ -26,6 +47,7 @@ impl<'a> ScopedRecordCollectionClient<'a> {
             org_id: org_id.to_string(),
             app_id: app_id.to_string(),
             collection: collection.into(),
+            validator: None,
         }
     }
 
@@ -34,14 +56,70 @@ impl<'a> ScopedRecordCollectionClient<'a> {
         &self.collection
     }
 
+    pub(crate) fn client(&self) -> &'a CopepodClient {
+        self.client
+    }
+
+    pub(crate) fn org_id(&self) -> &str {
+        &self.org_id
+    }
+
+    pub(crate) fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Validate bodies against `validator` before `create` and `update`.
+    ///
+    /// Invalid bodies fail with [`CopepodError::Validation`] without a
+    /// request being sent. Updates are validated as partial records.
+    pub fn with_validator(mut self, validator: SchemaValidator) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Fetch this collection's schema and validate bodies against it.
+    pub async fn validated(self) -> Result<Self> {
+        let collection = self
+            .client
+            .get_collection(&self.org_id, &self.app_id, &self.collection)
+            .await?;
+        let validator = SchemaValidator::from_collection(&collection)?;
+        Ok(self.with_validator(validator))
+    }
+
+    /// Return the validator bodies are checked against, if any.
+    pub fn validator(&self) -> Option<&SchemaValidator> {
+        self.validator.as_deref()
+    }
+
     /// Start building a query for this collection.
     pub fn query(&self) -> RecordQueryBuilder<'a> {
         self.client
             .records(&self.org_id, &self.app_id, &self.collection)
     }
 
+    /// Start building a realtime subscription to this collection's events.
+    pub fn subscribe(&self) -> SubscriptionBuilder<'a> {
+        self.client
+            .subscription(&self.org_id, &self.app_id)
+            .collection(&self.collection)
+    }
+
+    /// Wrap in a read-through cache.
+    pub fn cached(self, options: CacheOptions) -> CachedCollection<'a> {
+        CachedCollection::new(self, options)
+    }
+
+    /// Convert into typed helpers that deserialize records into `T`.
+    pub fn typed<T>(self) -> TypedCollection<'a, T> {
+        TypedCollection::new(self)
+    }
+
     /// Create a new record in this collection.
     pub async fn create(&self, body: &impl Serialize) -> Result<Value> {
+        if let Some(validator) = &self.validator {
+            validator.validate_record(body)?;
+        }
         self.client
             .create_record(&self.org_id, &self.app_id, &self.collection, body)
             .await
@@ -49,6 +127,9 @@ impl<'a> ScopedRecordCollectionClient<'a> {
 
     /// Update an existing record in this collection.
     pub async fn update(&self, record_id: &str, body: &impl Serialize) -> Result<Value> {
+        if let Some(validator) = &self.validator {
+            validator.validate_partial_record(body)?;
+        }
         self.client
             .update_record(
                 &self.org_id,
@@ -66,4 +147,81 @@ impl<'a> ScopedRecordCollectionClient<'a> {
             .delete_record(&self.org_id, &self.app_id, &self.collection, record_id)
             .await
     }
+
+    /// Create or update the record whose `field` equals `value`.
+    ///
+    /// Looks the record up with a filter on `field`, then patches it with
+    /// `body` or creates it (adding `field` to `body` if missing). If the
+    /// create loses a race against a concurrent writer and fails with a
+    /// unique-constraint conflict, the lookup is retried and the winner is
+    /// updated instead. `field` must be a plain (optionally dotted) field
+    /// name; anything else fails with [`CopepodError::Query`].
+    pub async fn upsert_by(
+        &self,
+        field: &str,
+        value: impl Into<Value>,
+        body: &impl Serialize,
+    ) -> Result<UpsertResult> {
+        if !is_field_name(field) {
+            return Err(CopepodError::Query(format!(
+                "`{field}` is not a valid field name"
+            )));
+        }
+        let value = value.into();
+        let filter = format!("{} = {}", field, filter_literal(&value)?);
+
+        if let Some(id) = self.find_id(&filter).await? {
+            let record = self.update(&id, body).await?;
+            return Ok(UpsertResult {
+                action: UpsertAction::Updated,
+                record,
+            });
+        }
+
+        let mut create_body = serde_json::to_value(body)?;
+        if let Value::Object(ref mut map) = create_body {
+            map.entry(field).or_insert(value);
+        }
+        match self.create(&create_body).await {
+            Ok(record) => Ok(UpsertResult {
+                action: UpsertAction::Created,
+                record,
+            }),
+            Err(err) if is_unique_conflict(&err) => match self.find_id(&filter).await? {
+                Some(id) => Ok(UpsertResult {
+                    action: UpsertAction::Updated,
+                    record: self.update(&id, body).await?,
+                }),
+                None => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Return the ID of the first record matching `filter`.
+    async fn find_id(&self, filter: &str) -> Result<Option<String>> {
+        let page = self.query().filter(filter).per_page(1).list().await?;
+        Ok(page
+            .items
+            .first()
+            .and_then(|r| r.get("id"))
+            .and_then(|id| id.as_str())
+            .map(String::from))
+    }
+}
+
+/// Whether a create failed because a unique constraint was violated.
+fn is_unique_conflict(err: &CopepodError) -> bool {
+    match err {
+        CopepodError::Api { status: 409, .. } => true,
+        CopepodError::Api {
+            status: 400,
+            code,
+            message,
+        } => {
+            code.as_deref().is_some_and(|c| c.contains("unique"))
+                || message.to_lowercase().contains("unique")
+        }
+        _ => false,
+    }
 }
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+use futures_util::TryStreamExt;
+use serde_json::{json, Map, Value};
+
+use crate::error::{CopepodError, Result};
+use crate::models::{CollectionField, FieldType};
+use crate::schema::{SchemaValidator, SYSTEM_FIELDS};
+
+use super::bulk::run_bulk;
+use super::{BulkOptions, BulkReport, ScopedRecordCollectionClient};
+
+/// Serialization format for collection export and import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    /// One JSON record per line.
+    Ndjson,
+    /// Comma-separated values with a header row. Arrays and objects are
+    /// written as JSON text.
+    Csv,
+}
+
+/// Options controlling [`ScopedRecordCollectionClient::import_collection`].
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    bulk: BulkOptions,
+    columns: BTreeMap<String, Option<String>>,
+    validate: bool,
+    reject_file: Option<PathBuf>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            bulk: BulkOptions::default(),
+            columns: BTreeMap::new(),
+            validate: true,
+            reject_file: None,
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the options for the bulk insert, including its checkpoint file
+    /// (default: [`BulkOptions::default`]).
+    pub fn bulk(mut self, options: BulkOptions) -> Self {
+        self.bulk = options;
+        self
+    }
+
+    /// Import the input column or key `from` into the field `to`.
+    pub fn map_column(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.columns.insert(from.into(), Some(to.into()));
+        self
+    }
+
+    /// Drop the input column or key `column`.
+    pub fn skip_column(mut self, column: impl Into<String>) -> Self {
+        self.columns.insert(column.into(), None);
+        self
+    }
+
+    /// Validate rows against the collection schema before inserting them
+    /// (default: true).
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Write rejected rows to an NDJSON file, one
+    /// `{"row": ..., "error": ..., "data": ...}` object per line.
+    pub fn reject_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.reject_file = Some(path.into());
+        self
+    }
+}
+
+impl ScopedRecordCollectionClient<'_> {
+    /// Stream every record of the collection to `writer`, returning the
+    /// number of records written.
+    ///
+    /// Records are fetched page by page in ID order and written as they
+    /// arrive. CSV output has a header of the system fields followed by the
+    /// collection's fields.
+    pub async fn export_collection<W: Write>(&self, writer: W, format: DataFormat) -> Result<u64> {
+        let mut records = std::pin::pin!(self.query().iter_by_cursor("id"));
+        let mut count = 0;
+        match format {
+            DataFormat::Ndjson => {
+                let mut writer = writer;
+                while let Some(record) = records.try_next().await? {
+                    serde_json::to_writer(&mut writer, &record)?;
+                    writer.write_all(b"\n").map_err(write_error)?;
+                    count += 1;
+                }
+                writer.flush().map_err(write_error)?;
+            }
+            DataFormat::Csv => {
+                let collection = self
+                    .client()
+                    .get_collection(self.org_id(), self.app_id(), self.collection())
+                    .await?;
+                let header: Vec<&str> = SYSTEM_FIELDS
+                    .iter()
+                    .copied()
+                    .chain(
+                        collection
+                            .fields
+                            .iter()
+                            .map(|f| f.name.as_str())
+                            .filter(|name| !SYSTEM_FIELDS.contains(name)),
+                    )
+                    .collect();
+                let mut writer = csv::Writer::from_writer(writer);
+                writer.write_record(&header).map_err(csv_write_error)?;
+                while let Some(record) = records.try_next().await? {
+                    let row = header.iter().map(|field| csv_cell(record.get(*field)));
+                    writer.write_record(row).map_err(csv_write_error)?;
+                    count += 1;
+                }
+                writer.flush().map_err(write_error)?;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Parse records from `reader` and create them in this collection, with
+    /// the same concurrency, retries and checkpointing as
+    /// [`create_many`](Self::create_many).
+    ///
+    /// Columns are renamed or dropped as configured in `options`; `created`
+    /// and `updated` are always dropped. CSV cells are converted to the
+    /// collection's field types. Rows that fail to parse or validate are
+    /// reported as failures alongside rows the server rejected, keyed by
+    /// their zero-based position in the input (excluding the CSV header and
+    /// blank NDJSON lines), so the report's checkpoint resumes correctly
+    /// with the same input.
+    ///
+    /// The whole input is parsed into memory before the first record is
+    /// created, since the checkpoint fingerprints all of it. Split very
+    /// large inputs into several imports, each with its own checkpoint file.
+    pub async fn import_collection<R: Read>(
+        &self,
+        reader: R,
+        format: DataFormat,
+        options: &ImportOptions,
+    ) -> Result<BulkReport> {
+        let collection = if options.validate || format == DataFormat::Csv {
+            let collection = self
+                .client()
+                .get_collection(self.org_id(), self.app_id(), self.collection())
+                .await?;
+            Some(collection)
+        } else {
+            None
+        };
+        let fields = collection
+            .as_ref()
+            .map(|c| c.fields.as_slice())
+            .unwrap_or(&[]);
+        let records = match &collection {
+            Some(collection) if options.validate && self.validator().is_none() => self
+                .clone()
+                .with_validator(SchemaValidator::from_collection(collection)?),
+            _ => self.clone(),
+        };
+
+        let rows = match format {
+            DataFormat::Ndjson => read_ndjson(reader)?,
+            DataFormat::Csv => read_csv(reader, fields)?,
+        };
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .map(|row| row.map(|body| map_columns(body, &options.columns)))
+            .collect();
+
+        let records = &records;
+        let rows_ref = &rows;
+        let report = run_bulk(rows.len(), &rows, &options.bulk, |i| async move {
+            match &rows_ref[i] {
+                Ok(body) => records.create(body).await.map(Some),
+                Err(message) => Err(CopepodError::Query(message.clone())),
+            }
+        })
+        .await?;
+
+        if let Some(path) = &options.reject_file {
+            write_rejects(path, &report, &rows)?;
+        }
+        Ok(report)
+    }
+}
+
+/// A parsed input row, or why it could not be parsed.
+type Row = std::result::Result<Map<String, Value>, String>;
+
+fn read_ndjson(reader: impl Read) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|e| CopepodError::Io(format!("failed to read input: {e}")))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        rows.push(
+            serde_json::from_str::<Map<String, Value>>(&line)
+                .map_err(|e| format!("invalid JSON record: {e}")),
+        );
+    }
+    Ok(rows)
+}
+
+fn read_csv(reader: impl Read, fields: &[CollectionField]) -> Result<Vec<Row>> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let header = reader
+        .headers()
+        .map_err(|e| CopepodError::Io(format!("failed to read CSV header: {e}")))?
+        .clone();
+    let field_types: Vec<Option<&FieldType>> = header
+        .iter()
+        .map(|column| {
+            fields
+                .iter()
+                .find(|f| f.name == column)
+                .map(|f| &f.field_type)
+        })
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) if e.is_io_error() => {
+                return Err(CopepodError::Io(format!("failed to read input: {e}")))
+            }
+            Err(e) => {
+                rows.push(Err(format!("invalid CSV row: {e}")));
+                continue;
+            }
+        };
+        let body = header
+            .iter()
+            .zip(&field_types)
+            .zip(record.iter())
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|((column, field_type), cell)| (column.to_string(), parse_cell(cell, *field_type)))
+            .collect();
+        rows.push(Ok(body));
+    }
+    Ok(rows)
+}
+
+/// Convert a CSV cell to the JSON value for a field of `field_type`. Cells
+/// that don't parse as the expected type are kept as strings, so validation
+/// reports them.
+fn parse_cell(cell: &str, field_type: Option<&FieldType>) -> Value {
+    let parsed = match field_type {
+        Some(FieldType::Number) => cell.parse::<serde_json::Number>().ok().map(Value::Number),
+        Some(FieldType::Bool) => match cell.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Some(FieldType::Json) => serde_json::from_str(cell).ok(),
+        Some(FieldType::Select | FieldType::Relation | FieldType::File)
+            if cell.starts_with('[') =>
+        {
+            serde_json::from_str(cell).ok()
+        }
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(cell.to_string()))
+}
+
+fn csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn map_columns(
+    body: Map<String, Value>,
+    columns: &BTreeMap<String, Option<String>>,
+) -> Map<String, Value> {
+    body.into_iter()
+        .filter_map(|(key, value)| match columns.get(&key) {
+            Some(Some(to)) => Some((to.clone(), value)),
+            Some(None) => None,
+            None => Some((key, value)),
+        })
+        .filter(|(key, _)| key != "created" && key != "updated")
+        .collect()
+}
+
+fn write_rejects(path: &PathBuf, report: &BulkReport, rows: &[Row]) -> Result<()> {
+    let mut out = Vec::new();
+    for failure in &report.failed {
+        let data = match &rows[failure.index] {
+            Ok(body) => Value::Object(body.clone()),
+            Err(_) => Value::Null,
+        };
+        let line = json!({
+            "row": failure.index,
+            "error": failure.error.to_string(),
+            "data": data,
+        });
+        serde_json::to_writer(&mut out, &line)?;
+        out.push(b'\n');
+    }
+    std::fs::write(path, out).map_err(|e| {
+        CopepodError::Io(format!(
+            "failed to write reject file {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+fn write_error(e: std::io::Error) -> CopepodError {
+    CopepodError::Io(format!("failed to write export: {e}"))
+}
+
+fn csv_write_error(e: csv::Error) -> CopepodError {
+    CopepodError::Io(format!("failed to write export: {e}"))
+}
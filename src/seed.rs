@@ -0,0 +1,389 @@
+//! Fixture and seed data loading.
+//!
+//! Fixtures are records keyed by a symbolic name within their collection.
+//! String values of the form `@collection.key` refer to another fixture and
+//! are replaced by its record ID when seeding; write `@@` for a literal
+//! leading `@`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::client::CopepodClient;
+use crate::error::{CopepodError, Result};
+
+/// Length of the IDs derived for fixtures without an explicit `id`.
+const DERIVED_ID_LEN: usize = 15;
+const ID_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// A set of fixture records, grouped by collection and keyed by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fixtures {
+    collections: BTreeMap<String, BTreeMap<String, Map<String, Value>>>,
+}
+
+/// A reference to a fixture record, as `(collection, key)`.
+type FixtureRef = (String, String);
+
+impl Fixtures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load fixtures from a YAML or JSON file, or from every
+    /// `.yaml`/`.yml`/`.json` file in a directory.
+    ///
+    /// Each file holds the records of the collection named by its file stem,
+    /// as a mapping from fixture key to record body.
+    pub fn load(path: &Path) -> Result<Self> {
+        let files = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| CopepodError::Io(format!("failed to read fixture dir: {e}")))?
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some("yaml" | "yml" | "json")
+                    )
+                    .then_some(path)
+                })
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        let mut fixtures = Self::new();
+        for file in files {
+            fixtures.load_file(&file)?;
+        }
+        Ok(fixtures)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        let invalid = |message: String| {
+            CopepodError::Fixture(format!(
+                "invalid fixture file {}: {message}",
+                path.display()
+            ))
+        };
+        let collection = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| invalid("file name is not valid UTF-8".into()))?
+            .to_string();
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            CopepodError::Io(format!(
+                "failed to read fixture file {}: {e}",
+                path.display()
+            ))
+        })?;
+        let records: Map<String, Value> =
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                serde_json::from_str(&data).map_err(|e| invalid(e.to_string()))?
+            } else {
+                serde_norway::from_str(&data).map_err(|e| invalid(e.to_string()))?
+            };
+        for (key, record) in records {
+            match record {
+                Value::Object(record) => self.insert(&collection, key, record),
+                _ => return Err(invalid(format!("record `{key}` is not a mapping"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a fixture record, replacing any with the same collection and key.
+    pub fn insert(
+        &mut self,
+        collection: impl Into<String>,
+        key: impl Into<String>,
+        record: Map<String, Value>,
+    ) {
+        self.collections
+            .entry(collection.into())
+            .or_default()
+            .insert(key.into(), record);
+    }
+
+    /// Return the number of fixture records.
+    pub fn len(&self) -> usize {
+        self.collections.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the record ID a fixture is seeded with: its `id` field if set,
+    /// otherwise an ID derived from `collection` and `key`, so re-seeding
+    /// finds the same record.
+    pub fn record_id(&self, collection: &str, key: &str) -> Option<String> {
+        let record = self.collections.get(collection)?.get(key)?;
+        Some(match record.get("id").and_then(Value::as_str) {
+            Some(id) => id.to_string(),
+            None => derive_id(collection, key),
+        })
+    }
+
+    /// Return every fixture in dependency order: each record comes after the
+    /// records it references.
+    fn ordered(&self) -> Result<Vec<FixtureRef>> {
+        let mut deps: BTreeMap<FixtureRef, BTreeSet<FixtureRef>> = BTreeMap::new();
+        for (collection, records) in &self.collections {
+            for (key, record) in records {
+                let node = (collection.clone(), key.clone());
+                let mut refs = BTreeSet::new();
+                for value in record.values() {
+                    collect_refs(value, &mut refs);
+                }
+                for target in &refs {
+                    if self.record_id(&target.0, &target.1).is_none() {
+                        return Err(CopepodError::Fixture(format!(
+                            "{collection}.{key} references unknown fixture @{}.{}",
+                            target.0, target.1
+                        )));
+                    }
+                }
+                refs.remove(&node);
+                deps.insert(node, refs);
+            }
+        }
+
+        let mut order = Vec::with_capacity(deps.len());
+        let mut done = BTreeSet::new();
+        while !deps.is_empty() {
+            let ready: Vec<FixtureRef> = deps
+                .iter()
+                .filter(|(_, refs)| refs.is_subset(&done))
+                .map(|(node, _)| node.clone())
+                .collect();
+            if ready.is_empty() {
+                let cycle: Vec<String> = deps.keys().map(|(c, k)| format!("{c}.{k}")).collect();
+                return Err(CopepodError::Fixture(format!(
+                    "circular references between fixtures: {}",
+                    cycle.join(", ")
+                )));
+            }
+            for node in ready {
+                deps.remove(&node);
+                done.insert(node.clone());
+                order.push(node);
+            }
+        }
+        Ok(order)
+    }
+
+    /// Return the body of a fixture with references replaced by record IDs.
+    fn resolve(&self, collection: &str, key: &str) -> Map<String, Value> {
+        let mut record = self.collections[collection][key].clone();
+        for value in record.values_mut() {
+            self.resolve_value(value);
+        }
+        record.remove("id");
+        record
+    }
+
+    fn resolve_value(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => {
+                if let Some(literal) = s.strip_prefix("@@") {
+                    *s = format!("@{literal}");
+                } else if let Some((collection, key)) = parse_ref(s) {
+                    if let Some(id) = self.record_id(collection, key) {
+                        *s = id;
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|v| self.resolve_value(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.resolve_value(v)),
+            _ => {}
+        }
+    }
+}
+
+/// Outcome of seeding fixtures.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeedReport {
+    /// Record ID of each fixture, keyed by `collection.key`.
+    pub ids: BTreeMap<String, String>,
+    pub created: usize,
+    pub updated: usize,
+}
+
+impl CopepodClient {
+    /// Create or update every fixture record in dependency order.
+    ///
+    /// Records are written with the IDs from [`Fixtures::record_id`], so
+    /// seeding again updates the same records instead of duplicating them.
+    /// Fails if the server creates a record under a different ID, since
+    /// references to it would then be dangling.
+    pub async fn seed_fixtures(
+        &self,
+        org_id: &str,
+        app_id: &str,
+        fixtures: &Fixtures,
+    ) -> Result<SeedReport> {
+        let mut report = SeedReport::default();
+        for (collection, key) in fixtures.ordered()? {
+            let id = fixtures
+                .record_id(&collection, &key)
+                .expect("ordered fixtures exist");
+            let mut body = fixtures.resolve(&collection, &key);
+            let existing = self.records(org_id, app_id, &collection).get_one(&id).await;
+            match existing {
+                Ok(_) => {
+                    self.update_record(org_id, app_id, &collection, &id, &body)
+                        .await?;
+                    report.updated += 1;
+                }
+                Err(CopepodError::Api { status: 404, .. }) => {
+                    body.insert("id".into(), Value::String(id.clone()));
+                    let created = self
+                        .create_record(org_id, app_id, &collection, &body)
+                        .await?;
+                    // Later fixtures reference this record by the requested ID.
+                    let created_id = created.get("id").and_then(Value::as_str);
+                    if created_id != Some(id.as_str()) {
+                        return Err(CopepodError::Fixture(format!(
+                            "fixture `{collection}.{key}` was created with ID {} instead of `{id}`",
+                            created_id.map_or("<none>".into(), |i| format!("`{i}`"))
+                        )));
+                    }
+                    report.created += 1;
+                }
+                Err(e) => return Err(e),
+            }
+            report.ids.insert(format!("{collection}.{key}"), id);
+        }
+        Ok(report)
+    }
+
+    /// Delete every fixture record, in reverse dependency order. Records
+    /// that no longer exist are skipped. Returns the number deleted.
+    pub async fn teardown_fixtures(
+        &self,
+        org_id: &str,
+        app_id: &str,
+        fixtures: &Fixtures,
+    ) -> Result<usize> {
+        let mut deleted = 0;
+        for (collection, key) in fixtures.ordered()?.into_iter().rev() {
+            let id = fixtures
+                .record_id(&collection, &key)
+                .expect("ordered fixtures exist");
+            match self.delete_record(org_id, app_id, &collection, &id).await {
+                Ok(()) => deleted += 1,
+                Err(CopepodError::Api { status: 404, .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Parse `@collection.key` into its parts.
+fn parse_ref(s: &str) -> Option<(&str, &str)> {
+    let (collection, key) = s.strip_prefix('@')?.split_once('.')?;
+    (!s.starts_with("@@") && !collection.is_empty() && !key.is_empty()).then_some((collection, key))
+}
+
+fn collect_refs(value: &Value, refs: &mut BTreeSet<FixtureRef>) {
+    match value {
+        Value::String(s) => {
+            if let Some((collection, key)) = parse_ref(s) {
+                refs.insert((collection.to_string(), key.to_string()));
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+        Value::Object(map) => map.values().for_each(|v| collect_refs(v, refs)),
+        _ => {}
+    }
+}
+
+fn derive_id(collection: &str, key: &str) -> String {
+    let digest = Sha256::digest(format!("{collection}.{key}"));
+    digest
+        .iter()
+        .take(DERIVED_ID_LEN)
+        .map(|b| ID_ALPHABET[*b as usize % ID_ALPHABET.len()] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixtures(value: Value) -> Fixtures {
+        let mut fixtures = Fixtures::new();
+        for (collection, records) in value.as_object().unwrap() {
+            for (key, record) in records.as_object().unwrap() {
+                fixtures.insert(collection, key, record.as_object().unwrap().clone());
+            }
+        }
+        fixtures
+    }
+
+    #[test]
+    fn orders_by_references_and_resolves_ids() {
+        let fixtures = fixtures(json!({
+            "posts": {
+                "hello": { "title": "Hello", "author": "@users.alice", "tags": ["@tags.rust"] }
+            },
+            "tags": { "rust": { "id": "tag_rust", "name": "rust", "note": "@@literal" } },
+            "users": { "alice": { "name": "Alice" } }
+        }));
+
+        let order = fixtures.ordered().unwrap();
+        let position = |c: &str| order.iter().position(|(col, _)| col == c).unwrap();
+        assert!(position("users") < position("posts"));
+        assert!(position("tags") < position("posts"));
+
+        let alice = fixtures.record_id("users", "alice").unwrap();
+        assert_eq!(alice.len(), DERIVED_ID_LEN);
+        assert_eq!(Some(alice.clone()), fixtures.record_id("users", "alice"));
+
+        let post = fixtures.resolve("posts", "hello");
+        assert_eq!(post["author"], json!(alice));
+        assert_eq!(post["tags"], json!(["tag_rust"]));
+        assert_eq!(fixtures.resolve("tags", "rust")["note"], "@literal");
+        assert!(!fixtures.resolve("tags", "rust").contains_key("id"));
+    }
+
+    #[test]
+    fn rejects_unknown_and_circular_references() {
+        let unknown = fixtures(json!({ "posts": { "a": { "author": "@users.bob" } } }));
+        assert!(matches!(unknown.ordered(), Err(CopepodError::Fixture(_))));
+
+        let circular = fixtures(json!({
+            "users": {
+                "a": { "friend": "@users.b" },
+                "b": { "friend": "@users.a" },
+                "c": { "friend": "@users.c" }
+            }
+        }));
+        let err = circular.ordered().unwrap_err().to_string();
+        assert!(err.contains("users.a, users.b"), "{err}");
+        assert!(!err.contains("users.c"), "{err}");
+    }
+
+    #[test]
+    fn names_the_file_in_parse_errors() {
+        let dir = std::env::temp_dir().join(format!("copepod-bad-fixtures-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, data) in [("posts.json", "{ \"hello\": "), ("users.yaml", "alice: [")] {
+            let file = dir.join(name);
+            std::fs::write(&file, data).unwrap();
+            let err = Fixtures::load(&file).unwrap_err();
+            assert!(
+                matches!(&err, CopepodError::Fixture(msg) if msg.contains(&file.display().to_string())),
+                "{err}"
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
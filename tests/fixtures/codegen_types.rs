@@ -0,0 +1,70 @@
+// @generated by copepod-sdk codegen. Do not edit by hand.
+
+/// A record in the `categories` collection.
+#[derive(Debug, Clone, PartialEq, ::copepod_sdk::schema::__private::serde::Serialize, ::copepod_sdk::schema::__private::serde::Deserialize)]
+#[serde(crate = "::copepod_sdk::schema::__private::serde")]
+pub struct Category {
+    pub name: String,
+}
+
+impl ::copepod_sdk::schema::CopepodRecord for Category {
+    const COLLECTION: &'static str = "categories";
+
+    fn fields() -> ::std::vec::Vec<::copepod_sdk::models::CollectionField> {
+        ::copepod_sdk::schema::__private::fields_from_json(r#"[{"name":"name","type":"text","required":true,"options":null}]"#)
+    }
+}
+
+/// A record in the `blog_posts` collection.
+#[derive(Debug, Clone, PartialEq, ::copepod_sdk::schema::__private::serde::Serialize, ::copepod_sdk::schema::__private::serde::Deserialize)]
+#[serde(crate = "::copepod_sdk::schema::__private::serde")]
+pub struct BlogPost {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(rename = "viewCount", default, skip_serializing_if = "Option::is_none")]
+    pub view_count: Option<f64>,
+    pub published: bool,
+    #[serde(rename = "publishedAt", default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<::copepod_sdk::schema::__private::chrono::DateTime<::copepod_sdk::schema::__private::chrono::Utc>>,
+    pub category: ::copepod_sdk::schema::RelationId<Category>,
+    #[serde(default)]
+    pub related: Vec<::copepod_sdk::schema::RelationId<BlogPost>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<::copepod_sdk::schema::FileName>,
+    #[serde(default)]
+    pub images: Vec<::copepod_sdk::schema::FileName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<::copepod_sdk::schema::__private::serde_json::Value>,
+}
+
+impl ::copepod_sdk::schema::CopepodRecord for BlogPost {
+    const COLLECTION: &'static str = "blog_posts";
+
+    fn fields() -> ::std::vec::Vec<::copepod_sdk::models::CollectionField> {
+        ::copepod_sdk::schema::__private::fields_from_json(r#"[{"name":"id","type":"text","required":false,"options":null},{"name":"title","type":"text","required":true,"options":null},{"name":"type","type":"select","required":false,"options":{"values":["a","b"]}},{"name":"tags","type":"select","required":false,"options":{"max_select":2,"values":["x","y"]}},{"name":"viewCount","type":"number","required":false,"options":null},{"name":"published","type":"bool","required":true,"options":null},{"name":"publishedAt","type":"date","required":false,"options":null},{"name":"category","type":"relation","required":true,"options":{"collection":"categories","max_select":1}},{"name":"related","type":"relation","required":false,"options":{"collection":"blog_posts"}},{"name":"cover","type":"file","required":false,"options":{"max_files":1}},{"name":"images","type":"file","required":false,"options":null},{"name":"meta","type":"json","required":false,"options":null}]"#)
+    }
+}
+
+/// Typed accessors for the generated collections of an app.
+pub struct Collections<'a> {
+    app: ::copepod_sdk::ScopedAppClient<'a>,
+}
+
+impl<'a> Collections<'a> {
+    pub fn new(app: ::copepod_sdk::ScopedAppClient<'a>) -> Self {
+        Self { app }
+    }
+
+    /// Records in the `categories` collection.
+    pub fn categories(&self) -> ::copepod_sdk::TypedCollection<'a, Category> {
+        self.app.typed::<Category>()
+    }
+
+    /// Records in the `blog_posts` collection.
+    pub fn blog_posts(&self) -> ::copepod_sdk::TypedCollection<'a, BlogPost> {
+        self.app.typed::<BlogPost>()
+    }
+}
@@ -1,6 +1,7 @@
 use copepod_sdk::{CopepodClient, CopepodError};
+use futures_util::TryStreamExt;
 use serde_json::json;
-use wiremock::matchers::{body_json, header, method, path};
+use wiremock::matchers::{body_json, header, method, path, query_param, query_param_is_missing};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 // -- Client builder tests --
@@ -428,6 +429,401 @@ async fn test_list_records_with_query() {
     assert_eq!(result.items.len(), 2);
 }
 
+#[tokio::test]
+async fn test_record_query_streams_all_pages() {
+    let server = MockServer::start().await;
+
+    for (page, items) in [
+        ("1", json!([{ "id": "r1" }, { "id": "r2" }])),
+        ("2", json!([{ "id": "r3" }])),
+    ] {
+        Mock::given(method("GET"))
+            .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+            .and(query_param("filter", "published = true"))
+            .and(query_param("page", page))
+            .and(query_param("per_page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "page": page.parse::<u32>().unwrap(),
+                "per_page": 2,
+                "total_items": 3,
+                "total_pages": 2,
+                "items": items
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let records = client
+        .records("o1", "a1", "posts")
+        .filter("published = true")
+        .per_page(2)
+        .all()
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = records.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["r1", "r2", "r3"]);
+}
+
+/// Mount two pages of `items` (two then one) at `endpoint`, each only
+/// answering requests that carry the page's `page`/`per_page` and `query`.
+async fn mount_two_pages(
+    server: &MockServer,
+    endpoint: &str,
+    query: &[(&str, &str)],
+    items: [serde_json::Value; 3],
+) {
+    let [first, second, third] = items;
+    for (page, items) in [("1", json!([first, second])), ("2", json!([third]))] {
+        let mut mock = Mock::given(method("GET"))
+            .and(path(endpoint))
+            .and(query_param("page", page))
+            .and(query_param("per_page", "2"));
+        for (key, value) in query {
+            mock = mock.and(query_param(*key, *value));
+        }
+        mock.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": page.parse::<u32>().unwrap(),
+            "per_page": 2,
+            "total_items": 3,
+            "total_pages": 2,
+            "items": items
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+    }
+}
+
+fn paginating_client(server: &MockServer) -> CopepodClient {
+    CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_paginate_jobs_walks_every_page() {
+    let server = MockServer::start().await;
+    let job = |id: &str| {
+        json!({
+            "id": id, "app_id": "a1", "job_type": "email", "status": "done",
+            "attempts": 1, "max_attempts": 3, "created": "2024-01-01T00:00:00Z"
+        })
+    };
+    mount_two_pages(
+        &server,
+        "/api/platform/apps/a1/jobs",
+        &[],
+        [job("j1"), job("j2"), job("j3")],
+    )
+    .await;
+
+    let client = paginating_client(&server);
+    let jobs = client.paginate_jobs("a1").per_page(2).all().await.unwrap();
+    let ids: Vec<_> = jobs.iter().map(|j| j.id.as_str()).collect();
+    assert_eq!(ids, ["j1", "j2", "j3"]);
+}
+
+#[tokio::test]
+async fn test_paginate_app_users_walks_every_page() {
+    let server = MockServer::start().await;
+    let user = |id: &str| {
+        json!({
+            "id": id, "email": format!("{id}@example.com"),
+            "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z"
+        })
+    };
+    mount_two_pages(
+        &server,
+        "/api/platform/orgs/o1/apps/a1/users",
+        &[],
+        [user("u1"), user("u2"), user("u3")],
+    )
+    .await;
+
+    let client = paginating_client(&server);
+    let users = client
+        .paginate_app_users("o1", "a1")
+        .per_page(2)
+        .all()
+        .await
+        .unwrap();
+    let ids: Vec<_> = users.iter().map(|u| u.id.as_str()).collect();
+    assert_eq!(ids, ["u1", "u2", "u3"]);
+}
+
+#[tokio::test]
+async fn test_paginate_entitlements_walks_every_page() {
+    let server = MockServer::start().await;
+    let entitlement = |id: &str| {
+        json!({
+            "id": id, "org_id": "o1", "feature_key": "seats", "limit_value": "10",
+            "source": "plan", "created": "2024-01-01T00:00:00Z"
+        })
+    };
+    mount_two_pages(
+        &server,
+        "/api/platform/orgs/o1/entitlements",
+        &[],
+        [entitlement("e1"), entitlement("e2"), entitlement("e3")],
+    )
+    .await;
+
+    let client = paginating_client(&server);
+    let entitlements = client
+        .paginate_entitlements("o1")
+        .per_page(2)
+        .all()
+        .await
+        .unwrap();
+    let ids: Vec<_> = entitlements.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["e1", "e2", "e3"]);
+}
+
+#[tokio::test]
+async fn test_paginate_logs_walks_every_page() {
+    let server = MockServer::start().await;
+    let log = |id: &str| json!({ "id": id, "action": "login", "created": "2024-01-01T00:00:00Z" });
+    mount_two_pages(
+        &server,
+        "/api/platform/logs",
+        &[],
+        [log("l1"), log("l2"), log("l3")],
+    )
+    .await;
+
+    let client = paginating_client(&server);
+    let logs = client.paginate_logs().per_page(2).all().await.unwrap();
+    let ids: Vec<_> = logs.iter().map(|l| l.id.as_str()).collect();
+    assert_eq!(ids, ["l1", "l2", "l3"]);
+}
+
+#[tokio::test]
+async fn test_paginate_app_tickets_keeps_filters_on_every_page() {
+    let server = MockServer::start().await;
+    let ticket = |id: &str| json!({ "id": id, "subject": "Bug report", "status": "open" });
+    mount_two_pages(
+        &server,
+        "/api/platform/orgs/o1/apps/a1/tickets",
+        &[("status", "open"), ("search", "crash")],
+        [ticket("t1"), ticket("t2"), ticket("t3")],
+    )
+    .await;
+
+    let client = paginating_client(&server);
+    let query = copepod_sdk::models::TicketListQuery {
+        status: Some("open".into()),
+        search: Some("crash".into()),
+        // The paginator chooses the page and page size itself.
+        page: Some(5),
+        per_page: Some(50),
+        ..Default::default()
+    };
+    let tickets = client
+        .paginate_app_tickets_filtered("o1", "a1", &query)
+        .per_page(2)
+        .all()
+        .await
+        .unwrap();
+    let ids: Vec<_> = tickets.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["t1", "t2", "t3"]);
+}
+
+#[tokio::test]
+async fn test_record_query_iterates_by_cursor() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param("filter", "status = 'open'"))
+        .and(query_param("sort", "created,id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 2,
+            "total_items": 3,
+            "total_pages": 2,
+            "items": [
+                { "id": "r1", "created": "2024-01-01T00:00:00Z" },
+                { "id": "r2", "created": "2024-01-02T00:00:00Z" }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param(
+            "filter",
+            "(status = 'open') && (created > '2024-01-02T00:00:00Z' || \
+             (created = '2024-01-02T00:00:00Z' && id > 'r2'))",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 2,
+            "total_items": 1,
+            "total_pages": 1,
+            "items": [{ "id": "r3", "created": "2024-01-02T00:00:00Z" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let records: Vec<_> = client
+        .records("o1", "a1", "posts")
+        .filter("status = 'open'")
+        .per_page(2)
+        .iter_by_cursor("created")
+        .try_collect()
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = records.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["r1", "r2", "r3"]);
+}
+
+#[tokio::test]
+async fn test_record_query_cursor_walk_survives_capped_page_size() {
+    let server = MockServer::start().await;
+
+    // The server caps pages at two items although five were requested.
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param("sort", "id"))
+        .and(query_param("per_page", "5"))
+        .and(query_param_is_missing("filter"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 2,
+            "total_items": 3,
+            "total_pages": 2,
+            "items": [{ "id": "r1" }, { "id": "r2" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param("filter", "id > 'r2'"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 2,
+            "total_items": 1,
+            "total_pages": 1,
+            "items": [{ "id": "r3" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let records: Vec<_> = client
+        .records("o1", "a1", "posts")
+        .per_page(5)
+        .iter_by_cursor("id")
+        .try_collect()
+        .await
+        .unwrap();
+    let ids: Vec<_> = records.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["r1", "r2", "r3"]);
+
+    let err = client
+        .records("o1", "a1", "posts")
+        .iter_by_cursor("created || true")
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CopepodError::Query(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn test_record_query_count_and_aggregates() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/tickets";
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("fields", "id"))
+        .and(query_param("per_page", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 1,
+            "total_items": 42,
+            "total_pages": 42,
+            "items": [{ "id": "t1" }]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("fields", "hours"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 100,
+            "total_items": 3,
+            "total_pages": 1,
+            "items": [{ "hours": 1.5 }, { "hours": 4 }, { "hours": null }]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("fields", "status"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 100,
+            "total_items": 3,
+            "total_pages": 1,
+            "items": [{ "status": "open" }, { "status": "closed" }, { "status": "open" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let query = || client.records("o1", "a1", "tickets");
+
+    assert_eq!(query().filter("status = 'open'").count().await.unwrap(), 42);
+    assert!(query().exists().await.unwrap());
+    assert_eq!(query().sum("hours").await.unwrap(), 5.5);
+    assert_eq!(query().max::<f64>("hours").await.unwrap(), Some(4.0));
+    assert_eq!(query().min::<f64>("hours").await.unwrap(), Some(1.5));
+
+    let groups = query().group_by("status").await.unwrap();
+    assert_eq!(groups["open"], 2);
+    assert_eq!(groups["closed"], 1);
+}
+
 #[tokio::test]
 async fn test_list_launchpads() {
     let server = MockServer::start().await;
@@ -1,6 +1,24 @@
-use copepod_sdk::{AppLoginResult, CopepodClient};
+use std::path::Path;
+use std::time::Duration;
+
+use copepod_sdk::api::deployment_logs::LogFollowOptions;
+use copepod_sdk::live::LiveDiff;
+use copepod_sdk::realtime::{
+    ConnectionState, HubOptions, RealtimeEvent, RealtimeTransport, ReconnectOptions,
+    SubscriptionEvent, SubscriptionTargets,
+};
+use copepod_sdk::replica::{ConflictPolicy, MemoryStore, PushReport};
+use copepod_sdk::schema::codegen::{generate_module, load_snapshot};
+use copepod_sdk::schema::{CollectionChange, CopepodRecord, RelationId};
+use copepod_sdk::seed::Fixtures;
+use copepod_sdk::{
+    AppLoginResult, BulkOptions, CacheOptions, CopepodClient, CopepodError, DataFormat,
+    ImportOptions, Record, RecordAction, Relation, UpsertAction,
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use wiremock::matchers::{body_json, method, path};
+use wiremock::matchers::{body_json, header, method, path, query_param, query_param_is_missing};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
@@ -85,3 +103,2570 @@ async fn scoped_auth_client_reuses_bound_app_context() {
         AppLoginResult::MfaRequired(_) => panic!("expected successful auth response"),
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Note {
+    title: String,
+    #[serde(default)]
+    pinned: bool,
+}
+
+#[tokio::test]
+async fn typed_collection_deserializes_records_with_system_fields() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes"))
+        .and(query_param("sort", "-created"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 30,
+            "total_items": 1,
+            "total_pages": 1,
+            "items": [{
+                "id": "rec_1",
+                "created": "2024-01-01T00:00:00Z",
+                "updated": "2024-01-02T00:00:00Z",
+                "title": "Typed note",
+                "pinned": true
+            }]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes"))
+        .and(body_json(json!({ "title": "New note", "pinned": false })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "rec_2",
+            "created": "2024-01-03T00:00:00Z",
+            "updated": "2024-01-03T00:00:00Z",
+            "title": "New note"
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let notes = client.app("o1", "a1").collection::<Note>("notes");
+
+    let page = notes.query().sort("-created").list().await.unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, "rec_1");
+    assert_eq!(page.items[0].title, "Typed note");
+    assert!(page.items[0].pinned);
+    assert!(page.items[0].updated > page.items[0].created);
+
+    let created = notes
+        .create(&Note {
+            title: "New note".into(),
+            pinned: false,
+        })
+        .await
+        .unwrap();
+    assert_eq!(created.id, "rec_2");
+    assert_eq!(created.data.title, "New note");
+    assert!(!created.pinned);
+}
+
+#[tokio::test]
+async fn create_many_reports_failures_and_retries_rate_limits() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes"))
+        .and(body_json(json!({ "title": "a" })))
+        .respond_with(ResponseTemplate::new(429))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    for title in ["a", "b"] {
+        Mock::given(method("POST"))
+            .and(path("/api/platform/orgs/o1/apps/a1/records/notes"))
+            .and(body_json(json!({ "title": title })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "id": title, "title": title })),
+            )
+            .mount(&server)
+            .await;
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes"))
+        .and(body_json(json!({ "title": "" })))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "code": "validation_failed",
+            "message": "title is required"
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let notes = client.app("o1", "a1").records("notes");
+    let items = [
+        json!({ "title": "a" }),
+        json!({ "title": "" }),
+        json!({ "title": "b" }),
+    ];
+    let options = BulkOptions::new()
+        .concurrency(2)
+        .retry_backoff(Duration::from_millis(1));
+
+    let report = notes.create_many(&items, &options).await.unwrap();
+    assert!(!report.is_success());
+    let ok: Vec<_> = report.succeeded.iter().map(|s| s.index).collect();
+    assert_eq!(ok, [0, 2]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].index, 1);
+    assert!(matches!(
+        report.failed[0].error,
+        CopepodError::Api { status: 400, .. }
+    ));
+
+    // Resuming skips everything that already succeeded.
+    let resumed = notes
+        .create_many(&items, &options.resume_from(report.checkpoint))
+        .await
+        .unwrap();
+    assert_eq!(resumed.skipped, 2);
+    assert_eq!(resumed.failed.len(), 1);
+}
+
+#[tokio::test]
+async fn bulk_checkpoint_file_is_fingerprinted_and_removed_after_success() {
+    let server = MockServer::start().await;
+    let notes_path = "/api/platform/orgs/o1/apps/a1/records/notes";
+
+    Mock::given(method("POST"))
+        .and(path(notes_path))
+        .and(body_json(json!({ "title": "a" })))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(notes_path))
+        .and(body_json(json!({ "title": "" })))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(notes_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "n" })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let notes = client.app("o1", "a1").records("notes");
+    let checkpoint =
+        std::env::temp_dir().join(format!("copepod-checkpoint-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&checkpoint);
+    // A long backoff: the 429 must be retried after its `Retry-After: 0`.
+    let options = BulkOptions::new()
+        .retry_backoff(Duration::from_secs(60))
+        .checkpoint_file(&checkpoint);
+
+    let items = [json!({ "title": "a" }), json!({ "title": "" })];
+    let report = tokio::time::timeout(Duration::from_secs(5), notes.create_many(&items, &options))
+        .await
+        .expect("Retry-After was not honoured")
+        .unwrap();
+    assert_eq!(report.succeeded.len(), 1);
+    assert!(checkpoint.exists());
+
+    // The leftover checkpoint belongs to other input.
+    let other = [json!({ "title": "c" })];
+    let err = notes.create_many(&other, &options).await.unwrap_err();
+    assert!(matches!(err, CopepodError::Query(_)), "{err}");
+
+    // A run without failures removes it, so the next run starts over.
+    let fixed = [json!({ "title": "a" }), json!({ "title": "b" })];
+    std::fs::remove_file(&checkpoint).unwrap();
+    let report = notes.create_many(&fixed, &options).await.unwrap();
+    assert!(report.is_success());
+    assert!(!checkpoint.exists());
+    let again = notes.create_many(&fixed, &options).await.unwrap();
+    assert_eq!((again.skipped, again.succeeded.len()), (0, 2));
+}
+
+#[tokio::test]
+async fn bulk_retries_wait_as_long_as_retry_after_asks() {
+    let server = MockServer::start().await;
+    let notes_path = "/api/platform/orgs/o1/apps/a1/records/notes";
+
+    Mock::given(method("GET"))
+        .and(path(format!("{notes_path}/n1")))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "7"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(notes_path))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(notes_path))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "n2" })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let notes = client.app("o1", "a1").records("notes");
+
+    // Outside bulk operations a rate limited request is a plain API error.
+    assert!(matches!(
+        notes.query().get_one("n1").await.unwrap_err(),
+        CopepodError::Api { status: 429, .. }
+    ));
+
+    // The server's `Retry-After: 0` replaces the hour-long backoff.
+    let options = BulkOptions::new().retry_backoff(Duration::from_secs(3600));
+    let report = tokio::time::timeout(
+        Duration::from_secs(5),
+        notes.create_many(&[json!({ "title": "a" })], &options),
+    )
+    .await
+    .expect("retry waited for the backoff instead of Retry-After")
+    .unwrap();
+    assert!(report.is_success());
+}
+
+#[tokio::test]
+async fn upsert_by_rejects_field_names_that_are_not_identifiers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let contacts = client.app("o1", "a1").records("contacts");
+
+    for field in ["x = 1 || id", "", "1st", "name'"] {
+        let err = contacts
+            .upsert_by(field, "ext-1", &json!({ "name": "Ada" }))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CopepodError::Query(_)), "{field}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn upsert_by_retries_as_update_after_unique_conflict() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/contacts";
+    let empty_page = json!({
+        "page": 1, "per_page": 1, "total_items": 0, "total_pages": 0, "items": []
+    });
+
+    // The first lookup misses; a concurrent writer creates the record before us.
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("filter", "external_id = 'ext-1'"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(empty_page))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("filter", "external_id = 'ext-1'"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 1, "total_items": 1, "total_pages": 1,
+            "items": [{ "id": "rec_9", "external_id": "ext-1", "name": "Old" }]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(records))
+        .and(body_json(json!({ "name": "New", "external_id": "ext-1" })))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "code": "unique_violation",
+            "message": "external_id must be unique"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/rec_9")))
+        .and(body_json(json!({ "name": "New" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "rec_9", "external_id": "ext-1", "name": "New"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let result = client
+        .app("o1", "a1")
+        .records("contacts")
+        .upsert_by("external_id", "ext-1", &json!({ "name": "New" }))
+        .await
+        .unwrap();
+
+    assert_eq!(result.action, UpsertAction::Updated);
+    assert_eq!(result.record["name"], "New");
+}
+
+mod generated {
+    include!("fixtures/codegen_types.rs");
+}
+
+#[test]
+fn generated_types_fixture_matches_codegen_output() {
+    let schemas = load_snapshot(Path::new("tests/fixtures/codegen_schema.json")).unwrap();
+    assert_eq!(
+        generate_module(&schemas),
+        include_str!("fixtures/codegen_types.rs"),
+        "regenerate tests/fixtures/codegen_types.rs with copepod-codegen --from-snapshot"
+    );
+}
+
+#[tokio::test]
+async fn generated_types_read_and_write_records() {
+    let server = MockServer::start().await;
+    let post = json!({
+        "id": "p1",
+        "created": "2024-01-01T00:00:00Z",
+        "updated": "2024-01-01T00:00:00Z",
+        "title": "Hello",
+        "type": "a",
+        "tags": ["x"],
+        "viewCount": 3,
+        "published": true,
+        "publishedAt": "2024-01-02T00:00:00Z",
+        "category": "c1",
+        "related": ["p0"],
+        "cover": "cover.png",
+        "images": [],
+        "meta": { "draft": false }
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/blog_posts/p1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&post))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let collections = generated::Collections::new(client.app("o1", "a1"));
+
+    let record = collections.blog_posts().get("p1").await.unwrap();
+    assert_eq!(record.data.title, "Hello");
+    assert_eq!(record.data.r#type.as_deref(), Some("a"));
+    assert_eq!(record.data.view_count, Some(3.0));
+    assert_eq!(record.data.category.id(), "c1");
+    assert_eq!(record.data.related.len(), 1);
+    assert_eq!(
+        <generated::BlogPost as CopepodRecord>::COLLECTION,
+        "blog_posts"
+    );
+    assert_eq!(<generated::Category as CopepodRecord>::fields().len(), 1);
+    assert_eq!(collections.categories().collection(), "categories");
+
+    let body = serde_json::to_value(&record.data).unwrap();
+    let mut expected = post.clone();
+    for system in ["id", "created", "updated"] {
+        expected.as_object_mut().unwrap().remove(system);
+    }
+    expected["viewCount"] = json!(3.0);
+    expected["publishedAt"] = json!("2024-01-02T00:00:00Z");
+    assert_eq!(body, expected);
+}
+
+#[tokio::test]
+async fn collection_schemas_reads_every_page() {
+    let server = MockServer::start().await;
+    for (page, name) in [("1", "posts"), ("2", "comments")] {
+        Mock::given(method("GET"))
+            .and(path("/api/platform/orgs/o1/apps/a1/collections"))
+            .and(query_param("page", page))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "page": page.parse::<u32>().unwrap(),
+                "per_page": 1,
+                "total_items": 2,
+                "total_pages": 2,
+                "items": [{
+                    "id": format!("col_{name}"),
+                    "name": name,
+                    "app_id": "a1",
+                    "created": "2024-01-01T00:00:00Z",
+                    "updated": "2024-01-01T00:00:00Z"
+                }]
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+    }
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let schemas = client.collection_schemas("o1", "a1").await.unwrap();
+    let names: Vec<_> = schemas.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["posts", "comments"]);
+    let code = client.generate_types("o1", "a1").await.unwrap();
+    assert!(code.contains("pub struct Comment {"));
+}
+
+#[tokio::test]
+async fn validated_collection_rejects_invalid_bodies_before_sending() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/collections/tasks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "col_1",
+            "name": "tasks",
+            "app_id": "a1",
+            "fields": [
+                { "name": "title", "type": "text", "required": true },
+                { "name": "priority", "type": "number", "options": { "min": 1, "max": 3 } }
+            ],
+            "created": "2024-01-01T00:00:00Z",
+            "updated": "2024-01-01T00:00:00Z"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/tasks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "rec_1", "title": "Ship", "priority": 2
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let tasks = client
+        .app("o1", "a1")
+        .records("tasks")
+        .validated()
+        .await
+        .unwrap();
+
+    let err = tasks.create(&json!({ "priority": 7 })).await.unwrap_err();
+    match err {
+        CopepodError::Validation(errors) => {
+            let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+            assert_eq!(fields, ["title", "priority"]);
+        }
+        other => panic!("expected validation error, got {other:?}"),
+    }
+
+    let record = tasks
+        .create(&json!({ "title": "Ship", "priority": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(record["id"], "rec_1");
+}
+
+#[tokio::test]
+async fn schema_plan_requires_opt_in_for_destructive_changes() {
+    let server = MockServer::start().await;
+    let collections = "/api/platform/orgs/o1/apps/a1/collections";
+
+    Mock::given(method("GET"))
+        .and(path(collections))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": 1, "total_pages": 1,
+            "items": [{
+                "id": "col_1", "name": "posts", "app_id": "a1",
+                "fields": [
+                    { "name": "title", "type": "text", "required": true },
+                    { "name": "legacy", "type": "json" }
+                ],
+                "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z"
+            }]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path(format!("{collections}/col_1")))
+        .and(body_json(json!({
+            "name": "posts",
+            "fields": [
+                { "name": "title", "type": "text", "required": true, "options": null },
+                { "name": "slug", "type": "text", "required": false, "unique": true, "options": null }
+            ],
+            "indexes": []
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "col_1", "name": "posts", "app_id": "a1",
+            "created": "2024-01-01T00:00:00Z", "updated": "2024-01-02T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let app = client.app("o1", "a1");
+
+    let desired = serde_json::from_value::<Vec<copepod_sdk::CollectionSchema>>(json!([{
+        "name": "posts",
+        "fields": [
+            { "name": "title", "type": "text", "required": true },
+            { "name": "slug", "type": "text", "unique": true }
+        ]
+    }]))
+    .unwrap();
+
+    let plan = app.plan_schema(&desired).await.unwrap();
+    assert!(plan.is_destructive());
+
+    let err = app.apply_schema_plan(&plan, false).await.unwrap_err();
+    assert!(matches!(err, CopepodError::Schema(_)));
+
+    let applied = app.apply_schema_plan(&plan, true).await.unwrap();
+    assert_eq!(applied[0].id, "col_1");
+}
+
+#[tokio::test]
+async fn schema_plan_diffs_collections_on_every_page() {
+    let server = MockServer::start().await;
+
+    for (page, id, name) in [("1", "col_1", "posts"), ("2", "col_2", "comments")] {
+        Mock::given(method("GET"))
+            .and(path("/api/platform/orgs/o1/apps/a1/collections"))
+            .and(query_param("page", page))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "page": page.parse::<u32>().unwrap(), "per_page": 1,
+                "total_items": 2, "total_pages": 2,
+                "items": [{
+                    "id": id, "name": name, "app_id": "a1",
+                    "fields": [{ "name": "body", "type": "text", "required": true }],
+                    "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z"
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let desired = serde_json::from_value::<Vec<copepod_sdk::CollectionSchema>>(json!([
+        { "name": "posts", "fields": [{ "name": "body", "type": "text", "required": true }] },
+        {
+            "name": "comments",
+            "fields": [
+                { "name": "body", "type": "text", "required": true },
+                { "name": "score", "type": "number" }
+            ]
+        }
+    ]))
+    .unwrap();
+
+    let plan = client.app("o1", "a1").plan_schema(&desired).await.unwrap();
+    assert!(!plan.is_destructive());
+    match &plan.changes[..] {
+        [CollectionChange::Update { id, diff, .. }] => {
+            assert_eq!(id, "col_2");
+            assert_eq!(diff.added_fields[0].name, "score");
+        }
+        other => panic!("expected one update to comments, got {other:?}"),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    bio: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Post {
+    title: String,
+    author: Relation<Author>,
+    editor: Relation<Author>,
+}
+
+#[tokio::test]
+async fn typed_query_inlines_expanded_relations() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts/rec_1"))
+        .and(query_param("expand", "author,author.profile"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "rec_1",
+            "title": "Hello",
+            "author": "u1",
+            "editor": "u2",
+            "expand": {
+                "author": {
+                    "id": "u1",
+                    "name": "Ada",
+                    "profile": "p1",
+                    "expand": { "profile": { "id": "p1", "bio": "Mathematician" } }
+                }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let post = client
+        .app("o1", "a1")
+        .collection::<Post>("posts")
+        .query()
+        .expand_path("author")
+        .expand_path("author.profile")
+        .inline_expand()
+        .get_one("rec_1")
+        .await
+        .unwrap();
+
+    assert_eq!(post.title, "Hello");
+    let author = post.author.expanded().unwrap();
+    assert_eq!(author.id, "u1");
+    assert_eq!(author.name, "Ada");
+    assert_eq!(post.editor.id(), "u2");
+    assert!(!post.editor.is_expanded());
+
+    let profile: Record<Profile> = post.expand.get("author.profile").unwrap().unwrap();
+    assert_eq!(profile.bio, "Mathematician");
+    assert!(post
+        .expand
+        .get::<Record<Author>>("editor")
+        .unwrap()
+        .is_none());
+    assert_eq!(serde_json::to_value(&post.author).unwrap(), json!("u1"));
+}
+
+#[tokio::test]
+async fn typed_query_keeps_relation_ids_when_expanding() {
+    #[derive(Debug, Deserialize)]
+    struct Comment {
+        body: String,
+        author: RelationId<Author>,
+    }
+
+    let server = MockServer::start().await;
+    let comment = json!({
+        "id": "c1",
+        "body": "Nice",
+        "author": "u1",
+        "expand": { "author": { "id": "u1", "name": "Ada" } }
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/comments"))
+        .and(query_param("expand", "author"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": 1, "total_pages": 1,
+            "items": [comment.clone()]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/comments/c1"))
+        .and(query_param("expand", "author"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(comment))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let comments = client.app("o1", "a1").collection::<Comment>("comments");
+
+    let listed = comments.query().expand("author").list().await.unwrap();
+    let fetched = comments
+        .query()
+        .expand("author")
+        .get_one("c1")
+        .await
+        .unwrap();
+    for comment in [&listed.items[0], &fetched] {
+        assert_eq!(comment.body, "Nice");
+        assert_eq!(comment.author.id(), "u1");
+        let author: Record<Author> = comment.expand.get("author").unwrap().unwrap();
+        assert_eq!(author.name, "Ada");
+    }
+}
+
+#[tokio::test]
+async fn aggregates_ignore_inlined_expansions() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param("expand", "author"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 100, "total_items": 2, "total_pages": 1,
+            "items": [
+                { "author": "u1", "expand": { "author": { "id": "u1", "name": "Ada" } } },
+                { "author": "u1", "expand": { "author": { "id": "u1", "name": "Ada" } } }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+
+    let groups = client
+        .app("o1", "a1")
+        .collection::<Post>("posts")
+        .query()
+        .expand("author")
+        .inline_expand()
+        .group_by("author")
+        .await
+        .unwrap();
+    assert_eq!(groups.into_iter().collect::<Vec<_>>(), [("u1".into(), 2)]);
+}
+
+#[tokio::test]
+async fn cached_collection_serves_hits_and_invalidates_on_events() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/posts";
+
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/rec_1")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "rec_1", "title": "Cached"
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("filter", "published = true"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": 1, "total_pages": 1,
+            "items": [{ "id": "rec_1", "title": "Cached" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let event = json!({
+        "action": "update",
+        "collection": "posts",
+        "record": { "id": "rec_1", "title": "Changed" }
+    });
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("event: record\ndata: {event}\n\n")),
+        )
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let posts = client
+        .app("o1", "a1")
+        .records("posts")
+        .cached(CacheOptions::new().capacity(10));
+
+    assert_eq!(posts.get("rec_1").await.unwrap()["title"], "Cached");
+    assert_eq!(posts.get("rec_1").await.unwrap()["title"], "Cached");
+    let query = posts.query().filter("published = true");
+    assert_eq!(posts.list(query.clone()).await.unwrap().items.len(), 1);
+    assert_eq!(posts.list(query).await.unwrap().items.len(), 1);
+
+    let metrics = posts.metrics();
+    assert_eq!((metrics.hits, metrics.misses), (2, 2));
+    assert_eq!(posts.len(), 2);
+
+    // Entries cached before the subscription connected are dropped.
+    let invalidation = tokio::spawn(posts.realtime_invalidation().await.unwrap());
+    assert!(posts.is_empty());
+    assert_eq!(posts.metrics().invalidations, 2);
+
+    // The stream ends after one event; whatever is cached meanwhile is
+    // dropped by the event or when the subscription reconnects.
+    posts.get("rec_1").await.unwrap();
+    assert_eq!(posts.metrics().misses, 3);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !posts.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("cache was not invalidated after reconnecting");
+    invalidation.abort();
+}
+
+#[tokio::test]
+async fn replica_queues_local_writes_and_merges_conflicts_on_push() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/posts";
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": 2, "total_pages": 1,
+            "items": [
+                { "id": "p1", "title": "First", "views": 1, "updated": "2024-01-01T00:00:00Z" },
+                { "id": "p2", "title": "Second", "views": 7, "updated": "2024-01-01T00:00:00Z" }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    // Changed on the server after the local edit was queued.
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/p1")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "Remote", "views": 1, "updated": "2099-01-01T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/p1")))
+        .and(body_json(json!({ "views": 9 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "Remote", "views": 9, "updated": "2099-01-02T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(records))
+        .and(body_json(json!({ "title": "Offline", "views": 3 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p3", "title": "Offline", "views": 3, "updated": "2099-01-01T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let replica = client
+        .app("o1", "a1")
+        .replica(MemoryStore::new())
+        .collection("posts")
+        .conflict_policy(ConflictPolicy::FieldMerge);
+
+    replica.bootstrap().await.unwrap();
+    let popular = replica
+        .query("posts")
+        .filter("views > 2 || title ~ 'first'")
+        .sort("-views")
+        .all()
+        .unwrap();
+    let ids: Vec<_> = popular.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["p2", "p1"]);
+
+    replica
+        .update("posts", "p1", &json!({ "title": "Local", "views": 9 }))
+        .unwrap();
+    let created = replica
+        .create("posts", &json!({ "title": "Offline", "views": 3 }))
+        .unwrap();
+    let temp_id = created["id"].as_str().unwrap();
+    assert!(temp_id.starts_with("local-"));
+    assert_eq!(replica.pending_writes().unwrap().len(), 2);
+    assert_eq!(
+        replica.query("posts").filter("views >= 3").count().unwrap(),
+        3
+    );
+
+    let report = replica.push().await.unwrap();
+    assert_eq!(
+        report,
+        PushReport {
+            pushed: 2,
+            conflicts: 1,
+            dropped: 0
+        }
+    );
+    assert!(replica.pending_writes().unwrap().is_empty());
+    assert_eq!(
+        replica.get("posts", "p1").unwrap().unwrap()["title"],
+        "Remote"
+    );
+    assert!(replica.get("posts", temp_id).unwrap().is_none());
+    assert_eq!(
+        replica.get("posts", "p3").unwrap().unwrap()["title"],
+        "Offline"
+    );
+}
+
+#[tokio::test]
+async fn replica_keeps_local_changes_made_while_a_push_is_in_flight() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/posts";
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": 1, "total_pages": 1,
+            "items": [{ "id": "p1", "title": "First", "updated": "2024-01-01T00:00:00Z" }]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(records))
+        .and(body_json(json!({ "title": "Draft" })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(
+                    json!({ "id": "p2", "title": "Draft", "updated": "2024-01-02T00:00:00Z" }),
+                )
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/p2")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p2", "title": "Draft", "updated": "2024-01-02T00:00:00Z"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/p2")))
+        .and(body_json(json!({ "title": "Final" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p2", "title": "Final", "updated": "2024-01-03T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/p1")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "First", "updated": "2024-01-01T00:00:00Z"
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/p1")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "Second", "updated": "2024-01-02T00:00:00Z"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/p1")))
+        .and(body_json(json!({ "title": "Second" })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(
+                    json!({ "id": "p1", "title": "Second", "updated": "2024-01-02T00:00:00Z" }),
+                )
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/p1")))
+        .and(body_json(json!({ "title": "Third" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "Third", "updated": "2024-01-03T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let replica = client
+        .app("o1", "a1")
+        .replica(MemoryStore::new())
+        .collection("posts");
+    replica.bootstrap().await.unwrap();
+
+    // Edited while its create is in flight.
+    let created = replica
+        .create("posts", &json!({ "title": "Draft" }))
+        .unwrap();
+    let temp_id = created["id"].as_str().unwrap().to_string();
+    let (report, _) = tokio::join!(replica.push(), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        replica
+            .update("posts", &temp_id, &json!({ "title": "Final" }))
+            .unwrap();
+    });
+    assert_eq!(report.unwrap().pushed, 2);
+    assert!(replica.pending_writes().unwrap().is_empty());
+    assert_eq!(
+        replica.get("posts", "p2").unwrap().unwrap()["title"],
+        "Final"
+    );
+
+    // Edited while its update is in flight.
+    replica
+        .update("posts", "p1", &json!({ "title": "Second" }))
+        .unwrap();
+    let (report, _) = tokio::join!(replica.push(), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        replica
+            .update("posts", "p1", &json!({ "title": "Third" }))
+            .unwrap();
+    });
+    assert_eq!(
+        report.unwrap(),
+        PushReport {
+            pushed: 2,
+            conflicts: 0,
+            dropped: 0
+        }
+    );
+    assert!(replica.pending_writes().unwrap().is_empty());
+    assert_eq!(
+        replica.get("posts", "p1").unwrap().unwrap()["title"],
+        "Third"
+    );
+}
+
+#[tokio::test]
+async fn replica_pull_keeps_records_pushed_while_it_lists() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/posts";
+
+    // The list was taken before the create below reached the server.
+    Mock::given(method("GET"))
+        .and(path(records))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "page": 1, "per_page": 100, "total_items": 0, "total_pages": 1, "items": []
+                }))
+                .set_delay(Duration::from_millis(200)),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(records))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "p1", "title": "Draft", "updated": "2024-01-01T00:00:00Z"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let replica = client
+        .app("o1", "a1")
+        .replica(MemoryStore::new())
+        .collection("posts");
+    replica
+        .create("posts", &json!({ "title": "Draft" }))
+        .unwrap();
+
+    let (pulled, pushed) = tokio::join!(replica.bootstrap(), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        replica.push().await
+    });
+    pulled.unwrap();
+    assert_eq!(pushed.unwrap().pushed, 1);
+    assert_eq!(
+        replica.get("posts", "p1").unwrap().unwrap()["title"],
+        "Draft"
+    );
+}
+
+#[tokio::test]
+async fn replica_pulls_again_after_realtime_reconnects() {
+    let server = MockServer::start().await;
+    let event = json!({ "action": "create", "collection": "posts", "record": { "id": "p3" } });
+
+    // Each connection delivers one event and closes, so the subscription
+    // keeps reconnecting.
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("event: record\ndata: {event}\n\n")),
+        )
+        .mount(&server)
+        .await;
+    let list = |ids: &[&str]| {
+        let items: Vec<_> = ids.iter().map(|id| json!({ "id": id })).collect();
+        ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 100, "total_items": items.len(), "total_pages": 1,
+            "items": items
+        }))
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .respond_with(list(&["p1"]))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    // Created while the subscription was disconnected.
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .respond_with(list(&["p1", "p2"]))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let replica = client
+        .app("o1", "a1")
+        .replica(MemoryStore::new())
+        .collection("posts");
+    replica.bootstrap().await.unwrap();
+    assert!(replica.get("posts", "p2").unwrap().is_none());
+
+    let sync = tokio::spawn(replica.realtime_sync().await.unwrap());
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while replica.get("posts", "p2").unwrap().is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("replica was not pulled again after reconnecting");
+    sync.abort();
+}
+
+#[tokio::test]
+async fn export_and_import_collection_in_csv_and_ndjson() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/tasks";
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/collections/tasks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "col_1",
+            "name": "tasks",
+            "app_id": "a1",
+            "fields": [
+                { "name": "title", "type": "text", "required": true },
+                { "name": "priority", "type": "number" },
+                { "name": "labels", "type": "json" }
+            ],
+            "created": "2024-01-01T00:00:00Z",
+            "updated": "2024-01-01T00:00:00Z"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("sort", "id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 100, "total_items": 2, "total_pages": 1,
+            "items": [
+                { "id": "t1", "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z",
+                  "title": "Ship, soon", "priority": 2, "labels": ["a"] },
+                { "id": "t2", "created": "2024-01-01T00:00:00Z", "updated": "2024-01-01T00:00:00Z",
+                  "title": "Plan", "priority": null }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(records))
+        .and(body_json(
+            json!({ "title": "Ship", "priority": 2, "labels": ["a"] }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "t3" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let tasks = client.app("o1", "a1").records("tasks");
+
+    let mut csv = Vec::new();
+    let count = tasks
+        .export_collection(&mut csv, DataFormat::Csv)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "id,created,updated,title,priority,labels\n\
+         t1,2024-01-01T00:00:00Z,2024-01-01T00:00:00Z,\"Ship, soon\",2,\"[\"\"a\"\"]\"\n\
+         t2,2024-01-01T00:00:00Z,2024-01-01T00:00:00Z,Plan,,\n"
+    );
+
+    let mut ndjson = Vec::new();
+    tasks
+        .export_collection(&mut ndjson, DataFormat::Ndjson)
+        .await
+        .unwrap();
+    let lines: Vec<serde_json::Value> = String::from_utf8(ndjson)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["labels"], json!(["a"]));
+
+    let rejects =
+        std::env::temp_dir().join(format!("copepod-rejects-{}.ndjson", std::process::id()));
+    let input = "Name,priority,labels,created\n\
+                 Ship,2,\"[\"\"a\"\"]\",2024-01-01T00:00:00Z\n\
+                 ,9,,\n";
+    let report = tasks
+        .import_collection(
+            input.as_bytes(),
+            DataFormat::Csv,
+            &ImportOptions::new()
+                .map_column("Name", "title")
+                .reject_file(&rejects),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.succeeded.len(), 1);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].index, 1);
+    assert!(matches!(
+        report.failed[0].error,
+        CopepodError::Validation(_)
+    ));
+
+    let rejected: serde_json::Value =
+        serde_json::from_str(std::fs::read_to_string(&rejects).unwrap().trim()).unwrap();
+    std::fs::remove_file(&rejects).unwrap();
+    assert_eq!(rejected["row"], 1);
+    assert_eq!(rejected["data"], json!({ "priority": 9 }));
+}
+
+#[tokio::test]
+async fn export_collection_continues_after_a_short_page() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/tasks";
+
+    // The server caps pages at two records, below the 100 requested, and
+    // reports that more follow.
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("sort", "id"))
+        .and(query_param_is_missing("filter"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 2, "total_items": 3, "total_pages": 2,
+            "items": [{ "id": "t1", "title": "a" }, { "id": "t2", "title": "b" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("filter", "id > 't2'"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 2, "total_items": 1, "total_pages": 1,
+            "items": [{ "id": "t3", "title": "c" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let mut ndjson = Vec::new();
+    let count = client
+        .app("o1", "a1")
+        .records("tasks")
+        .export_collection(&mut ndjson, DataFormat::Ndjson)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+    let lines: Vec<serde_json::Value> = String::from_utf8(ndjson)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let ids: Vec<_> = lines.iter().map(|r| r["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["t1", "t2", "t3"]);
+}
+
+#[tokio::test]
+async fn seed_resolves_fixture_references_and_tears_down() {
+    let dir = std::env::temp_dir().join(format!("copepod-fixtures-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("users.yaml"), "alice:\n  name: Alice\n").unwrap();
+    std::fs::write(
+        dir.join("posts.json"),
+        r#"{ "hello": { "title": "Hello", "author": "@users.alice" } }"#,
+    )
+    .unwrap();
+    let fixtures = Fixtures::load(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(fixtures.len(), 2);
+
+    let alice = fixtures.record_id("users", "alice").unwrap();
+    let hello = fixtures.record_id("posts", "hello").unwrap();
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records";
+
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/users/{alice}")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "not found" })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("{records}/users")))
+        .and(body_json(json!({ "id": alice, "name": "Alice" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": alice })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // The post already exists from an earlier run, so it is updated in place.
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/posts/{hello}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": hello })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("{records}/posts/{hello}")))
+        .and(body_json(json!({ "title": "Hello", "author": alice })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": hello })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path(format!("{records}/posts/{hello}")))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(format!("{records}/users/{alice}")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "not found" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let app = client.app("o1", "a1");
+
+    let report = app.seed(&fixtures).await.unwrap();
+    assert_eq!((report.created, report.updated), (1, 1));
+    assert_eq!(report.ids["posts.hello"], hello);
+
+    assert_eq!(app.teardown(&fixtures).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn seed_fails_when_the_server_assigns_another_id() {
+    let mut fixtures = Fixtures::new();
+    fixtures.insert(
+        "users",
+        "alice",
+        json!({ "name": "Alice" }).as_object().unwrap().clone(),
+    );
+    let alice = fixtures.record_id("users", "alice").unwrap();
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/users";
+
+    Mock::given(method("GET"))
+        .and(path(format!("{records}/{alice}")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "not found" })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(records))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "generated" })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let err = client.app("o1", "a1").seed(&fixtures).await.unwrap_err();
+    assert!(
+        matches!(&err, CopepodError::Fixture(msg) if msg.contains("`generated`")),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn resilient_subscription_resumes_from_last_event_id() {
+    let server = MockServer::start().await;
+    let realtime = "/api/platform/orgs/o1/apps/a1/realtime";
+    let event =
+        |id: &str| json!({ "action": "create", "collection": "posts", "record": { "id": id } });
+
+    Mock::given(method("GET"))
+        .and(path(realtime))
+        .and(header("last-event-id", "1"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("id: 2\nevent: record\ndata: {}\n\n", event("p2"))),
+        )
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(realtime))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!(
+                    "retry: 5\nid: 1\nevent: record\ndata: {}\n\n",
+                    event("p1")
+                )),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(realtime))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let options = ReconnectOptions::new().initial_backoff(Duration::from_millis(1));
+    let events: Vec<_> = client
+        .subscribe_with_reconnect("o1", "a1", options)
+        .take(6)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let describe: Vec<String> = events
+        .iter()
+        .map(|event| match event {
+            SubscriptionEvent::State(state) => format!("{state:?}"),
+            SubscriptionEvent::Event(RealtimeEvent::Record(record)) => {
+                format!("record {}", record.record["id"])
+            }
+            other => format!("{other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        describe,
+        [
+            "Connecting",
+            "Connected",
+            "record \"p1\"",
+            "Reconnecting { attempt: 1, delay: 5ms }",
+            "Connected",
+            "record \"p2\"",
+        ]
+    );
+
+    // With no reconnect budget left, the stream closes once the server
+    // stops accepting connections.
+    let states: Vec<_> = client
+        .subscribe_with_reconnect(
+            "o1",
+            "a1",
+            ReconnectOptions::new()
+                .initial_backoff(Duration::from_millis(1))
+                .max_attempts(1),
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert!(matches!(
+        states.last(),
+        Some(SubscriptionEvent::State(ConnectionState::Closed))
+    ));
+}
+
+#[tokio::test]
+async fn collection_subscription_sends_targets_and_filters_locally() {
+    let server = MockServer::start().await;
+    let events = [
+        json!({ "action": "create", "collection": "comments", "record": { "id": "c1" } }),
+        json!({ "action": "create", "collection": "posts", "record": { "id": "p1", "published": false } }),
+        json!({ "action": "update", "collection": "posts", "record": { "id": "p2", "published": true } }),
+    ];
+    let body: String = events
+        .iter()
+        .map(|event| format!("event: record\ndata: {event}\n\n"))
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .and(query_param("collections", "posts"))
+        .and(query_param("filter", "published = true"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let posts = client.app("o1", "a1").records("posts");
+
+    assert!(posts.subscribe().filter("published =").stream().is_err());
+
+    let records: Vec<_> = posts
+        .subscribe()
+        .filter("published = true")
+        .stream()
+        .unwrap()
+        .filter_map(|event| async move {
+            match event.unwrap() {
+                SubscriptionEvent::Event(RealtimeEvent::Record(record)) => Some(record),
+                _ => None,
+            }
+        })
+        .take(1)
+        .collect()
+        .await;
+    assert_eq!(records[0].record["id"], "p2");
+}
+
+#[tokio::test]
+async fn subscribe_yields_typed_events_instead_of_errors() {
+    #[derive(Debug, Deserialize)]
+    struct Post {
+        title: String,
+    }
+
+    let server = MockServer::start().await;
+    let body = concat!(
+        "event: connect\ndata: {\"clientId\":\"cl1\"}\n\n",
+        "event: ping\ndata: \n\n",
+        "event: presence\ndata: joined\n\n",
+        "event: record\ndata: {\"action\":\"delete\",\"collection\":\"posts\",\"record\":{\"id\":\"p1\",\"title\":\"Hi\"}}\n\n",
+        "event: record\ndata: {\"action\":\"restore\",\"collection\":\"posts\",\"record\":{\"id\":\"p1\"}}\n\n",
+    );
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let events: Vec<_> = client
+        .subscribe("o1", "a1")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(events.len(), 5);
+    assert!(matches!(&events[0], RealtimeEvent::Connected { client_id } if client_id == "cl1"));
+    assert!(matches!(events[1], RealtimeEvent::Ping));
+    assert!(
+        matches!(&events[2], RealtimeEvent::Custom { event, data } if event == "presence" && data == "joined")
+    );
+    let record = events[3].as_record().unwrap();
+    assert_eq!(record.action, RecordAction::Delete);
+    let post: Record<Post> = record.decode().unwrap();
+    assert_eq!(post.id, "p1");
+    assert_eq!(post.title, "Hi");
+    let restored = events[4].as_record().unwrap();
+    assert_eq!(restored.action, RecordAction::Other("restore".into()));
+    assert_eq!(serde_json::to_value(&restored.action).unwrap(), "restore");
+}
+
+#[tokio::test]
+async fn live_query_applies_events_to_the_listed_records() {
+    let server = MockServer::start().await;
+    let events = [
+        json!({ "action": "create", "collection": "posts", "record": { "id": "p3", "views": 4 } }),
+        json!({ "action": "update", "collection": "posts", "record": { "id": "p1", "views": 1 } }),
+        json!({ "action": "create", "collection": "posts", "record": { "id": "p4", "views": 9 } }),
+    ];
+    let body: String = events
+        .iter()
+        .map(|event| format!("event: record\ndata: {event}\n\n"))
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .and(query_param("collections", "posts"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .and(query_param("filter", "views > 2"))
+        .and(query_param("sort", "views"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1,
+            "per_page": 30,
+            "total_items": 2,
+            "total_pages": 1,
+            "items": [{ "id": "p1", "views": 3 }, { "id": "p2", "views": 5 }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let mut live = client
+        .app("o1", "a1")
+        .records("posts")
+        .query()
+        .filter("views > 2")
+        .sort("views")
+        .live()
+        .await
+        .unwrap();
+    let watch = live.watch();
+    assert_eq!(live.snapshot().len(), 2);
+
+    let ids = |records: &[serde_json::Value]| -> Vec<String> {
+        records
+            .iter()
+            .map(|r| r["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Inserted { index: 1 }]);
+    assert_eq!(ids(&change.snapshot), ["p1", "p3", "p2"]);
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Removed { index: 0 }]);
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Inserted { index: 2 }]);
+    assert_eq!(ids(&watch.borrow()), ["p3", "p2", "p4"]);
+}
+
+#[tokio::test]
+async fn live_query_lists_again_after_reconnecting() {
+    let server = MockServer::start().await;
+    let event = json!({ "action": "create", "collection": "posts", "record": { "id": "p3" } });
+
+    // Each connection delivers one event and closes, so the subscription
+    // keeps reconnecting.
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(format!("event: record\ndata: {event}\n\n")),
+        )
+        .mount(&server)
+        .await;
+    let list = |ids: &[&str]| {
+        let items: Vec<_> = ids.iter().map(|id| json!({ "id": id })).collect();
+        ResponseTemplate::new(200).set_body_json(json!({
+            "page": 1, "per_page": 30, "total_items": items.len(), "total_pages": 1,
+            "items": items
+        }))
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .respond_with(list(&["p1"]))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/posts"))
+        .respond_with(list(&["p1", "p2"]))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let mut live = client
+        .app("o1", "a1")
+        .records("posts")
+        .query()
+        .live()
+        .await
+        .unwrap();
+    let ids = |change: &copepod_sdk::live::LiveChange<serde_json::Value>| -> Vec<String> {
+        change
+            .snapshot
+            .iter()
+            .map(|r| r["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Inserted { index: 1 }]);
+    assert_eq!(ids(&change), ["p1", "p3"]);
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Reset]);
+    assert_eq!(ids(&change), ["p1", "p2"]);
+    let change = live.next().await.unwrap().unwrap();
+    assert_eq!(change.diff, [LiveDiff::Inserted { index: 2 }]);
+    assert_eq!(live.snapshot().len(), 3);
+}
+
+#[tokio::test]
+async fn live_query_lists_every_page_unless_per_page_is_set() {
+    let server = MockServer::start().await;
+    let records = "/api/platform/orgs/o1/apps/a1/records/posts";
+
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(""),
+        )
+        .mount(&server)
+        .await;
+    let page = |page: u32, id: &str| {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "page": page, "per_page": 1, "total_items": 2, "total_pages": 2,
+            "items": [{ "id": id }]
+        }))
+    };
+    // A query without `page` asks for the first one.
+    Mock::given(method("GET"))
+        .and(path(records))
+        .and(query_param("page", "2"))
+        .respond_with(page(2, "p2"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(records))
+        .respond_with(page(1, "p1"))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let query = || client.app("o1", "a1").records("posts").query();
+
+    let live = query().live().await.unwrap();
+    assert_eq!(live.snapshot().len(), 2);
+    let live = query().per_page(1).live().await.unwrap();
+    assert_eq!(live.snapshot().len(), 1);
+    let err = query().page(2).live().await.err().unwrap();
+    assert!(matches!(err, CopepodError::Query(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn realtime_hub_shares_one_connection_and_reports_lag() {
+    let server = MockServer::start().await;
+    let body: String = [("posts", "p1"), ("comments", "c1"), ("posts", "p2")]
+        .iter()
+        .map(|(collection, id)| {
+            let event =
+                json!({ "action": "create", "collection": collection, "record": { "id": id } });
+            format!("event: record\ndata: {event}\n\n")
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let hub = client.realtime_hub(
+        HubOptions::new()
+            .capacity(2)
+            .reconnect(ReconnectOptions::new().max_attempts(0)),
+    );
+    let posts = hub
+        .subscribe("o1", "a1")
+        .collection("posts")
+        .stream()
+        .unwrap();
+    let everything = hub.subscribe("o1", "a1").stream().unwrap();
+    assert_eq!(hub.connections(), 1);
+    assert!(hub.subscribe("o1", "a1").filter("(").stream().is_err());
+
+    let describe = |event: SubscriptionEvent| match event {
+        SubscriptionEvent::Event(RealtimeEvent::Record(record)) => {
+            format!("record {}", record.record["id"].as_str().unwrap())
+        }
+        other => format!("{other:?}"),
+    };
+    let posts: Vec<_> = posts.map(|e| describe(e.unwrap())).collect().await;
+    assert_eq!(
+        posts,
+        [
+            "State(Connecting)",
+            "State(Connected)",
+            "record p1",
+            "record p2",
+            "State(Closed)"
+        ]
+    );
+
+    // The second subscriber was not polled while six items were broadcast,
+    // so only the last two are left for it.
+    let everything: Vec<_> = everything.map(|e| describe(e.unwrap())).collect().await;
+    assert_eq!(
+        everything,
+        ["Lagged { skipped: 4 }", "record p2", "State(Closed)"]
+    );
+    assert_eq!(hub.connections(), 0);
+}
+
+#[tokio::test]
+#[allow(clippy::result_large_err)] // the handshake callback signature is fixed
+async fn websocket_subscription_goes_through_the_http_client_proxy() {
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+    use tokio_tungstenite::tungstenite::Message;
+
+    // The "proxy" answers the upgrade itself, so the test only passes if
+    // the request was sent to it rather than to the unresolvable host.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut seen = (String::new(), None);
+        let mut socket =
+            tokio_tungstenite::accept_hdr_async(tcp, |req: &Request, res: Response| {
+                let app = req
+                    .headers()
+                    .get("x-app")
+                    .map(|v| v.to_str().unwrap().to_string());
+                seen = (req.uri().to_string(), app);
+                Ok(res)
+            })
+            .await
+            .unwrap();
+        let connect = json!({ "event": "connect", "data": { "clientId": "ws1" } });
+        socket
+            .send(Message::text(connect.to_string()))
+            .await
+            .unwrap();
+        seen
+    });
+
+    let http = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{addr}")).unwrap())
+        .default_headers(
+            [(
+                reqwest::header::HeaderName::from_static("x-app"),
+                reqwest::header::HeaderValue::from_static("copepod-tests"),
+            )]
+            .into_iter()
+            .collect(),
+        )
+        .build()
+        .unwrap();
+    let client = CopepodClient::builder()
+        .base_url("http://copepod.invalid")
+        .token("tok")
+        .auto_refresh(false)
+        .http_client(http)
+        .realtime_transport(RealtimeTransport::WebSocket)
+        .build()
+        .unwrap();
+    let mut events = Box::pin(client.subscribe("o1", "a1").await.unwrap());
+
+    match events.next().await.unwrap().unwrap() {
+        RealtimeEvent::Connected { client_id } => assert_eq!(client_id, "ws1"),
+        other => panic!("unexpected {other:?}"),
+    }
+    let (uri, header) = server.await.unwrap();
+    assert_eq!(
+        uri,
+        "http://copepod.invalid/api/platform/orgs/o1/apps/a1/realtime?access_token=tok"
+    );
+    assert_eq!(header.as_deref(), Some("copepod-tests"));
+}
+
+#[tokio::test]
+#[allow(clippy::result_large_err)] // the handshake callback signature is fixed
+async fn websocket_subscription_keeps_alive_and_retargets_without_reconnecting() {
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut uri = String::new();
+        let mut socket =
+            tokio_tungstenite::accept_hdr_async(tcp, |req: &Request, res: Response| {
+                uri = req.uri().to_string();
+                Ok(res)
+            })
+            .await
+            .unwrap();
+        let frame = |event: &str, data: serde_json::Value| {
+            Message::text(json!({ "event": event, "data": data }).to_string())
+        };
+        let record = |collection: &str, id: &str| {
+            frame(
+                "record",
+                json!({ "action": "create", "collection": collection, "record": { "id": id } }),
+            )
+        };
+        socket
+            .send(frame("connect", json!({ "clientId": "ws1" })))
+            .await
+            .unwrap();
+        socket.send(record("posts", "p1")).await.unwrap();
+
+        // Answer the subscribe message once a keepalive ping has arrived too.
+        let (mut pinged, mut subscribe) = (false, None);
+        while let Some(Ok(message)) = socket.next().await {
+            match message {
+                Message::Ping(_) => pinged = true,
+                Message::Text(text) => subscribe = Some(serde_json::from_str(&text).unwrap()),
+                _ => continue,
+            }
+            if pinged && subscribe.is_some() {
+                socket.send(record("posts", "p2")).await.unwrap();
+                socket.send(record("tags", "t1")).await.unwrap();
+                break;
+            }
+        }
+        (uri, pinged, subscribe)
+    });
+
+    let client = CopepodClient::builder()
+        .base_url(format!("http://{addr}"))
+        .token("tok")
+        .auto_refresh(false)
+        .realtime_transport(RealtimeTransport::WebSocket)
+        .build()
+        .unwrap();
+    let mut subscription = client
+        .subscription("o1", "a1")
+        .collection("posts")
+        .reconnect(
+            ReconnectOptions::new()
+                .keepalive(Duration::from_millis(50))
+                .max_attempts(0),
+        )
+        .open()
+        .unwrap();
+
+    let mut seen = Vec::new();
+    while let Some(event) = subscription.next().await {
+        match event.unwrap() {
+            SubscriptionEvent::Event(RealtimeEvent::Connected { client_id }) => {
+                seen.push(format!("connected {client_id}"));
+            }
+            SubscriptionEvent::Event(RealtimeEvent::Record(record)) => {
+                let id = record.record["id"].as_str().unwrap().to_string();
+                seen.push(format!("record {id}"));
+                if id == "p1" {
+                    subscription
+                        .update(SubscriptionTargets::new().collection("tags"))
+                        .unwrap();
+                } else {
+                    break;
+                }
+            }
+            SubscriptionEvent::State(state) => seen.push(format!("{state:?}")),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+    drop(subscription);
+
+    assert_eq!(
+        seen,
+        [
+            "Connecting",
+            "Connected",
+            "connected ws1",
+            "record p1",
+            "record t1"
+        ]
+    );
+    let (uri, pinged, subscribe): (_, _, Option<serde_json::Value>) = server.await.unwrap();
+    assert_eq!(
+        uri,
+        "/api/platform/orgs/o1/apps/a1/realtime?access_token=tok&collections=posts"
+    );
+    assert!(pinged);
+    assert_eq!(
+        subscribe,
+        Some(json!({ "type": "subscribe", "collections": "tags" }))
+    );
+}
+
+fn auth_response(token: &str, refresh_token: &str) -> serde_json::Value {
+    json!({
+        "token": token,
+        "refresh_token": refresh_token,
+        "user": {
+            "id": "u1",
+            "email": "user@example.com",
+            "created": "2024-01-01T00:00:00Z",
+            "updated": "2024-01-01T00:00:00Z"
+        }
+    })
+}
+
+fn describe_subscription_event(event: copepod_sdk::error::Result<SubscriptionEvent>) -> String {
+    match event {
+        Ok(SubscriptionEvent::Event(RealtimeEvent::Record(record))) => {
+            format!("record {}", record.record["id"].as_str().unwrap())
+        }
+        Ok(SubscriptionEvent::State(ConnectionState::Reconnecting { attempt, .. })) => {
+            format!("Reconnecting {attempt}")
+        }
+        Ok(SubscriptionEvent::State(state)) => format!("{state:?}"),
+        Ok(other) => format!("{other:?}"),
+        Err(CopepodError::Auth(message)) => format!("auth error: {message}"),
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn realtime_refreshes_rejected_token_and_resumes() {
+    let server = MockServer::start().await;
+    let sse = |id: &str| {
+        let event = json!({ "action": "create", "collection": "posts", "record": { "id": id } });
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_string(format!("id: {id}\nevent: record\ndata: {event}\n\n"))
+    };
+    let realtime =
+        || Mock::given(method("GET")).and(path("/api/platform/orgs/o1/apps/a1/realtime"));
+    realtime()
+        .and(query_param("access_token", "old"))
+        .and(header("Last-Event-ID", "p1"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+    realtime()
+        .and(query_param("access_token", "old"))
+        .respond_with(sse("p1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    realtime()
+        .and(query_param("access_token", "new"))
+        .and(header("Last-Event-ID", "p1"))
+        .respond_with(sse("p2"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/platform/auth/refresh"))
+        .and(body_json(json!({ "refresh_token": "r1" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(auth_response("new", "r2")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("old")
+        .refresh_token("r1")
+        .build()
+        .unwrap();
+    let events: Vec<_> = client
+        .subscribe_with_reconnect(
+            "o1",
+            "a1",
+            ReconnectOptions::new().initial_backoff(Duration::from_millis(1)),
+        )
+        .map(describe_subscription_event)
+        .take(7)
+        .collect()
+        .await;
+    assert_eq!(
+        events,
+        [
+            "Connecting",
+            "Connected",
+            "record p1",
+            "Reconnecting 1",
+            "Connected",
+            "record p2",
+            "Reconnecting 1"
+        ]
+    );
+    assert_eq!(client.token_store().get().await.unwrap().token, "new");
+}
+
+#[tokio::test]
+async fn realtime_reports_auth_failure_when_token_cannot_be_refreshed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/realtime"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("old")
+        .build()
+        .unwrap();
+    let events: Vec<_> = client
+        .subscribe_with_reconnect("o1", "a1", ReconnectOptions::new())
+        .map(describe_subscription_event)
+        .collect()
+        .await;
+    assert_eq!(events.len(), 3, "{events:?}");
+    assert_eq!(events[0], "Connecting");
+    assert!(
+        events[1].starts_with("auth error:") && events[1].contains("No refresh token"),
+        "{events:?}"
+    );
+    assert_eq!(events[2], "Closed");
+}
+
+fn expiring_jwt() -> String {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    // Within the 60 second refresh threshold.
+    let exp = chrono::Utc::now().timestamp() + 30;
+    let claims = URL_SAFE_NO_PAD.encode(json!({ "exp": exp }).to_string());
+    format!("eyJhbGciOiJIUzI1NiJ9.{claims}.sig")
+}
+
+#[tokio::test]
+async fn app_user_session_refreshes_at_its_collection_endpoint() {
+    let server = MockServer::start().await;
+    let auth = "/api/platform/orgs/o1/apps/a1/auth/users";
+    Mock::given(method("POST"))
+        .and(path(format!("{auth}/auth-with-password")))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(auth_response(&expiring_jwt(), "r1")),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("{auth}/auth-refresh")))
+        .and(body_json(json!({ "refresh_token": "r1" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(auth_response("new", "r2")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/platform/auth/refresh"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes/n1"))
+        .and(header("Authorization", "Bearer new"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "n1" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .build()
+        .unwrap();
+    let login = client
+        .app_login("o1", "a1", "users", "ada@example.com", "secret")
+        .await
+        .unwrap();
+    assert!(matches!(login, AppLoginResult::Success(_)));
+    client
+        .records("o1", "a1", "notes")
+        .get_one("n1")
+        .await
+        .unwrap();
+    let pair = client.token_store().get().await.unwrap();
+    assert_eq!(
+        (pair.token.as_str(), pair.refresh_token.as_str()),
+        ("new", "r2")
+    );
+}
+
+#[tokio::test]
+async fn concurrent_requests_share_one_token_refresh() {
+    let server = MockServer::start().await;
+    // The delay keeps the first refresh in flight while the second request
+    // finds the token expiring too.
+    Mock::given(method("POST"))
+        .and(path("/api/platform/auth/refresh"))
+        .and(body_json(json!({ "refresh_token": "r1" })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(auth_response("new", "r2"))
+                .set_delay(Duration::from_millis(100)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes/n1"))
+        .and(header("Authorization", "Bearer new"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "n1" })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token(expiring_jwt())
+        .refresh_token("r1")
+        .build()
+        .unwrap();
+    let get = || client.records("o1", "a1", "notes").get_one("n1");
+    let (first, second) = tokio::join!(get(), get());
+    first.unwrap();
+    second.unwrap();
+    let pair = client.token_store().get().await.unwrap();
+    assert_eq!(
+        (pair.token.as_str(), pair.refresh_token.as_str()),
+        ("new", "r2")
+    );
+}
+
+#[tokio::test]
+async fn token_only_client_keeps_using_an_expiring_token() {
+    let server = MockServer::start().await;
+    let token = expiring_jwt();
+    Mock::given(method("GET"))
+        .and(path("/api/platform/orgs/o1/apps/a1/records/notes/n1"))
+        .and(header("Authorization", format!("Bearer {token}").as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "n1" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token(&token)
+        .build()
+        .unwrap();
+    client
+        .records("o1", "a1", "notes")
+        .get_one("n1")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn realtime_rotates_token_before_expiry_without_a_gap() {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let jwt = |exp: i64| {
+        let claims = URL_SAFE_NO_PAD.encode(json!({ "exp": exp }).to_string());
+        format!("eyJhbGciOiJIUzI1NiJ9.{claims}.sig")
+    };
+    let now = chrono::Utc::now().timestamp();
+    // Due for refresh one to two seconds from now, 60 seconds before it
+    // expires.
+    let old_token = jwt(now + 62);
+    let new_token = jwt(now + 3600);
+
+    // A stand-in server that keeps SSE connections open, which wiremock
+    // can't do.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (seen_tx, mut seen) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let refreshed = new_token.clone();
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        for id in ["p1", "p2"] {
+            loop {
+                let (mut tcp, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let head = loop {
+                    let n = tcp.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length: ")
+                                    .map(str::to_string)
+                            })
+                            .map_or(0, |l| l.trim().parse::<usize>().unwrap());
+                        if request.len() >= end + 4 + length {
+                            break text[..end].to_lowercase();
+                        }
+                    }
+                };
+                let line = head.lines().next().unwrap().to_string();
+                let resume = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("last-event-id: "))
+                    .unwrap_or("-")
+                    .to_string();
+                seen_tx.send(format!("{line} resume={resume}")).unwrap();
+
+                if line.starts_with("post /api/platform/auth/refresh") {
+                    let body = auth_response(&refreshed, "r2").to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    tcp.write_all(response.as_bytes()).await.unwrap();
+                    continue;
+                }
+                let event =
+                    json!({ "action": "create", "collection": "posts", "record": { "id": id } });
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\nid: {id}\nevent: record\ndata: {event}\n\n"
+                );
+                tcp.write_all(response.as_bytes()).await.unwrap();
+                open.push(tcp);
+                break;
+            }
+        }
+        std::future::pending::<()>().await;
+    });
+
+    let client = CopepodClient::builder()
+        .base_url(format!("http://{addr}"))
+        .token(old_token.clone())
+        .refresh_token("r1")
+        .build()
+        .unwrap();
+    let events: Vec<_> = client
+        .subscribe_with_reconnect("o1", "a1", ReconnectOptions::new())
+        .map(describe_subscription_event)
+        .take(4)
+        .collect()
+        .await;
+    assert_eq!(
+        events,
+        ["Connecting", "Connected", "record p1", "record p2"]
+    );
+
+    let mut requests = Vec::new();
+    while let Ok(request) = seen.try_recv() {
+        requests.push(request);
+    }
+    let realtime = |token: &str, resume: &str| {
+        format!(
+            "get /api/platform/orgs/o1/apps/a1/realtime?access_token={} http/1.1 resume={resume}",
+            token.to_lowercase()
+        )
+    };
+    assert_eq!(
+        requests,
+        [
+            realtime(&old_token, "-"),
+            "post /api/platform/auth/refresh http/1.1 resume=-".to_string(),
+            realtime(&new_token, "p1"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn follow_deployment_logs_streams_then_polls_without_repeating_lines() {
+    let server = MockServer::start().await;
+    let logs_path = "/api/platform/orgs/o1/deployments/d1/logs";
+    let streamed = [
+        json!({ "timestamp": "2024-05-01T12:00:00Z", "replica": "web-1", "container": "app", "message": "a" }),
+        json!({ "timestamp": "2024-05-01T12:00:01Z", "replica": "web-1", "container": "app", "message": "b" }),
+    ];
+    let body: String = streamed
+        .iter()
+        .map(|line| format!("event: ping\ndata: \n\nevent: log\ndata: {line}\n\n"))
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path(logs_path))
+        .and(query_param("follow", "true"))
+        .and(query_param("tail", "50"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(logs_path))
+        .and(query_param("since", "2024-05-01T12:00:01Z"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "lines": [
+                "[pod/web-1/app] 2024-05-01T12:00:01Z b",
+                "[pod/web-1/app] 2024-05-01T12:00:02Z c",
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let client = CopepodClient::builder()
+        .base_url(server.uri())
+        .token("tok")
+        .auto_refresh(false)
+        .build()
+        .unwrap();
+    let options = LogFollowOptions::new()
+        .tail(50)
+        .poll_interval(Duration::from_millis(20));
+    let lines: Vec<_> = client
+        .follow_deployment_logs("o1", "d1", options)
+        .take(3)
+        .map(|line| line.unwrap())
+        .collect()
+        .await;
+
+    let messages: Vec<_> = lines.iter().map(|l| l.message.as_str()).collect();
+    assert_eq!(messages, ["a", "b", "c"]);
+    assert_eq!(lines[2].replica.as_deref(), Some("web-1"));
+    assert_eq!(lines[2].container.as_deref(), Some("app"));
+    assert_eq!(
+        lines[2].timestamp.unwrap().to_rfc3339(),
+        "2024-05-01T12:00:02+00:00"
+    );
+}